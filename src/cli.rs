@@ -3,12 +3,15 @@
 //! Defines the command-line interface for ulm including subcommands
 //! and argument handling.
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 /// Command-line arguments for ulm.
 ///
 /// ulm supports two modes:
-/// - Subcommand mode: `ulm setup` or `ulm update`
+/// - Subcommand mode: `ulm setup`, `ulm update`, `ulm index export <file>` or
+///   `ulm prompt show <query>`
 /// - Query mode: `ulm "find large files"`
 #[derive(Parser, Debug)]
 #[command(name = "ulm")]
@@ -23,10 +26,16 @@ use clap::{Parser, Subcommand};
     thousands of powerful but cryptic Unix tools available on your system."
 )]
 pub struct Args {
-    /// Subcommand to execute (setup, update).
+    /// Subcommand to execute (setup, update, clean, index, prompt).
     #[command(subcommand)]
     pub command: Option<Commands>,
 
+    /// Random seed for the LLM, to reproduce suggestions.
+    ///
+    /// Overrides `seed` in the `[models.options]` config table.
+    #[arg(long)]
+    pub seed: Option<i64>,
+
     /// Natural language query describing what you want to do.
     ///
     /// Example: `ulm "find large files in current directory"`
@@ -57,6 +66,59 @@ pub enum Commands {
     /// Deletes the manpage index and configuration file.
     /// Useful for starting fresh or uninstalling.
     Clean,
+
+    /// Export or import the manpage index.
+    ///
+    /// Bundles let you build the index once and copy it to other
+    /// machines instead of re-embedding every manpage.
+    Index {
+        /// Index operation to run.
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+
+    /// Inspect the prompt sent to the LLM.
+    ///
+    /// The prompt is rendered from the built-in template, or from
+    /// `prompt.toml` in the config directory if it exists.
+    Prompt {
+        /// Prompt operation to run.
+        #[command(subcommand)]
+        command: PromptCommands,
+    },
+}
+
+/// Subcommands of `ulm index`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum IndexCommands {
+    /// Write the index to a portable bundle file.
+    Export {
+        /// Path of the bundle file to write.
+        file: PathBuf,
+    },
+
+    /// Replace the index with a bundle file and refresh it.
+    ///
+    /// The bundle must have vectors for the configured embedding model.
+    /// Manpages that differ on this machine are re-embedded.
+    Import {
+        /// Path of the bundle file to read.
+        file: PathBuf,
+    },
+}
+
+/// Subcommands of `ulm prompt`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum PromptCommands {
+    /// Print the fully rendered prompt for a query.
+    ///
+    /// Runs the search and fits the prompt into the model's context
+    /// window like a query, but doesn't call the LLM.
+    Show {
+        /// Natural language query to render the prompt for.
+        #[arg(required = true, trailing_var_arg = true)]
+        query: Vec<String>,
+    },
 }
 
 impl Args {
@@ -0,0 +1,348 @@
+//! Portable index bundles for provisioning other machines.
+//!
+//! A bundle is a compacted copy of `index.db` (via `VACUUM INTO`) with an
+//! extra `bundle_info` table recording the schema version. The vectors of
+//! every embedding model and the file records in `indexed_files` travel
+//! with it, so an imported index can be refreshed incrementally.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use tracing::info;
+
+use super::registry;
+use super::schema::{self, SCHEMA_VERSION};
+use super::{get_database_path, open_connection, open_index, remove_database_files, sibling_path};
+
+/// Metadata stored in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleInfo {
+    /// Embedding models the bundle has vectors for.
+    pub embedding_models: Vec<String>,
+    /// Schema version of the exported index.
+    pub schema_version: i32,
+}
+
+/// Exports the index into a bundle file, replacing it if it exists.
+///
+/// Returns the metadata of the written bundle.
+///
+/// # Errors
+///
+/// Returns an error if the index doesn't exist or the bundle cannot be written.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn export_index(dest: &Path) -> Result<BundleInfo> {
+    let db_path = get_database_path()?;
+
+    info!(path = %dest.display(), "Exporting index");
+
+    export_to(&db_path, dest)
+}
+
+/// Reads the metadata of a bundle file.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a ulm index bundle.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn read_bundle_info(bundle: &Path) -> Result<BundleInfo> {
+    read_info(bundle)
+}
+
+/// Replaces the index with the contents of a bundle file.
+///
+/// The imported index is migrated to the current schema before it
+/// replaces `index.db`.
+///
+/// # Errors
+///
+/// Returns an error if the bundle cannot be read or is incompatible.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn import_index(bundle: &Path) -> Result<BundleInfo> {
+    let db_path = get_database_path()?;
+
+    info!(path = %bundle.display(), "Importing index");
+
+    import_from(bundle, &db_path)
+}
+
+/// Writes a compacted copy of the index with bundle metadata to `dest`.
+///
+/// The bundle is written to a temporary file next to `dest`, which is
+/// removed if the export fails.
+fn export_to(index_path: &Path, dest: &Path) -> Result<BundleInfo> {
+    if !index_path.exists() {
+        anyhow::bail!("Index not found. Please run 'ulm setup' first.");
+    }
+
+    let tmp = sibling_path(dest, "tmp");
+    remove_database_files(&tmp)?;
+
+    let result = write_bundle(index_path, &tmp).and_then(|info| {
+        fs::rename(&tmp, dest)
+            .with_context(|| format!("Failed to write bundle: {}", dest.display()))?;
+        Ok(info)
+    });
+
+    if result.is_err() {
+        let _ = remove_database_files(&tmp);
+    }
+
+    result
+}
+
+/// Writes the bundle of the index at `index_path` to `path`.
+fn write_bundle(index_path: &Path, path: &Path) -> Result<BundleInfo> {
+    let conn = open_index(index_path)?;
+    vacuum_into(&conn, path)?;
+    drop(conn);
+
+    let bundle = open_connection(path)?;
+    let info = BundleInfo {
+        embedding_models: registry::model_names(&bundle)?,
+        schema_version: SCHEMA_VERSION,
+    };
+    write_info(&bundle, &info)?;
+
+    Ok(info)
+}
+
+/// Copies a bundle over the index at `index_path` and returns its metadata.
+fn import_from(bundle: &Path, index_path: &Path) -> Result<BundleInfo> {
+    let info = read_info(bundle)?;
+
+    if info.schema_version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "Bundle was exported by a newer version of ulm (schema {}, supported {}).",
+            info.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+
+    let tmp = sibling_path(index_path, "import");
+    remove_database_files(&tmp)?;
+
+    let result = copy_bundle(bundle, &tmp).and_then(|()| {
+        fs::rename(&tmp, index_path)
+            .with_context(|| format!("Failed to replace index: {}", index_path.display()))
+    });
+
+    if result.is_err() {
+        let _ = remove_database_files(&tmp);
+    }
+
+    result.map(|()| info)
+}
+
+/// Copies a bundle to `path` as an index at the current schema version.
+fn copy_bundle(bundle: &Path, path: &Path) -> Result<()> {
+    let source = open_connection(bundle)?;
+    vacuum_into(&source, path)?;
+    drop(source);
+
+    let mut conn = open_connection(path)?;
+    conn.execute("DROP TABLE bundle_info", [])
+        .context("Failed to remove bundle metadata")?;
+    schema::migrate(&mut conn)
+}
+
+/// Reads the `bundle_info` table of a bundle.
+fn read_info(bundle: &Path) -> Result<BundleInfo> {
+    if !bundle.exists() {
+        anyhow::bail!("Bundle not found: {}", bundle.display());
+    }
+
+    let conn = open_connection(bundle)?;
+    if !schema::table_exists(&conn, "bundle_info").unwrap_or(false) {
+        anyhow::bail!("Not a ulm index bundle: {}", bundle.display());
+    }
+
+    let value = |key: &str| -> Result<Option<String>> {
+        conn.query_row(
+            "SELECT value FROM bundle_info WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        )
+        .optional()
+        .with_context(|| format!("Failed to read bundle field '{key}'"))
+    };
+
+    let schema_version = value("schema_version")?
+        .context("Bundle has no schema version")?
+        .parse()
+        .context("Invalid schema version in bundle")?;
+
+    let embedding_models = if schema::table_exists(&conn, "embedding_models")? {
+        registry::model_names(&conn)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(BundleInfo {
+        embedding_models,
+        schema_version,
+    })
+}
+
+/// Writes the `bundle_info` table.
+fn write_info(conn: &Connection, info: &BundleInfo) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE bundle_info (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+        [],
+    )
+    .context("Failed to create bundle metadata table")?;
+
+    conn.execute(
+        "INSERT INTO bundle_info (key, value) VALUES ('schema_version', ?1)",
+        [info.schema_version.to_string()],
+    )
+    .context("Failed to write bundle metadata")?;
+
+    Ok(())
+}
+
+/// Writes a compacted copy of the database to `dest`.
+fn vacuum_into(conn: &Connection, dest: &Path) -> Result<()> {
+    conn.execute("VACUUM INTO ?1", [dest.to_string_lossy()])
+        .with_context(|| format!("Failed to copy database to {}", dest.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::rebuild_tables;
+    use crate::db::tests::{create_test_entries, TEST_MODEL};
+    use crate::setup::{FileRecord, VectorSettings};
+    use tempfile::TempDir;
+
+    fn test_info() -> BundleInfo {
+        BundleInfo {
+            embedding_models: vec![TEST_MODEL.to_string()],
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source.db");
+        let bundle = temp.path().join("index.ulm");
+        let target = temp.path().join("target.db");
+
+        let entries = create_test_entries(3);
+        let record = FileRecord {
+            source_path: "/usr/share/man/man1/tool0.1.gz".to_string(),
+            hash: "abc123".to_string(),
+            mtime: None,
+            embedding_model: "nomic-embed-text".to_string(),
+            indexed_at: 0,
+        };
+        let mut conn = open_connection(&source).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            std::slice::from_ref(&record),
+        )
+        .unwrap();
+        drop(conn);
+
+        assert_eq!(export_to(&source, &bundle).unwrap(), test_info());
+        assert_eq!(read_info(&bundle).unwrap(), test_info());
+
+        let info = import_from(&bundle, &target).unwrap();
+        assert_eq!(info, test_info());
+
+        let conn = open_connection(&target).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM manpages", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+        assert!(!schema::table_exists(&conn, "bundle_info").unwrap());
+        assert_eq!(crate::db::query_file_records(&conn).unwrap(), vec![record]);
+    }
+
+    #[test]
+    fn test_read_info_rejects_plain_database() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db");
+        let mut conn = open_connection(&path).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(1),
+            &[],
+        )
+        .unwrap();
+        drop(conn);
+
+        let err = read_info(&path).unwrap_err();
+        assert!(err.to_string().contains("Not a ulm index bundle"));
+    }
+
+    #[test]
+    fn test_import_rejects_newer_schema() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source.db");
+        let bundle = temp.path().join("index.ulm");
+
+        let mut conn = open_connection(&source).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(1),
+            &[],
+        )
+        .unwrap();
+        drop(conn);
+
+        export_to(&source, &bundle).unwrap();
+        let conn = open_connection(&bundle).unwrap();
+        conn.execute(
+            "UPDATE bundle_info SET value = ?1 WHERE key = 'schema_version'",
+            [(SCHEMA_VERSION + 1).to_string()],
+        )
+        .unwrap();
+        drop(conn);
+
+        let err = import_from(&bundle, &temp.path().join("target.db")).unwrap_err();
+        assert!(err.to_string().contains("newer version"));
+    }
+
+    #[test]
+    fn test_failed_export_and_import_remove_temporary_files() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source.db");
+        let bundle = temp.path().join("index.ulm");
+
+        let mut conn = open_connection(&source).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(1),
+            &[],
+        )
+        .unwrap();
+        drop(conn);
+
+        // A directory in place of the destination makes the final rename fail
+        let blocked = temp.path().join("blocked.ulm");
+        fs::create_dir(&blocked).unwrap();
+        assert!(export_to(&source, &blocked).is_err());
+        assert!(!sibling_path(&blocked, "tmp").exists());
+
+        export_to(&source, &bundle).unwrap();
+        let blocked = temp.path().join("blocked.db");
+        fs::create_dir(&blocked).unwrap();
+        assert!(import_from(&bundle, &blocked).is_err());
+        assert!(!sibling_path(&blocked, "import").exists());
+    }
+}
@@ -0,0 +1,210 @@
+//! Lock file that keeps two index updates from running at once.
+//!
+//! The lock is an `index.db.lock` file holding the PID of its owner. The
+//! PID is written to a file of its own first, which is then hard-linked into
+//! place, so the lock file never exists without its PID. A lock left behind
+//! by a process that no longer runs is treated as stale and replaced.
+//!
+//! Two processes can find the same stale lock. Each moves it aside under a
+//! name of its own before removing it, and puts back a lock that turns out
+//! to be held by a running process, so one can't remove the other's fresh
+//! lock. The lock is only removed on drop while it still holds our PID.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sysinfo::{Pid, ProcessesToUpdate, System};
+use tracing::{debug, warn};
+
+use super::{get_database_path, remove_if_exists, sibling_path};
+
+/// Exclusive lock on the index, released when dropped.
+#[derive(Debug)]
+pub struct IndexLock {
+    /// Path of the lock file.
+    path: PathBuf,
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        if read_owner(&self.path) != Some(std::process::id()) {
+            warn!(path = %self.path.display(), "Index lock was taken over, leaving it in place");
+            return;
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), error = %e, "Failed to remove index lock");
+        }
+    }
+}
+
+/// Acquires the index lock for an update.
+///
+/// # Errors
+///
+/// Returns an error if another ulm process holds the lock or the lock
+/// file cannot be created.
+pub fn lock_index() -> Result<IndexLock> {
+    let path = sibling_path(&get_database_path()?, "lock");
+    acquire(&path)
+}
+
+/// Creates the lock file at `path`, replacing a stale one.
+fn acquire(path: &Path) -> Result<IndexLock> {
+    let pid = std::process::id();
+    let owner = sibling_path(path, &pid.to_string());
+    fs::write(&owner, format!("{pid}\n"))
+        .with_context(|| format!("Failed to write lock file: {}", owner.display()))?;
+
+    let result = link_lock(&owner, path);
+    let _ = fs::remove_file(&owner);
+    result
+}
+
+/// Links the file naming the owner to `path`, which fails if it exists.
+fn link_lock(owner: &Path, path: &Path) -> Result<IndexLock> {
+    // A second attempt is only made after removing a stale lock
+    for _ in 0..2 {
+        match fs::hard_link(owner, path) {
+            Ok(()) => {
+                debug!(path = %path.display(), "Acquired index lock");
+                return Ok(IndexLock {
+                    path: path.to_path_buf(),
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if let Some(pid) = read_owner(path).filter(|&pid| is_running(pid)) {
+                    return Err(already_running(pid, path));
+                }
+                warn!(path = %path.display(), "Removing stale index lock");
+                remove_stale(path)?;
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to create lock file: {}", path.display()));
+            }
+        }
+    }
+
+    anyhow::bail!("Failed to acquire index lock: {}", path.display())
+}
+
+/// Removes a stale lock at `path`.
+///
+/// The lock is renamed to a name of this process first. If the renamed
+/// lock holds a running PID, another process replaced the stale lock in the
+/// meantime, and its lock is put back.
+fn remove_stale(path: &Path) -> Result<()> {
+    let stale = sibling_path(path, &format!("stale.{}", std::process::id()));
+    match fs::rename(path, &stale) {
+        Ok(()) => {}
+        // Another process removed the stale lock first
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to remove stale lock: {}", path.display()));
+        }
+    }
+
+    let result = match read_owner(&stale).filter(|&pid| is_running(pid)) {
+        // Linking fails if yet another process took the lock meanwhile
+        Some(pid) => {
+            let _ = fs::hard_link(&stale, path);
+            Err(already_running(pid, path))
+        }
+        None => Ok(()),
+    };
+    remove_if_exists(&stale)?;
+    result
+}
+
+/// Returns the error for a lock held by the running process `pid`.
+fn already_running(pid: u32, path: &Path) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Another ulm update is already running (pid {pid}).\n\
+         Wait for it to finish, or remove {} if it was interrupted.",
+        path.display()
+    )
+}
+
+/// Reads the PID of the lock owner, if the lock file holds one.
+fn read_owner(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Checks if a process with the given PID is running.
+fn is_running(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+    system.process(pid).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_acquire_is_exclusive() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db.lock");
+
+        let lock = acquire(&path).unwrap();
+        assert_eq!(read_owner(&path), Some(std::process::id()));
+
+        let err = acquire(&path).unwrap_err();
+        assert!(err.to_string().contains("already running"));
+
+        drop(lock);
+        assert!(!path.exists());
+        assert!(acquire(&path).is_ok());
+
+        // No lock or owner files are left behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_acquire_replaces_stale_lock() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db.lock");
+
+        // Empty lock file left by an interrupted run
+        fs::write(&path, "").unwrap();
+
+        let _lock = acquire(&path).unwrap();
+        assert_eq!(read_owner(&path), Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_remove_stale_puts_back_live_lock() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db.lock");
+
+        // Another process replaced the stale lock before this one removed it
+        let lock = acquire(&path).unwrap();
+        let err = remove_stale(&path).unwrap_err();
+        assert!(err.to_string().contains("already running"));
+        assert_eq!(read_owner(&path), Some(std::process::id()));
+
+        drop(lock);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_drop_keeps_lock_of_other_owner() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db.lock");
+
+        let lock = acquire(&path).unwrap();
+        fs::write(&path, "1\n").unwrap();
+        drop(lock);
+        assert_eq!(read_owner(&path), Some(1));
+    }
+
+    #[test]
+    fn test_is_running() {
+        assert!(is_running(std::process::id()));
+    }
+}
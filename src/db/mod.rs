@@ -0,0 +1,1441 @@
+//! `SQLite`-vec operations for vector storage and search.
+//!
+//! This module handles all interactions with the `SQLite` database
+//! using the sqlite-vec extension for vector similarity search.
+
+#![allow(unsafe_code)] // Required for loading SQLite extensions
+
+mod bundle;
+mod lock;
+mod quantize;
+mod registry;
+mod schema;
+mod search;
+
+pub use bundle::{export_index, import_index, read_bundle_info, BundleInfo};
+pub use lock::{lock_index, IndexLock};
+pub use quantize::VectorStorage;
+pub use schema::SCHEMA_VERSION;
+pub use search::{search, ChunkMatch, SearchResult};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::ffi::sqlite3_auto_extension;
+use rusqlite::{Connection, OptionalExtension};
+use tracing::{debug, info};
+use zerocopy::AsBytes;
+
+use crate::setup::{
+    FileRecord, ManpageAlias, ManpageChunk, ManpageContent, ManpageEntry, VectorSettings,
+};
+use registry::VectorTables;
+use schema::SchemaStatus;
+
+/// Name of the database file.
+const DB_FILENAME: &str = "index.db";
+
+/// How long a connection waits for a writer to release the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Gets the path to the `SQLite` database.
+///
+/// Uses XDG Base Directory specification:
+/// - Linux: ~/.local/share/ulm/index.db
+/// - macOS: ~/Library/Application Support/ulm/index.db
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be determined.
+pub fn get_database_path() -> Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "ulm")
+        .context("Could not determine data directory")?;
+
+    let data_dir = dirs.data_dir();
+
+    // Create parent directories if needed
+    fs::create_dir_all(data_dir)
+        .with_context(|| format!("Failed to create data directory: {}", data_dir.display()))?;
+
+    Ok(data_dir.join(DB_FILENAME))
+}
+
+/// Lists the index database and its companion files in the data directory.
+///
+/// Besides `index.db`, these are its WAL and shared-memory files, the update
+/// lock and the leftovers of interrupted builds and imports, which all share
+/// the database file name as prefix.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be determined or read.
+pub fn database_files() -> Result<Vec<PathBuf>> {
+    let db_path = get_database_path()?;
+    let data_dir = db_path.parent().context("Database path has no directory")?;
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(data_dir)
+        .with_context(|| format!("Failed to read data directory: {}", data_dir.display()))?
+    {
+        let path = entry?.path();
+        let is_index_file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(DB_FILENAME));
+        if is_index_file && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Initialize sqlite-vec as an auto-extension (called once at startup).
+fn init_sqlite_vec() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        // SAFETY: This registers sqlite-vec as an auto-extension that loads
+        // automatically for all new connections. The transmute converts the
+        // init function pointer to the expected callback signature.
+        #[allow(clippy::missing_transmute_annotations)]
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+    });
+}
+
+/// Opens a connection to the database with sqlite-vec loaded.
+fn open_connection(path: &Path) -> Result<Connection> {
+    // Initialize sqlite-vec auto-extension before opening connection
+    init_sqlite_vec();
+
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to open database: {}", path.display()))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .context("Failed to set database busy timeout")?;
+
+    Ok(conn)
+}
+
+/// Opens the index database, upgrading its schema if needed.
+///
+/// # Errors
+///
+/// Returns a "rebuild required" error if the index was built by an
+/// incompatible version of ulm.
+fn open_index(path: &Path) -> Result<Connection> {
+    let mut conn = open_connection(path)?;
+    schema::migrate(&mut conn)?;
+
+    Ok(conn)
+}
+
+/// Returns `path` with an extra extension (e.g., `index.db.tmp`).
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Removes a leftover file from an interrupted run.
+fn remove_if_exists(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale file: {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Removes a database file together with its WAL and shared-memory files.
+fn remove_database_files(path: &Path) -> Result<()> {
+    remove_if_exists(path)?;
+    for suffix in ["-wal", "-shm"] {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(suffix);
+        remove_if_exists(Path::new(&name))?;
+    }
+
+    Ok(())
+}
+
+/// Inserts or replaces the tracking record of an indexed file.
+fn upsert_file_record(conn: &Connection, record: &FileRecord) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO indexed_files
+            (source_path, hash, mtime, embedding_model, indexed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            record.source_path,
+            record.hash,
+            record.mtime,
+            record.embedding_model,
+            record.indexed_at
+        ],
+    )
+    .context("Failed to store file record")?;
+
+    Ok(())
+}
+
+/// Inserts a single entry and its chunks into the manpage and vector tables.
+fn insert_entry(conn: &Connection, tables: &VectorTables, entry: &ManpageEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO manpages (tool_name, section, description, source_path, content, language)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            entry.tool_name,
+            entry.section,
+            entry.description,
+            entry.source_path.to_string_lossy(),
+            entry.content,
+            entry.language
+        ],
+    )
+    .context("Failed to insert manpage")?;
+
+    let id = conn.last_insert_rowid();
+
+    conn.execute(
+        "INSERT INTO manpages_fts (rowid, tool_name, description) VALUES (?1, ?2, ?3)",
+        rusqlite::params![id, entry.tool_name, entry.description],
+    )
+    .context("Failed to insert full-text entry")?;
+
+    for chunk in &entry.chunks {
+        conn.execute(
+            "INSERT INTO chunks (manpage_id, heading, text) VALUES (?1, ?2, ?3)",
+            rusqlite::params![id, chunk.heading, chunk.text],
+        )
+        .context("Failed to insert chunk")?;
+    }
+
+    insert_vectors(conn, tables, id, entry)
+}
+
+/// Inserts the vectors of an entry into the tables of one embedding model.
+///
+/// Chunk vectors are matched to the stored chunks of the manpage in order.
+fn insert_vectors(
+    conn: &Connection,
+    tables: &VectorTables,
+    manpage_id: i64,
+    entry: &ManpageEntry,
+) -> Result<()> {
+    // Insert vector as blob
+    quantize::insert_vector(
+        conn,
+        tables,
+        &tables.manpages(),
+        &tables.full_manpages(),
+        manpage_id,
+        tables.prepare(&entry.vector).as_bytes(),
+    )?;
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM chunks WHERE manpage_id = ?1 ORDER BY id")
+        .context("Failed to prepare chunk query")?;
+    let chunk_ids = stmt
+        .query_map([manpage_id], |row| row.get::<_, i64>(0))
+        .context("Failed to query chunks")?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read chunk")?;
+
+    for (chunk_id, chunk) in chunk_ids.into_iter().zip(&entry.chunks) {
+        quantize::insert_vector(
+            conn,
+            tables,
+            &tables.chunks(),
+            &tables.full_chunks(),
+            chunk_id,
+            tables.prepare(&chunk.vector).as_bytes(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the vectors of a manpage and its chunks from one embedding model.
+fn delete_vectors(conn: &Connection, tables: &VectorTables, manpage_id: i64) -> Result<()> {
+    let mut chunk_tables = vec![tables.chunks()];
+    let mut manpage_tables = vec![tables.manpages()];
+    if tables.is_quantized() {
+        chunk_tables.push(tables.full_chunks());
+        manpage_tables.push(tables.full_manpages());
+    }
+
+    for table in chunk_tables {
+        conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE id IN (SELECT id FROM chunks WHERE manpage_id = ?1)"
+            ),
+            [manpage_id],
+        )
+        .context("Failed to delete chunk vectors")?;
+    }
+    for table in manpage_tables {
+        conn.execute(&format!("DELETE FROM {table} WHERE id = ?1"), [manpage_id])
+            .context("Failed to delete vector")?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the entry and file record for a source path.
+///
+/// Returns `true` if an entry was removed.
+fn delete_entry(conn: &Connection, source_path: &str) -> Result<bool> {
+    conn.execute(
+        "DELETE FROM indexed_files WHERE source_path = ?1",
+        [source_path],
+    )
+    .context("Failed to delete file record")?;
+
+    let id: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM manpages WHERE source_path = ?1",
+            [source_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to look up manpage")?;
+
+    let Some(id) = id else {
+        return Ok(false);
+    };
+
+    // Vectors of every embedding model are stale once the text changes
+    for tables in registry::all(conn)? {
+        delete_vectors(conn, &tables, id)?;
+    }
+    conn.execute("DELETE FROM chunks WHERE manpage_id = ?1", [id])
+        .context("Failed to delete chunks")?;
+    conn.execute("DELETE FROM aliases WHERE manpage_id = ?1", [id])
+        .context("Failed to delete aliases")?;
+    conn.execute("DELETE FROM manpages_fts WHERE rowid = ?1", [id])
+        .context("Failed to delete full-text entry")?;
+    conn.execute("DELETE FROM manpages WHERE id = ?1", [id])
+        .context("Failed to delete manpage")?;
+
+    Ok(true)
+}
+
+/// Deletes the entry with the same tool name, section and language from another source path.
+///
+/// `(tool_name, section, language)` identifies a page, so a page that moved
+/// to a directory earlier in the search order replaces the shadowed one.
+fn delete_conflicting_entry(conn: &Connection, entry: &ManpageEntry) -> Result<()> {
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT source_path FROM manpages
+             WHERE tool_name = ?1 AND section = ?2 AND language = ?3",
+            [&entry.tool_name, &entry.section, &entry.language],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to look up manpage")?;
+
+    if let Some(path) = existing {
+        debug!(tool = %entry.tool_name, section = %entry.section, path = %path, "Replacing entry");
+        delete_entry(conn, &path)?;
+    }
+
+    Ok(())
+}
+
+/// Drops and re-creates the tables, then inserts all entries and file records.
+///
+/// Entries carry vectors of the embedding model `model`, stored with the
+/// given vector settings.
+fn rebuild_tables(
+    conn: &mut Connection,
+    model: &str,
+    settings: VectorSettings,
+    entries: &[ManpageEntry],
+    records: &[FileRecord],
+) -> Result<()> {
+    let tx = conn.transaction().context("Failed to start transaction")?;
+
+    // Drop existing tables, whatever schema version they have
+    schema::drop_tables(&tx)?;
+    schema::create_tables(&tx)?;
+
+    // Get vector dimension from first entry
+    if let Some(first) = entries.first() {
+        let tables = registry::register(&tx, model, first.vector.len(), settings)?;
+        for entry in entries {
+            insert_entry(&tx, &tables, entry)?;
+        }
+    }
+    for record in records {
+        upsert_file_record(&tx, record)?;
+    }
+
+    tx.commit().context("Failed to commit index")?;
+
+    info!("Created index with {} entries", entries.len());
+
+    Ok(())
+}
+
+/// Builds a new index in a shadow file and renames it over `index_path`.
+///
+/// Readers keep using the old index until the rename, and an interrupted
+/// build leaves it untouched.
+fn build_index(
+    index_path: &Path,
+    model: &str,
+    settings: VectorSettings,
+    entries: &[ManpageEntry],
+    records: &[FileRecord],
+) -> Result<()> {
+    let shadow = sibling_path(index_path, "build");
+    remove_database_files(&shadow)?;
+
+    let result = build_shadow(&shadow, model, settings, entries, records).and_then(|()| {
+        fs::rename(&shadow, index_path)
+            .with_context(|| format!("Failed to replace index: {}", index_path.display()))
+    });
+
+    if result.is_err() {
+        let _ = remove_database_files(&shadow);
+    }
+
+    result
+}
+
+/// Writes all entries and file records into a fresh database at `path`.
+fn build_shadow(
+    path: &Path,
+    model: &str,
+    settings: VectorSettings,
+    entries: &[ManpageEntry],
+    records: &[FileRecord],
+) -> Result<()> {
+    let mut conn = open_connection(path)?;
+
+    // WAL avoids writing every page twice during the bulk insert
+    set_journal_mode(&conn, "WAL")?;
+    rebuild_tables(&mut conn, model, settings, entries, records)?;
+
+    // Fold the WAL back into the database so the file can be renamed alone
+    set_journal_mode(&conn, "DELETE")?;
+
+    Ok(())
+}
+
+/// Switches the journal mode of a connection.
+fn set_journal_mode(conn: &Connection, mode: &str) -> Result<()> {
+    let actual: String = conn
+        .pragma_update_and_check(None, "journal_mode", mode, |row| row.get(0))
+        .with_context(|| format!("Failed to set journal mode to {mode}"))?;
+
+    if !actual.eq_ignore_ascii_case(mode) {
+        anyhow::bail!("Database refused journal mode {mode} (using {actual})");
+    }
+
+    Ok(())
+}
+
+/// Replaces the entries for the given source paths, keeping all others.
+///
+/// File records are stored in the same transaction, including records of
+/// files that produced no entry (e.g., malformed manpages). The tables must
+/// already exist at the current schema version. Replaced entries lose the
+/// vectors of other embedding models.
+fn upsert_rows(
+    conn: &mut Connection,
+    model: &str,
+    settings: VectorSettings,
+    entries: &[ManpageEntry],
+    records: &[FileRecord],
+) -> Result<()> {
+    let tx = conn.transaction().context("Failed to start transaction")?;
+
+    if let Some(first) = entries.first() {
+        let tables = registry::register(&tx, model, first.vector.len(), settings)?;
+        for entry in entries {
+            delete_entry(&tx, &entry.source_path.to_string_lossy())?;
+            delete_conflicting_entry(&tx, entry)?;
+            insert_entry(&tx, &tables, entry)?;
+        }
+    }
+    for record in records {
+        upsert_file_record(&tx, record)?;
+    }
+
+    tx.commit().context("Failed to commit index update")?;
+
+    Ok(())
+}
+
+/// Removes the entries for the given source paths.
+fn delete_rows(conn: &mut Connection, source_paths: &[String]) -> Result<usize> {
+    let tx = conn.transaction().context("Failed to start transaction")?;
+
+    let mut removed = 0;
+    for path in source_paths {
+        if delete_entry(&tx, path)? {
+            removed += 1;
+        }
+    }
+
+    tx.commit().context("Failed to commit index deletions")?;
+
+    Ok(removed)
+}
+
+/// Creates or overwrites the vector index with the given entries.
+///
+/// The index is built in a separate file and atomically renamed over
+/// `index.db`, so queries never see a partially built index. The entries
+/// carry vectors of the embedding model `model`, stored with the given
+/// vector settings. The file records of the indexed manpages are stored in
+/// the same transaction.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn create_index(
+    model: &str,
+    settings: VectorSettings,
+    entries: Vec<ManpageEntry>,
+    records: Vec<FileRecord>,
+) -> Result<()> {
+    let db_path = get_database_path()?;
+
+    info!(path = %db_path.display(), model = %model, entries = entries.len(), "Creating vector index");
+
+    build_index(&db_path, model, settings, &entries, &records)
+}
+
+/// Inserts or replaces entries in the existing index, keyed by source path.
+///
+/// Entries for source paths not in `entries` are left untouched. The
+/// entries carry vectors of the embedding model `model`. If the model's
+/// vectors were stored with other settings, they are dropped and must be
+/// backfilled. The file records are stored in the same transaction.
+///
+/// # Errors
+///
+/// Returns an error if the index needs a rebuild or database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn upsert_entries(
+    model: &str,
+    settings: VectorSettings,
+    entries: Vec<ManpageEntry>,
+    records: Vec<FileRecord>,
+) -> Result<()> {
+    let db_path = get_database_path()?;
+
+    info!(path = %db_path.display(), model = %model, entries = entries.len(), "Updating vector index");
+
+    let mut conn = open_index(&db_path)?;
+    upsert_rows(&mut conn, model, settings, &entries, &records)
+}
+
+/// Loads the stored manpages that have no vectors for an embedding model.
+///
+/// Used to backfill vectors after switching models or vector settings,
+/// without re-extracting the manpages. If the model's vectors were stored
+/// with other settings, all stored manpages are returned. Entries without
+/// stored text are not returned; they are re-extracted by the regular
+/// update instead.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn pages_without_vectors(
+    model: &str,
+    settings: VectorSettings,
+) -> Result<Vec<ManpageContent>> {
+    let db_path = get_database_path()?;
+
+    let conn = open_index(&db_path)?;
+    query_pages_without_vectors(&conn, model, settings)
+}
+
+/// Queries the stored manpages that have no vectors for an embedding model.
+fn query_pages_without_vectors(
+    conn: &Connection,
+    model: &str,
+    settings: VectorSettings,
+) -> Result<Vec<ManpageContent>> {
+    if !schema::table_exists(conn, "manpages")? {
+        return Ok(Vec::new());
+    }
+
+    let filter = registry::find(conn, model)?
+        .filter(|tables| tables.settings == settings)
+        .map(|tables| format!("AND id NOT IN (SELECT id FROM {})", tables.manpages()))
+        .unwrap_or_default();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, tool_name, section, description, source_path, content, language
+            FROM manpages
+            WHERE content IS NOT NULL {filter}
+            ORDER BY id"
+        ))
+        .context("Failed to prepare manpage query")?;
+    let mut chunk_stmt = conn
+        .prepare("SELECT heading, text FROM chunks WHERE manpage_id = ?1 ORDER BY id")
+        .context("Failed to prepare chunk query")?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                ManpageContent {
+                    tool_name: row.get(1)?,
+                    section: row.get(2)?,
+                    description: row.get(3)?,
+                    source_path: PathBuf::from(row.get::<_, String>(4)?),
+                    content: row.get(5)?,
+                    language: row.get(6)?,
+                    chunks: Vec::new(),
+                },
+            ))
+        })
+        .context("Failed to query manpages")?;
+
+    let mut pages = Vec::new();
+    for row in rows {
+        let (id, mut page) = row.context("Failed to read manpage")?;
+        page.chunks = chunk_stmt
+            .query_map([id], |row| {
+                Ok(ManpageChunk {
+                    heading: row.get(0)?,
+                    text: row.get(1)?,
+                })
+            })
+            .context("Failed to query chunks")?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to read chunk")?;
+        pages.push(page);
+    }
+
+    Ok(pages)
+}
+
+/// Stores vectors of an embedding model for entries already in the index.
+///
+/// Only the vector tables of `model` are written; the manpage text is
+/// left untouched. Entries whose source path is no longer indexed are
+/// skipped. Returns the number of entries stored.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn store_vectors(
+    model: &str,
+    settings: VectorSettings,
+    entries: Vec<ManpageEntry>,
+) -> Result<usize> {
+    let db_path = get_database_path()?;
+
+    info!(path = %db_path.display(), model = %model, entries = entries.len(), "Storing vectors");
+
+    let mut conn = open_index(&db_path)?;
+    store_vector_rows(&mut conn, model, settings, &entries)
+}
+
+/// Replaces the vectors of `model` for the given entries in one transaction.
+fn store_vector_rows(
+    conn: &mut Connection,
+    model: &str,
+    settings: VectorSettings,
+    entries: &[ManpageEntry],
+) -> Result<usize> {
+    let Some(first) = entries.first() else {
+        return Ok(0);
+    };
+
+    let tx = conn.transaction().context("Failed to start transaction")?;
+    let tables = registry::register(&tx, model, first.vector.len(), settings)?;
+
+    let mut stored = 0;
+    for entry in entries {
+        let id: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM manpages WHERE source_path = ?1",
+                [entry.source_path.to_string_lossy()],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to look up manpage")?;
+
+        if let Some(id) = id {
+            delete_vectors(&tx, &tables, id)?;
+            insert_vectors(&tx, &tables, id, entry)?;
+            stored += 1;
+        }
+    }
+
+    tx.commit().context("Failed to commit vectors")?;
+
+    Ok(stored)
+}
+
+/// Checks if the index has vectors for an embedding model.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn has_vectors(model: &str) -> Result<bool> {
+    let db_path = get_database_path()?;
+
+    if !db_path.exists() {
+        return Ok(false);
+    }
+
+    let conn = open_index(&db_path)?;
+    if !schema::table_exists(&conn, "embedding_models")? {
+        return Ok(false);
+    }
+
+    Ok(registry::find(&conn, model)?.is_some())
+}
+
+/// Reports the vector storage of an embedding model.
+///
+/// For quantized models, the recall of the quantized search is measured on
+/// sampled vectors. Returns `None` if the index has no vectors for `model`.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn vector_storage(model: &str) -> Result<Option<VectorStorage>> {
+    let db_path = get_database_path()?;
+
+    let conn = open_index(&db_path)?;
+    if !schema::table_exists(&conn, "embedding_models")? {
+        return Ok(None);
+    }
+
+    registry::find(&conn, model)?
+        .map(|tables| quantize::storage(&conn, &tables))
+        .transpose()
+}
+
+/// Loads the tracking records of all indexed files.
+///
+/// Returns an empty list if the index doesn't exist.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn load_file_records() -> Result<Vec<FileRecord>> {
+    if !index_exists().await? {
+        return Ok(Vec::new());
+    }
+
+    let db_path = get_database_path()?;
+    let conn = open_index(&db_path)?;
+
+    query_file_records(&conn)
+}
+
+/// Queries all file records.
+fn query_file_records(conn: &Connection) -> Result<Vec<FileRecord>> {
+    let mut stmt = conn
+        .prepare("SELECT source_path, hash, mtime, embedding_model, indexed_at FROM indexed_files")
+        .context("Failed to prepare file record query")?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(FileRecord {
+                source_path: row.get(0)?,
+                hash: row.get(1)?,
+                mtime: row.get(2)?,
+                embedding_model: row.get(3)?,
+                indexed_at: row.get(4)?,
+            })
+        })
+        .context("Failed to query file records")?;
+
+    rows.map(|r| r.context("Failed to read file record"))
+        .collect()
+}
+
+/// Replaces the aliases stored in the index.
+///
+/// Aliases whose target page is not in the index are dropped.
+/// Returns the number of aliases stored.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn replace_aliases(aliases: Vec<ManpageAlias>) -> Result<usize> {
+    let db_path = get_database_path()?;
+    let mut conn = open_index(&db_path)?;
+
+    let stored = replace_alias_rows(&mut conn, &aliases)?;
+    debug!(stored = stored, "Stored aliases");
+
+    Ok(stored)
+}
+
+/// Replaces all alias rows in one transaction.
+fn replace_alias_rows(conn: &mut Connection, aliases: &[ManpageAlias]) -> Result<usize> {
+    let tx = conn.transaction().context("Failed to start transaction")?;
+
+    tx.execute("DELETE FROM aliases", [])
+        .context("Failed to clear aliases")?;
+
+    let mut stored = 0;
+    for alias in aliases {
+        stored += tx
+            .execute(
+                "INSERT INTO aliases (manpage_id, name, section)
+                 SELECT id, ?1, ?2 FROM manpages WHERE source_path = ?3",
+                rusqlite::params![alias.name, alias.section, alias.target.to_string_lossy()],
+            )
+            .context("Failed to store alias")?;
+    }
+
+    tx.commit().context("Failed to commit aliases")?;
+
+    Ok(stored)
+}
+
+/// Deletes the entries for the given source paths from the index.
+///
+/// Returns the number of entries removed.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn delete_entries(source_paths: &[String]) -> Result<usize> {
+    if source_paths.is_empty() {
+        return Ok(0);
+    }
+
+    let db_path = get_database_path()?;
+    let mut conn = open_index(&db_path)?;
+
+    let removed = delete_rows(&mut conn, source_paths)?;
+    debug!(removed = removed, "Deleted entries from index");
+
+    Ok(removed)
+}
+
+/// Checks if the index exists and can be updated incrementally.
+///
+/// Indexes whose schema cannot be migrated in place (e.g., built before
+/// incremental updates or by a newer ulm) must be rebuilt from scratch.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn supports_incremental_update() -> Result<bool> {
+    if !index_exists().await? {
+        return Ok(false);
+    }
+
+    let db_path = get_database_path()?;
+    let conn = open_connection(&db_path)?;
+
+    Ok(matches!(
+        schema::schema_status(&conn)?,
+        SchemaStatus::Current | SchemaStatus::Outdated(_)
+    ))
+}
+
+/// Loads the stored manpage text for a tool in a section and language.
+///
+/// Returns `None` if the index doesn't exist or has no text for the page
+/// (e.g., entries indexed before full text was stored).
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn load_content(
+    tool_name: &str,
+    section: &str,
+    language: &str,
+) -> Result<Option<String>> {
+    if !index_exists().await? {
+        return Ok(None);
+    }
+
+    let db_path = get_database_path()?;
+    let conn = open_index(&db_path)?;
+
+    query_content(&conn, tool_name, section, language)
+}
+
+/// Queries the stored text of the page identified by tool name, section and language.
+fn query_content(
+    conn: &Connection,
+    tool_name: &str,
+    section: &str,
+    language: &str,
+) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT content FROM manpages
+         WHERE tool_name = ?1 AND section = ?2 AND language = ?3 AND content IS NOT NULL",
+        [tool_name, section, language],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("Failed to load manpage content")
+}
+
+/// Returns the source paths of entries that have no stored manpage text.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn source_paths_without_content() -> Result<Vec<String>> {
+    if !index_exists().await? {
+        return Ok(Vec::new());
+    }
+
+    let db_path = get_database_path()?;
+    let conn = open_index(&db_path)?;
+
+    let mut stmt = conn
+        .prepare("SELECT source_path FROM manpages WHERE content IS NULL")
+        .context("Failed to prepare source path query")?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .context("Failed to query source paths")?;
+
+    rows.map(|r| r.context("Failed to read source path"))
+        .collect()
+}
+
+/// Checks if the vector index exists.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn index_exists() -> Result<bool> {
+    let db_path = get_database_path()?;
+
+    // Check if database file exists
+    if !db_path.exists() {
+        return Ok(false);
+    }
+
+    let conn = open_connection(&db_path)?;
+
+    // Check for manpages table
+    schema::table_exists(&conn, "manpages")
+}
+
+/// Gets the total number of entries in the index.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn count_entries() -> Result<usize> {
+    let db_path = get_database_path()?;
+
+    let conn = open_connection(&db_path)?;
+
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM manpages", [], |row| row.get(0))
+        .context("Failed to count entries")?;
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    Ok(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::locale::DEFAULT_LANGUAGE;
+    use crate::setup::ChunkEntry;
+    use tempfile::TempDir;
+
+    /// Embedding model the test entries are indexed with.
+    pub(super) const TEST_MODEL: &str = "nomic-embed-text";
+
+    pub(super) fn create_test_entries(count: usize) -> Vec<ManpageEntry> {
+        (0..count)
+            .map(|i| ManpageEntry {
+                tool_name: format!("tool{i}"),
+                section: "1".to_string(),
+                language: DEFAULT_LANGUAGE.to_string(),
+                description: format!("Description for tool {i}"),
+                content: format!("NAME\n tool{i} - test tool"),
+                source_path: PathBuf::from(format!("/usr/share/man/man1/tool{i}.1.gz")),
+                #[allow(clippy::cast_precision_loss)]
+                vector: vec![0.1 * i as f32; 8], // Small vectors for testing
+                #[allow(clippy::cast_precision_loss)]
+                chunks: vec![ChunkEntry {
+                    heading: "OPTIONS".to_string(),
+                    text: format!("--option{i} enables feature {i}"),
+                    vector: vec![0.1 * i as f32; 8],
+                }],
+            })
+            .collect()
+    }
+
+    fn count_rows(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_get_database_path() {
+        let path = get_database_path().unwrap();
+        assert!(path.to_string_lossy().contains("ulm"));
+        assert!(path.to_string_lossy().ends_with("index.db"));
+    }
+
+    #[test]
+    fn test_upsert_keeps_unchanged_entries() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(5),
+            &[],
+        )
+        .unwrap();
+
+        // Re-index one existing page and add a new one
+        let mut changed = create_test_entries(6);
+        changed.drain(..4);
+        changed[0].description = "Updated description".to_string();
+        upsert_rows(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &changed,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(count_rows(&conn, "manpages"), 6);
+        assert_eq!(count_rows(&conn, "manpages_vec_1"), 6);
+        assert_eq!(count_rows(&conn, "chunks_vec_1"), 6);
+
+        let description: String = conn
+            .query_row(
+                "SELECT description FROM manpages WHERE tool_name = 'tool4'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(description, "Updated description");
+    }
+
+    #[test]
+    fn test_delete_rows_removes_vectors() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(3),
+            &[],
+        )
+        .unwrap();
+
+        let removed = delete_rows(
+            &mut conn,
+            &[
+                "/usr/share/man/man1/tool1.1.gz".to_string(),
+                "/usr/share/man/man1/missing.1".to_string(),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(removed, 1);
+        assert_eq!(count_rows(&conn, "manpages"), 2);
+        assert_eq!(count_rows(&conn, "manpages_vec_1"), 2);
+        assert_eq!(count_rows(&conn, "chunks"), 2);
+        assert_eq!(count_rows(&conn, "chunks_vec_1"), 2);
+    }
+
+    #[test]
+    fn test_replace_alias_rows() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        let entries = create_test_entries(3);
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            &[],
+        )
+        .unwrap();
+
+        let alias = |name: &str, target: &Path| ManpageAlias {
+            name: name.to_string(),
+            section: "1".to_string(),
+            target: target.to_path_buf(),
+        };
+        let aliases = [
+            alias("vi", &entries[0].source_path),
+            alias("gone", Path::new("/usr/share/man/man1/missing.1")),
+        ];
+        assert_eq!(replace_alias_rows(&mut conn, &aliases).unwrap(), 1);
+        assert_eq!(replace_alias_rows(&mut conn, &aliases[..1]).unwrap(), 1);
+        assert_eq!(count_rows(&conn, "aliases"), 1);
+
+        // Aliases go away with their target page
+        delete_rows(&mut conn, &["/usr/share/man/man1/tool0.1.gz".to_string()]).unwrap();
+        assert_eq!(count_rows(&conn, "aliases"), 0);
+    }
+
+    #[test]
+    fn test_query_content() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(2),
+            &[],
+        )
+        .unwrap();
+
+        let content = query_content(&conn, "tool1", "1", DEFAULT_LANGUAGE).unwrap();
+        assert_eq!(content.as_deref(), Some("NAME\n tool1 - test tool"));
+        assert!(query_content(&conn, "tool1", "5", DEFAULT_LANGUAGE)
+            .unwrap()
+            .is_none());
+        assert!(query_content(&conn, "missing", "1", DEFAULT_LANGUAGE)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_query_content_by_section() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(2);
+        entries[1].tool_name = "tool0".to_string();
+        entries[1].section = "5".to_string();
+        entries[1].content = "file format".to_string();
+        entries[1].source_path = PathBuf::from("/usr/share/man/man5/tool0.5.gz");
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            &[],
+        )
+        .unwrap();
+
+        let content = query_content(&conn, "tool0", "5", DEFAULT_LANGUAGE).unwrap();
+        assert_eq!(content.as_deref(), Some("file format"));
+    }
+
+    #[test]
+    fn test_query_content_by_language() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(2);
+        entries[1].tool_name = "tool0".to_string();
+        entries[1].language = "de".to_string();
+        entries[1].content = "BEZEICHNUNG\n tool0 - Testwerkzeug".to_string();
+        entries[1].source_path = PathBuf::from("/usr/share/man/de/man1/tool0.1.gz");
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            &[],
+        )
+        .unwrap();
+
+        let content = query_content(&conn, "tool0", "1", "de").unwrap();
+        assert_eq!(
+            content.as_deref(),
+            Some("BEZEICHNUNG\n tool0 - Testwerkzeug")
+        );
+        let content = query_content(&conn, "tool0", "1", DEFAULT_LANGUAGE).unwrap();
+        assert_eq!(content.as_deref(), Some("NAME\n tool0 - test tool"));
+    }
+
+    #[test]
+    fn test_upsert_replaces_entry_with_same_identity() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(2),
+            &[],
+        )
+        .unwrap();
+
+        // Same page found in a directory earlier in the search order
+        let mut moved = create_test_entries(1);
+        moved[0].source_path = PathBuf::from("/usr/local/share/man/man1/tool0.1");
+        upsert_rows(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &moved,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(count_rows(&conn, "manpages"), 2);
+        assert_eq!(count_rows(&conn, "chunks_vec_1"), 2);
+        let path: String = conn
+            .query_row(
+                "SELECT source_path FROM manpages WHERE tool_name = 'tool0'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(path, "/usr/local/share/man/man1/tool0.1");
+    }
+
+    #[test]
+    fn test_rebuild_replaces_incompatible_schema() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        // Schema from before incremental updates cannot be migrated
+        conn.execute(
+            "CREATE TABLE manpages (id INTEGER PRIMARY KEY, tool_name TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        assert!(schema::migrate(&mut conn).is_err());
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(2),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(schema::schema_status(&conn).unwrap(), SchemaStatus::Current);
+        assert_eq!(count_rows(&conn, "manpages"), 2);
+    }
+
+    #[test]
+    fn test_build_index_replaces_existing_index() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db");
+
+        let mut conn = open_connection(&path).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(5),
+            &[],
+        )
+        .unwrap();
+
+        // A reader opened before the rebuild keeps seeing the old index
+        build_index(
+            &path,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(2),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(count_rows(&conn, "manpages"), 5);
+        drop(conn);
+
+        let conn = open_index(&path).unwrap();
+        assert_eq!(count_rows(&conn, "manpages"), 2);
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+        assert!(!sibling_path(&path, "build").exists());
+        assert!(!temp.path().join("index.db.build-wal").exists());
+    }
+
+    #[test]
+    fn test_build_index_failure_keeps_existing_index() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("index.db");
+
+        let mut conn = open_connection(&path).unwrap();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(3),
+            &[],
+        )
+        .unwrap();
+        drop(conn);
+
+        // Two entries with the same identity violate the unique index
+        let mut entries = create_test_entries(1);
+        entries.push(entries[0].clone());
+        entries[1].source_path = PathBuf::from("/usr/share/man/man1/other.1.gz");
+
+        assert!(build_index(&path, TEST_MODEL, VectorSettings::default(), &entries, &[]).is_err());
+        assert!(!sibling_path(&path, "build").exists());
+
+        let conn = open_index(&path).unwrap();
+        assert_eq!(count_rows(&conn, "manpages"), 3);
+    }
+
+    #[test]
+    fn test_vectors_per_model() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &create_test_entries(3),
+            &[],
+        )
+        .unwrap();
+        assert!(
+            query_pages_without_vectors(&conn, TEST_MODEL, VectorSettings::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        // A new model needs vectors for every stored page, chunks included
+        let pages =
+            query_pages_without_vectors(&conn, "mxbai-embed-large", VectorSettings::default())
+                .unwrap();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[1].tool_name, "tool1");
+        assert_eq!(pages[1].chunks[0].text, "--option1 enables feature 1");
+
+        let mut entries = create_test_entries(3);
+        for entry in &mut entries {
+            entry.vector = vec![0.5; 4];
+            entry.chunks[0].vector = vec![0.5; 4];
+        }
+        let stored = store_vector_rows(
+            &mut conn,
+            "mxbai-embed-large",
+            VectorSettings::default(),
+            &entries,
+        )
+        .unwrap();
+        assert_eq!(stored, 3);
+        assert_eq!(count_rows(&conn, "manpages_vec_2"), 3);
+        assert_eq!(count_rows(&conn, "chunks_vec_2"), 3);
+        assert_eq!(count_rows(&conn, "manpages_vec_1"), 3);
+        assert!(
+            query_pages_without_vectors(&conn, "mxbai-embed-large", VectorSettings::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        // Re-indexing a page with one model drops its stale vectors of the other
+        let mut changed = create_test_entries(1);
+        changed[0].description = "Updated description".to_string();
+        upsert_rows(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &changed,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(count_rows(&conn, "manpages_vec_1"), 3);
+        assert_eq!(count_rows(&conn, "manpages_vec_2"), 2);
+        let pages =
+            query_pages_without_vectors(&conn, "mxbai-embed-large", VectorSettings::default())
+                .unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].description, "Updated description");
+    }
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(
+            sibling_path(Path::new("/data/index.db"), "import"),
+            PathBuf::from("/data/index.db.import")
+        );
+    }
+
+    fn test_record(path: &str) -> FileRecord {
+        FileRecord {
+            source_path: path.to_string(),
+            hash: "abc123".to_string(),
+            mtime: Some(1_700_000_000),
+            embedding_model: "nomic-embed-text".to_string(),
+            indexed_at: 1_700_000_100,
+        }
+    }
+
+    #[test]
+    fn test_file_records_follow_entries() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let entries = create_test_entries(2);
+        let records: Vec<FileRecord> = entries
+            .iter()
+            .map(|e| test_record(&e.source_path.to_string_lossy()))
+            .collect();
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            &records,
+        )
+        .unwrap();
+
+        // A malformed page has a record but no entry
+        upsert_rows(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &[],
+            &[test_record("/man1/broken.1")],
+        )
+        .unwrap();
+        assert_eq!(query_file_records(&conn).unwrap().len(), 3);
+
+        delete_rows(&mut conn, &["/usr/share/man/man1/tool0.1.gz".to_string()]).unwrap();
+        let records = query_file_records(&conn).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records
+            .iter()
+            .all(|r| r.source_path != "/usr/share/man/man1/tool0.1.gz"));
+    }
+}
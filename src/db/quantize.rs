@@ -0,0 +1,366 @@
+//! Quantized vector storage.
+//!
+//! With `index.quantization` set, the vec0 tables of a model hold int8 or
+//! binary vectors, and a plain `*_full_<id>` table next to each keeps the
+//! full-precision vectors. KNN runs on the quantized vectors first; the
+//! closest [`RESCORE_FACTOR`] × k candidates are then rescored with the
+//! full-precision vectors.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use super::registry::VectorTables;
+use crate::setup::{DistanceMetric, Quantization, VectorSettings};
+
+/// Number of quantized candidates fetched per requested neighbor.
+pub(super) const RESCORE_FACTOR: usize = 8;
+
+/// Number of manpage vectors sampled to measure recall.
+const RECALL_SAMPLES: usize = 20;
+
+/// Number of neighbors compared per sample when measuring recall.
+const RECALL_K: usize = 10;
+
+/// Vector storage of one embedding model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorStorage {
+    /// Quantization of the vectors searched first.
+    pub quantization: Quantization,
+    /// Number of stored vectors (manpages and chunks).
+    pub vectors: usize,
+    /// Size of the vectors searched first, in bytes.
+    pub search_bytes: u64,
+    /// Size of the full-precision vectors kept for rescoring, in bytes.
+    pub rescore_bytes: u64,
+    /// Share of the exact nearest neighbors that the quantized search
+    /// returns, measured on sampled manpage vectors (quantized only).
+    pub recall: Option<f32>,
+}
+
+/// Returns the vec0 column type for vectors with the given settings.
+///
+/// Binary vectors are always compared by Hamming distance.
+pub(super) fn column_type(settings: VectorSettings, dimension: usize) -> String {
+    let metric = settings.metric.as_str();
+    match settings.quantization {
+        Quantization::None => format!("float[{dimension}] distance_metric={metric}"),
+        Quantization::Int8 => format!("int8[{dimension}] distance_metric={metric}"),
+        Quantization::Binary => format!("bit[{dimension}]"),
+    }
+}
+
+/// Wraps the SQL expression of a `float32` vector to match the vec0 column.
+pub(super) fn encode(quantization: Quantization, expr: &str) -> String {
+    match quantization {
+        Quantization::None => expr.to_string(),
+        // Normalized vectors lie in -1..1; larger values are clamped
+        Quantization::Int8 => format!("vec_quantize_int8({expr}, 'unit')"),
+        Quantization::Binary => format!("vec_quantize_binary({expr})"),
+    }
+}
+
+/// Returns the sqlite-vec function computing a distance metric.
+const fn distance_function(metric: DistanceMetric) -> &'static str {
+    match metric {
+        DistanceMetric::Cosine => "vec_distance_cosine",
+        DistanceMetric::L2 => "vec_distance_l2",
+        DistanceMetric::L1 => "vec_distance_l1",
+    }
+}
+
+/// Returns a query selecting `(id, distance)` of the `?2` vectors in
+/// `vec_table` nearest to the `float32` vector `?1`, closest first.
+///
+/// For quantized tables, the distances are recomputed from `full_table`.
+pub(super) fn knn_query(tables: &VectorTables, vec_table: &str, full_table: &str) -> String {
+    if !tables.is_quantized() {
+        return format!(
+            "SELECT id, distance FROM {vec_table}
+            WHERE embedding MATCH ?1 AND k = ?2
+            ORDER BY distance"
+        );
+    }
+
+    format!(
+        "WITH coarse AS MATERIALIZED (
+            SELECT id FROM {vec_table}
+            WHERE embedding MATCH {} AND k = ?2 * {RESCORE_FACTOR}
+        )
+        SELECT f.id, {}(f.embedding, ?1) AS distance
+        FROM coarse c
+        JOIN {full_table} f ON f.id = c.id
+        ORDER BY distance
+        LIMIT ?2",
+        encode(tables.settings.quantization, "?1"),
+        distance_function(tables.settings.metric)
+    )
+}
+
+/// Inserts a prepared vector into a vec0 table and, if quantized, its
+/// full-precision table.
+pub(super) fn insert_vector(
+    conn: &Connection,
+    tables: &VectorTables,
+    vec_table: &str,
+    full_table: &str,
+    id: i64,
+    vector: &[u8],
+) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {vec_table} (id, embedding) VALUES (?1, {})",
+            encode(tables.settings.quantization, "?2")
+        ),
+        rusqlite::params![id, vector],
+    )
+    .with_context(|| format!("Failed to insert vector into {vec_table}"))?;
+
+    if tables.is_quantized() {
+        conn.execute(
+            &format!("INSERT INTO {full_table} (id, embedding) VALUES (?1, ?2)"),
+            rusqlite::params![id, vector],
+        )
+        .with_context(|| format!("Failed to insert vector into {full_table}"))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the size of one stored vector in bytes.
+const fn vector_bytes(quantization: Quantization, dimension: usize) -> usize {
+    match quantization {
+        Quantization::None => dimension * 4,
+        Quantization::Int8 => dimension,
+        Quantization::Binary => dimension / 8,
+    }
+}
+
+/// Computes the vector storage of a model and, if quantized, its recall.
+pub(super) fn storage(conn: &Connection, tables: &VectorTables) -> Result<VectorStorage> {
+    let mut vectors = 0;
+    for table in [tables.manpages(), tables.chunks()] {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .with_context(|| format!("Failed to count vectors in {table}"))?;
+        vectors += usize::try_from(count).unwrap_or(0);
+    }
+
+    let quantization = tables.settings.quantization;
+    let search_bytes = vectors * vector_bytes(quantization, tables.dimension);
+    let rescore_bytes = if tables.is_quantized() {
+        vectors * vector_bytes(Quantization::None, tables.dimension)
+    } else {
+        0
+    };
+
+    Ok(VectorStorage {
+        quantization,
+        vectors,
+        search_bytes: search_bytes as u64,
+        rescore_bytes: rescore_bytes as u64,
+        recall: measure_recall(conn, tables)?,
+    })
+}
+
+/// Measures how many exact nearest neighbors the quantized search finds.
+///
+/// Sampled manpage vectors are used as queries; each sample itself is
+/// excluded from both result lists. Returns `None` for unquantized or
+/// empty tables.
+#[allow(clippy::cast_precision_loss)]
+fn measure_recall(conn: &Connection, tables: &VectorTables) -> Result<Option<f32>> {
+    if !tables.is_quantized() {
+        return Ok(None);
+    }
+
+    let full_table = tables.full_manpages();
+    let mut sample_stmt = conn
+        .prepare(&format!(
+            "SELECT id, embedding FROM {full_table} ORDER BY random() LIMIT ?1"
+        ))
+        .context("Failed to prepare recall sample query")?;
+    let samples = sample_stmt
+        .query_map([RECALL_SAMPLES], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })
+        .context("Failed to sample vectors")?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read sampled vector")?;
+
+    let mut approx_stmt = conn
+        .prepare(&knn_query(tables, &tables.manpages(), &full_table))
+        .context("Failed to prepare quantized search")?;
+    let mut exact_stmt = conn
+        .prepare(&format!(
+            "SELECT id, {}(embedding, ?1) AS distance FROM {full_table}
+            ORDER BY distance LIMIT ?2",
+            distance_function(tables.settings.metric)
+        ))
+        .context("Failed to prepare exact search")?;
+
+    let neighbors = |stmt: &mut rusqlite::Statement<'_>, id: i64, vector: &[u8]| {
+        stmt.query_map(rusqlite::params![vector, RECALL_K + 1], |row| {
+            row.get::<_, i64>(0)
+        })
+        .context("Failed to search sampled vector")?
+        .filter(|neighbor| neighbor.as_ref().map_or(true, |n| *n != id))
+        .take(RECALL_K)
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read neighbor")
+    };
+
+    let mut found = 0;
+    let mut total = 0;
+    for (id, vector) in &samples {
+        let approx: HashSet<i64> = neighbors(&mut approx_stmt, *id, vector)?
+            .into_iter()
+            .collect();
+        let exact = neighbors(&mut exact_stmt, *id, vector)?;
+        found += exact.iter().filter(|n| approx.contains(n)).count();
+        total += exact.len();
+    }
+
+    if total == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(found as f32 / total as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{create_test_entries, TEST_MODEL};
+    use crate::db::{open_connection, rebuild_tables, registry};
+    use tempfile::TempDir;
+
+    /// Builds an index with spread-out vectors and the given quantization.
+    fn quantized_index(temp: &TempDir, quantization: Quantization) -> (Connection, VectorTables) {
+        let mut entries = create_test_entries(30);
+        for (i, entry) in entries.iter_mut().enumerate() {
+            // Pseudo-random components in -1..1
+            #[allow(clippy::cast_precision_loss)]
+            let vector: Vec<f32> = (0..8)
+                .map(|d| ((i * 8 + d) * 2_654_435_761 % 1000) as f32 / 500.0 - 1.0)
+                .collect();
+            entry.vector.clone_from(&vector);
+            entry.chunks[0].vector = vector;
+        }
+
+        let settings = VectorSettings {
+            quantization,
+            ..VectorSettings::default()
+        };
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        rebuild_tables(&mut conn, TEST_MODEL, settings, &entries, &[]).unwrap();
+        let tables = registry::find(&conn, TEST_MODEL).unwrap().unwrap();
+        (conn, tables)
+    }
+
+    #[test]
+    fn test_column_type() {
+        let int8 = VectorSettings {
+            quantization: Quantization::Int8,
+            ..VectorSettings::default()
+        };
+        assert_eq!(column_type(int8, 768), "int8[768] distance_metric=cosine");
+
+        let binary = VectorSettings {
+            quantization: Quantization::Binary,
+            ..VectorSettings::default()
+        };
+        assert_eq!(column_type(binary, 768), "bit[768]");
+    }
+
+    #[test]
+    fn test_quantized_search_rescores_with_full_precision() {
+        for quantization in [Quantization::Int8, Quantization::Binary] {
+            let temp = TempDir::new().unwrap();
+            let (conn, tables) = quantized_index(&temp, quantization);
+
+            let (id, query): (i64, Vec<u8>) = conn
+                .query_row(
+                    &format!(
+                        "SELECT id, embedding FROM {} ORDER BY id LIMIT 1",
+                        tables.full_manpages()
+                    ),
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap();
+
+            let mut stmt = conn
+                .prepare(&knn_query(
+                    &tables,
+                    &tables.manpages(),
+                    &tables.full_manpages(),
+                ))
+                .unwrap();
+            let results: Vec<(i64, f32)> = stmt
+                .query_map(rusqlite::params![query, 3], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap();
+
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[0].0, id);
+            // Rescored distances are exact, so the query matches itself
+            assert!(results[0].1.abs() < 1e-6);
+            assert!(results[1].1 <= results[2].1);
+        }
+    }
+
+    #[test]
+    fn test_storage_reports_sizes_and_recall() {
+        let temp = TempDir::new().unwrap();
+        let (conn, tables) = quantized_index(&temp, Quantization::Binary);
+
+        let storage = storage(&conn, &tables).unwrap();
+        assert_eq!(storage.vectors, 60);
+        assert_eq!(storage.search_bytes, 60);
+        assert_eq!(storage.rescore_bytes, 60 * 32);
+        let recall = storage.recall.unwrap();
+        assert!((0.0..=1.0).contains(&recall));
+
+        // int8 recall is measured against the float32 vectors as well
+        let temp = TempDir::new().unwrap();
+        let (conn, tables) = quantized_index(&temp, Quantization::Int8);
+        let storage = super::storage(&conn, &tables).unwrap();
+        assert_eq!(storage.search_bytes, 60 * 8);
+        assert!(storage.recall.is_some_and(|recall| recall > 0.5));
+    }
+
+    #[test]
+    fn test_delete_removes_full_precision_vectors() {
+        let temp = TempDir::new().unwrap();
+        let (mut conn, tables) = quantized_index(&temp, Quantization::Int8);
+
+        crate::db::delete_rows(&mut conn, &["/usr/share/man/man1/tool0.1.gz".to_string()]).unwrap();
+
+        for table in [tables.full_manpages(), tables.full_chunks()] {
+            let count: i64 = conn
+                .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(count, 29);
+        }
+    }
+
+    #[test]
+    fn test_storage_without_quantization() {
+        let temp = TempDir::new().unwrap();
+        let (conn, tables) = quantized_index(&temp, Quantization::None);
+
+        let storage = storage(&conn, &tables).unwrap();
+        assert_eq!(storage.search_bytes, 60 * 32);
+        assert_eq!(storage.rescore_bytes, 0);
+        assert_eq!(storage.recall, None);
+    }
+}
@@ -0,0 +1,392 @@
+//! Registry of the embedding models that have vectors in the index.
+//!
+//! Manpage text and chunks are stored once. Each embedding model gets its
+//! own pair of vector tables (`manpages_vec_<id>` and `chunks_vec_<id>`), so
+//! switching models in the config only requires embedding the pages that
+//! have no vectors for the new model yet.
+//!
+//! The registry also records the distance metric, normalization and
+//! quantization the tables were built with, so search interprets distances
+//! correctly even after the config changed. Quantized models have an extra
+//! pair of `*_full_<id>` tables with full-precision vectors for rescoring.
+
+use anyhow::{Context, Result};
+use rusqlite::types::Type;
+use rusqlite::{Connection, OptionalExtension, Row};
+use tracing::info;
+
+use super::quantize;
+use crate::setup::{Quantization, VectorSettings};
+
+/// Vector tables of one embedding model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct VectorTables {
+    /// Row id in the `embedding_models` table.
+    id: i64,
+    /// Vector dimension of the model.
+    pub(super) dimension: usize,
+    /// Distance metric, normalization and quantization of the stored vectors.
+    pub(super) settings: VectorSettings,
+}
+
+impl VectorTables {
+    /// Prepares a vector for storage or querying (normalizes if enabled).
+    pub(super) fn prepare(&self, vector: &[f32]) -> Vec<f32> {
+        if self.settings.normalize {
+            normalize(vector)
+        } else {
+            vector.to_vec()
+        }
+    }
+
+    /// Converts a distance from these tables into a similarity in `0..=1`.
+    pub(super) fn similarity(&self, distance: f32) -> f32 {
+        self.settings.similarity(distance)
+    }
+
+    /// Name of the table holding manpage summary vectors.
+    pub(super) fn manpages(&self) -> String {
+        format!("manpages_vec_{}", self.id)
+    }
+
+    /// Name of the table holding chunk vectors.
+    pub(super) fn chunks(&self) -> String {
+        format!("chunks_vec_{}", self.id)
+    }
+
+    /// Name of the table holding full-precision manpage vectors (quantized only).
+    pub(super) fn full_manpages(&self) -> String {
+        format!("manpages_full_{}", self.id)
+    }
+
+    /// Name of the table holding full-precision chunk vectors (quantized only).
+    pub(super) fn full_chunks(&self) -> String {
+        format!("chunks_full_{}", self.id)
+    }
+
+    /// Checks if the vectors searched first are quantized.
+    pub(super) fn is_quantized(&self) -> bool {
+        self.settings.quantization != Quantization::None
+    }
+}
+
+/// Creates the registry table if it doesn't exist yet.
+pub(super) fn create_registry(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS embedding_models (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            dimension INTEGER NOT NULL,
+            metric TEXT NOT NULL,
+            normalized INTEGER NOT NULL,
+            quantization TEXT NOT NULL
+        )",
+        [],
+    )
+    .context("Failed to create embedding models table")?;
+
+    Ok(())
+}
+
+/// Returns the vector tables of a model, if it has any.
+pub(super) fn find(conn: &Connection, model: &str) -> Result<Option<VectorTables>> {
+    conn.query_row(
+        "SELECT id, dimension, metric, normalized, quantization
+         FROM embedding_models WHERE name = ?1",
+        [model],
+        read_tables,
+    )
+    .optional()
+    .with_context(|| format!("Failed to look up embedding model '{model}'"))
+}
+
+/// Returns the vector tables of a model, creating them if needed.
+///
+/// If the model was registered with a different dimension (e.g., a model
+/// tag was re-pulled with another size) or different vector settings, its
+/// vectors are dropped.
+pub(super) fn register(
+    conn: &Connection,
+    model: &str,
+    dimension: usize,
+    settings: VectorSettings,
+) -> Result<VectorTables> {
+    if let Some(tables) = find(conn, model)? {
+        if tables.dimension == dimension && tables.settings == settings {
+            return Ok(tables);
+        }
+
+        info!(
+            model = %model,
+            old_dimension = tables.dimension,
+            new_dimension = dimension,
+            old_metric = tables.settings.metric.as_str(),
+            new_metric = settings.metric.as_str(),
+            old_quantization = tables.settings.quantization.as_str(),
+            new_quantization = settings.quantization.as_str(),
+            "Vector layout changed, dropping vectors"
+        );
+        drop_vector_tables(conn, &tables)?;
+        conn.execute(
+            "UPDATE embedding_models
+             SET dimension = ?1, metric = ?2, normalized = ?3, quantization = ?4
+             WHERE id = ?5",
+            rusqlite::params![
+                dimension,
+                settings.metric.as_str(),
+                settings.normalize,
+                settings.quantization.as_str(),
+                tables.id
+            ],
+        )
+        .context("Failed to update embedding model")?;
+
+        let tables = VectorTables {
+            dimension,
+            settings,
+            ..tables
+        };
+        create_vector_tables(conn, &tables)?;
+        return Ok(tables);
+    }
+
+    conn.execute(
+        "INSERT INTO embedding_models (name, dimension, metric, normalized, quantization)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            model,
+            dimension,
+            settings.metric.as_str(),
+            settings.normalize,
+            settings.quantization.as_str()
+        ],
+    )
+    .context("Failed to register embedding model")?;
+
+    let tables = VectorTables {
+        id: conn.last_insert_rowid(),
+        dimension,
+        settings,
+    };
+    create_vector_tables(conn, &tables)?;
+
+    info!(model = %model, dimension = dimension, metric = settings.metric.as_str(), "Registered embedding model");
+
+    Ok(tables)
+}
+
+/// Returns the vector tables of all registered models.
+pub(super) fn all(conn: &Connection) -> Result<Vec<VectorTables>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, dimension, metric, normalized, quantization
+             FROM embedding_models ORDER BY id",
+        )
+        .context("Failed to prepare embedding models query")?;
+
+    let rows = stmt
+        .query_map([], read_tables)
+        .context("Failed to query embedding models")?;
+
+    rows.map(|r| r.context("Failed to read embedding model"))
+        .collect()
+}
+
+/// Returns the names of all registered models.
+pub(super) fn model_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM embedding_models ORDER BY id")
+        .context("Failed to prepare embedding models query")?;
+
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .context("Failed to query embedding models")?;
+
+    rows.map(|r| r.context("Failed to read embedding model"))
+        .collect()
+}
+
+/// Reads a registry row selected as
+/// `id, dimension, metric, normalized, quantization`.
+fn read_tables(row: &Row<'_>) -> rusqlite::Result<VectorTables> {
+    Ok(VectorTables {
+        id: row.get(0)?,
+        dimension: row.get(1)?,
+        settings: VectorSettings {
+            metric: parse_column(row, 2)?,
+            normalize: row.get(3)?,
+            quantization: parse_column(row, 4)?,
+        },
+    })
+}
+
+/// Parses a text column with [`std::str::FromStr`].
+fn parse_column<T>(row: &Row<'_>, index: usize) -> rusqlite::Result<T>
+where
+    T: std::str::FromStr<Err = anyhow::Error>,
+{
+    row.get::<_, String>(index)?
+        .parse()
+        .map_err(|e: anyhow::Error| {
+            rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into())
+        })
+}
+
+/// Scales a vector to unit length; zero vectors are returned unchanged.
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+/// Creates the vector tables of a model.
+fn create_vector_tables(conn: &Connection, tables: &VectorTables) -> Result<()> {
+    let column = quantize::column_type(tables.settings, tables.dimension);
+    for table in [tables.manpages(), tables.chunks()] {
+        conn.execute(
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS {table} USING vec0(
+                    id INTEGER PRIMARY KEY,
+                    embedding {column}
+                )"
+            ),
+            [],
+        )
+        .with_context(|| format!("Failed to create vector table {table}"))?;
+    }
+
+    if tables.is_quantized() {
+        for table in [tables.full_manpages(), tables.full_chunks()] {
+            conn.execute(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {table} (
+                        id INTEGER PRIMARY KEY,
+                        embedding BLOB NOT NULL
+                    )"
+                ),
+                [],
+            )
+            .with_context(|| format!("Failed to create vector table {table}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Drops the vector tables of a model.
+pub(super) fn drop_vector_tables(conn: &Connection, tables: &VectorTables) -> Result<()> {
+    for table in [
+        tables.manpages(),
+        tables.chunks(),
+        tables.full_manpages(),
+        tables.full_chunks(),
+    ] {
+        conn.execute(&format!("DROP TABLE IF EXISTS {table}"), [])
+            .with_context(|| format!("Failed to drop vector table {table}"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_connection;
+    use crate::db::schema::table_exists;
+    use crate::setup::DistanceMetric;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_register_creates_tables_per_model() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_registry(&conn).unwrap();
+
+        let nomic = register(&conn, "nomic-embed-text", 8, VectorSettings::default()).unwrap();
+        let mxbai = register(&conn, "mxbai-embed-large", 16, VectorSettings::default()).unwrap();
+
+        assert_ne!(nomic, mxbai);
+        assert!(table_exists(&conn, &nomic.manpages()).unwrap());
+        assert!(table_exists(&conn, &mxbai.chunks()).unwrap());
+        assert_eq!(
+            register(&conn, "nomic-embed-text", 8, VectorSettings::default()).unwrap(),
+            nomic
+        );
+        assert_eq!(find(&conn, "mxbai-embed-large").unwrap(), Some(mxbai));
+        assert_eq!(find(&conn, "all-minilm").unwrap(), None);
+        assert_eq!(
+            model_names(&conn).unwrap(),
+            vec!["nomic-embed-text", "mxbai-embed-large"]
+        );
+        assert_eq!(all(&conn).unwrap(), vec![nomic, mxbai]);
+    }
+
+    #[test]
+    fn test_register_replaces_tables_on_dimension_change() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_registry(&conn).unwrap();
+
+        let old = register(&conn, "nomic-embed-text", 8, VectorSettings::default()).unwrap();
+        conn.execute(
+            &format!(
+                "INSERT INTO {} (id, embedding) VALUES (1, ?1)",
+                old.manpages()
+            ),
+            [zerocopy::AsBytes::as_bytes(&[0.5_f32; 8][..])],
+        )
+        .unwrap();
+
+        let new = register(&conn, "nomic-embed-text", 4, VectorSettings::default()).unwrap();
+        assert_eq!(new.dimension, 4);
+        assert_eq!(new.manpages(), old.manpages());
+
+        let count: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", new.manpages()),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_register_replaces_tables_on_metric_change() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_registry(&conn).unwrap();
+
+        register(&conn, "nomic-embed-text", 8, VectorSettings::default()).unwrap();
+        let l1 = VectorSettings {
+            metric: DistanceMetric::L1,
+            normalize: false,
+            ..VectorSettings::default()
+        };
+        let tables = register(&conn, "nomic-embed-text", 8, l1).unwrap();
+
+        assert_eq!(tables.settings, l1);
+        assert_eq!(find(&conn, "nomic-embed-text").unwrap(), Some(tables));
+    }
+
+    #[test]
+    fn test_prepare_normalizes_vectors() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_registry(&conn).unwrap();
+
+        let tables = register(&conn, "nomic-embed-text", 2, VectorSettings::default()).unwrap();
+        assert_eq!(tables.prepare(&[3.0, 4.0]), vec![0.6, 0.8]);
+        assert_eq!(tables.prepare(&[0.0, 0.0]), vec![0.0, 0.0]);
+
+        let raw = VectorSettings {
+            metric: DistanceMetric::L2,
+            normalize: false,
+            ..VectorSettings::default()
+        };
+        let tables = register(&conn, "all-minilm", 2, raw).unwrap();
+        assert_eq!(tables.prepare(&[3.0, 4.0]), vec![3.0, 4.0]);
+    }
+}
@@ -0,0 +1,762 @@
+//! Schema versioning and migrations for the index database.
+//!
+//! The schema version is stored in `PRAGMA user_version`. Databases created
+//! by older versions of ulm are upgraded in place when possible; otherwise
+//! opening them fails with a single "rebuild required" error.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use tracing::info;
+
+use super::registry;
+use crate::setup::locale::DEFAULT_LANGUAGE;
+use crate::setup::{DistanceMetric, Quantization, VectorSettings};
+
+/// Current schema version of the index database.
+pub const SCHEMA_VERSION: i32 = 8;
+
+/// Error message for databases that cannot be upgraded in place.
+const REBUILD_REQUIRED: &str = "Index rebuild required: the index was built by an incompatible \
+     version of ulm.\nRun 'ulm update' to rebuild it.";
+
+/// Vector settings of indexes built before they were configurable.
+const LEGACY_VECTOR_SETTINGS: VectorSettings = VectorSettings {
+    metric: DistanceMetric::L2,
+    normalize: false,
+    quantization: Quantization::None,
+};
+
+/// A migration upgrading the schema from version `n` to `n + 1`.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Migrations in order; `MIGRATIONS[n]` upgrades version `n` to `n + 1`.
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
+
+/// State of the schema of an existing database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum SchemaStatus {
+    /// No index tables exist yet.
+    Missing,
+    /// Schema is at [`SCHEMA_VERSION`].
+    Current,
+    /// Schema is at an older version that can be upgraded in place.
+    Outdated(i32),
+    /// Schema cannot be upgraded; the index must be rebuilt.
+    RebuildRequired,
+}
+
+/// Determines the schema status of the database.
+pub(super) fn schema_status(conn: &Connection) -> Result<SchemaStatus> {
+    let version = schema_version(conn)?;
+
+    if version == SCHEMA_VERSION {
+        return Ok(SchemaStatus::Current);
+    }
+
+    if version > SCHEMA_VERSION {
+        // Written by a newer ulm
+        return Ok(SchemaStatus::RebuildRequired);
+    }
+
+    if version == 0 {
+        // Databases before versioning: only those tracking source paths
+        // (incremental updates) can be upgraded
+        if !table_exists(conn, "manpages")? {
+            return Ok(SchemaStatus::Missing);
+        }
+        if !has_column(conn, "manpages", "source_path")? {
+            return Ok(SchemaStatus::RebuildRequired);
+        }
+    }
+
+    Ok(SchemaStatus::Outdated(version))
+}
+
+/// Upgrades the database schema to [`SCHEMA_VERSION`] in place.
+///
+/// Each migration runs in its own transaction together with the version bump.
+///
+/// # Errors
+///
+/// Returns a "rebuild required" error if the schema cannot be upgraded,
+/// or an error if a migration fails.
+pub(super) fn migrate(conn: &mut Connection) -> Result<()> {
+    let from = match schema_status(conn)? {
+        SchemaStatus::Missing | SchemaStatus::Current => return Ok(()),
+        SchemaStatus::RebuildRequired => anyhow::bail!(REBUILD_REQUIRED),
+        SchemaStatus::Outdated(version) => version,
+    };
+
+    for version in from..SCHEMA_VERSION {
+        let index = usize::try_from(version).context("Invalid schema version")?;
+        let migration = MIGRATIONS
+            .get(index)
+            .with_context(|| format!("No migration from schema version {version}"))?;
+
+        let tx = conn.transaction().context("Failed to start transaction")?;
+        migration(&tx).with_context(|| {
+            format!(
+                "Failed to migrate index from schema version {version} to {}",
+                version + 1
+            )
+        })?;
+        set_schema_version(&tx, version + 1)?;
+        tx.commit().context("Failed to commit migration")?;
+
+        info!(from = version, to = version + 1, "Migrated index schema");
+    }
+
+    Ok(())
+}
+
+/// Version 0 → 1: stored manpage text, full-text table and file records.
+fn migrate_v0_to_v1(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "manpages", "content")? {
+        conn.execute("ALTER TABLE manpages ADD COLUMN content TEXT", [])
+            .context("Failed to add content column")?;
+    }
+
+    if !table_exists(conn, "manpages_fts")? {
+        create_fts_table(conn)?;
+        conn.execute(
+            "INSERT INTO manpages_fts (rowid, tool_name, description)
+             SELECT id, tool_name, description FROM manpages",
+            [],
+        )
+        .context("Failed to populate full-text table")?;
+    }
+
+    create_file_table(conn)
+}
+
+/// Version 1 → 2: chunk-level embeddings.
+///
+/// File records are cleared so the next update re-extracts every manpage
+/// into chunks.
+fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+    let vector_dim = vector_dimension(conn, "manpages_vec")?;
+    create_chunk_table(conn)?;
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_vec USING vec0(
+                id INTEGER PRIMARY KEY,
+                embedding FLOAT[{vector_dim}]
+            )"
+        ),
+        [],
+    )
+    .context("Failed to create chunk vector table")?;
+
+    conn.execute("DELETE FROM indexed_files", [])
+        .context("Failed to reset file records")?;
+
+    Ok(())
+}
+
+/// Version 2 → 3: `(tool_name, section)` identifies a manpage.
+///
+/// Duplicate pages (the same page found in several directories) are
+/// removed, keeping the first one indexed.
+fn migrate_v2_to_v3(conn: &Connection) -> Result<()> {
+    let duplicates = "SELECT id FROM manpages
+        WHERE id NOT IN (SELECT MIN(id) FROM manpages GROUP BY tool_name, section)";
+
+    conn.execute_batch(&format!(
+        "DELETE FROM chunks_vec WHERE id IN
+            (SELECT id FROM chunks WHERE manpage_id IN ({duplicates}));
+        DELETE FROM chunks WHERE manpage_id IN ({duplicates});
+        DELETE FROM manpages_vec WHERE id IN ({duplicates});
+        DELETE FROM manpages_fts WHERE rowid IN ({duplicates});
+        DELETE FROM indexed_files WHERE source_path IN
+            (SELECT source_path FROM manpages WHERE id IN ({duplicates}));
+        DELETE FROM manpages WHERE id IN ({duplicates});"
+    ))
+    .context("Failed to remove duplicate manpages")?;
+
+    // Extended by the language in version 8
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS manpages_identity ON manpages (tool_name, section)",
+        [],
+    )
+    .context("Failed to create manpage identity index")?;
+
+    Ok(())
+}
+
+/// Version 3 → 4: vector tables per embedding model.
+///
+/// Existing vectors are moved to the tables of the embedding model most
+/// files were indexed with. Without file records the model is unknown, so
+/// the vectors are dropped and re-embedded on the next update.
+fn migrate_v3_to_v4(conn: &Connection) -> Result<()> {
+    registry::create_registry(conn)?;
+
+    let model: Option<String> = conn
+        .query_row(
+            "SELECT embedding_model FROM indexed_files
+            GROUP BY embedding_model ORDER BY COUNT(*) DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to determine embedding model")?;
+
+    if let Some(model) = model {
+        let tables = registry::register(
+            conn,
+            &model,
+            vector_dimension(conn, "manpages_vec")?,
+            LEGACY_VECTOR_SETTINGS,
+        )?;
+        conn.execute_batch(&format!(
+            "INSERT INTO {} (id, embedding) SELECT id, embedding FROM manpages_vec;
+            INSERT INTO {} (id, embedding) SELECT id, embedding FROM chunks_vec;",
+            tables.manpages(),
+            tables.chunks()
+        ))
+        .context("Failed to move vectors to model tables")?;
+    }
+
+    conn.execute_batch("DROP TABLE chunks_vec; DROP TABLE manpages_vec;")
+        .context("Failed to drop shared vector tables")?;
+
+    Ok(())
+}
+
+/// Version 4 → 5: distance metric and normalization per embedding model.
+///
+/// Existing vectors were stored raw with L2 distance and keep working; they
+/// are re-embedded when the update runs with different vector settings.
+fn migrate_v4_to_v5(conn: &Connection) -> Result<()> {
+    // Registries created by the v3 → v4 migration already have the columns
+    if has_column(conn, "embedding_models", "metric")? {
+        return Ok(());
+    }
+
+    conn.execute_batch(&format!(
+        "ALTER TABLE embedding_models ADD COLUMN metric TEXT NOT NULL DEFAULT '{}';
+        ALTER TABLE embedding_models ADD COLUMN normalized INTEGER NOT NULL DEFAULT {};",
+        LEGACY_VECTOR_SETTINGS.metric.as_str(),
+        i32::from(LEGACY_VECTOR_SETTINGS.normalize)
+    ))
+    .context("Failed to add vector settings to embedding models")?;
+
+    Ok(())
+}
+
+/// Version 5 → 6: quantization mode per embedding model.
+///
+/// Existing vectors are unquantized.
+fn migrate_v5_to_v6(conn: &Connection) -> Result<()> {
+    // Registries created by earlier migrations already have the column
+    if has_column(conn, "embedding_models", "quantization")? {
+        return Ok(());
+    }
+
+    conn.execute(
+        &format!(
+            "ALTER TABLE embedding_models ADD COLUMN quantization TEXT NOT NULL DEFAULT '{}'",
+            Quantization::None.as_str()
+        ),
+        [],
+    )
+    .context("Failed to add quantization to embedding models")?;
+
+    Ok(())
+}
+
+/// Version 6 → 7: alias names of manpages.
+///
+/// Aliases are filled in by the next update.
+fn migrate_v6_to_v7(conn: &Connection) -> Result<()> {
+    create_alias_table(conn)
+}
+
+/// Version 7 → 8: language of localized manpages.
+///
+/// Existing pages are untranslated; `(tool_name, section, language)`
+/// becomes the identity of a manpage.
+fn migrate_v7_to_v8(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "manpages", "language")? {
+        conn.execute(
+            &format!(
+                "ALTER TABLE manpages ADD COLUMN language TEXT NOT NULL DEFAULT '{DEFAULT_LANGUAGE}'"
+            ),
+            [],
+        )
+        .context("Failed to add language column")?;
+    }
+
+    conn.execute("DROP INDEX IF EXISTS manpages_identity", [])
+        .context("Failed to drop manpage identity index")?;
+    create_identity_index(conn)
+}
+
+/// Creates all index tables if they don't exist yet and marks the schema current.
+///
+/// Vector tables are created per embedding model by [`registry::register`].
+pub(super) fn create_tables(conn: &Connection) -> Result<()> {
+    // Create metadata table
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS manpages (
+                id INTEGER PRIMARY KEY,
+                tool_name TEXT NOT NULL,
+                section TEXT NOT NULL,
+                description TEXT NOT NULL,
+                source_path TEXT NOT NULL UNIQUE,
+                content TEXT,
+                language TEXT NOT NULL DEFAULT '{DEFAULT_LANGUAGE}'
+            )"
+        ),
+        [],
+    )
+    .context("Failed to create manpages table")?;
+
+    create_identity_index(conn)?;
+    create_chunk_table(conn)?;
+    create_fts_table(conn)?;
+    create_file_table(conn)?;
+    create_alias_table(conn)?;
+    registry::create_registry(conn)?;
+
+    set_schema_version(conn, SCHEMA_VERSION)
+}
+
+/// Drops all index tables.
+pub(super) fn drop_tables(conn: &Connection) -> Result<()> {
+    if table_exists(conn, "embedding_models")? {
+        for tables in registry::all(conn)? {
+            registry::drop_vector_tables(conn, &tables)?;
+        }
+    }
+
+    // Shared vector tables are from schema versions before 4
+    for table in [
+        "embedding_models",
+        "aliases",
+        "indexed_files",
+        "chunks_vec",
+        "chunks",
+        "manpages_fts",
+        "manpages_vec",
+        "manpages",
+    ] {
+        conn.execute(&format!("DROP TABLE IF EXISTS {table}"), [])
+            .with_context(|| format!("Failed to drop {table} table"))?;
+    }
+
+    Ok(())
+}
+
+/// Makes `(tool_name, section, language)` the unique identity of a manpage.
+fn create_identity_index(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS manpages_identity
+         ON manpages (tool_name, section, language)",
+        [],
+    )
+    .context("Failed to create manpage identity index")?;
+
+    Ok(())
+}
+
+/// Creates the table for manpage chunks.
+fn create_chunk_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chunks (
+            id INTEGER PRIMARY KEY,
+            manpage_id INTEGER NOT NULL,
+            heading TEXT NOT NULL,
+            text TEXT NOT NULL
+        )",
+        [],
+    )
+    .context("Failed to create chunks table")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS chunks_manpage_id ON chunks (manpage_id)",
+        [],
+    )
+    .context("Failed to create chunks index")?;
+
+    Ok(())
+}
+
+/// Reads the vector dimension from the definition of a vector table.
+fn vector_dimension(conn: &Connection, table: &str) -> Result<usize> {
+    let sql: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE name = ?1",
+            [table],
+            |row| row.get(0),
+        )
+        .context("Failed to read vector table definition")?;
+
+    sql.split_once("FLOAT[")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .and_then(|(dim, _)| dim.trim().parse().ok())
+        .context("Failed to parse vector dimension")
+}
+
+/// Creates the full-text table for lexical search.
+fn create_fts_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS manpages_fts USING fts5(tool_name, description)",
+        [],
+    )
+    .context("Failed to create full-text table")?;
+
+    Ok(())
+}
+
+/// Creates the table tracking indexed manpage files.
+fn create_file_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS indexed_files (
+            source_path TEXT PRIMARY KEY,
+            hash TEXT NOT NULL,
+            mtime INTEGER,
+            embedding_model TEXT NOT NULL,
+            indexed_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .context("Failed to create indexed files table")?;
+
+    Ok(())
+}
+
+/// Creates the table of alias names (symlinks and `.so` redirects) of manpages.
+fn create_alias_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS aliases (
+            manpage_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            section TEXT NOT NULL
+        )",
+        [],
+    )
+    .context("Failed to create aliases table")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS aliases_name ON aliases (name)",
+        [],
+    )
+    .context("Failed to create aliases index")?;
+
+    Ok(())
+}
+
+/// Reads the schema version from `PRAGMA user_version`.
+fn schema_version(conn: &Connection) -> Result<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .context("Failed to read schema version")
+}
+
+/// Writes the schema version to `PRAGMA user_version`.
+fn set_schema_version(conn: &Connection, version: i32) -> Result<()> {
+    conn.execute_batch(&format!("PRAGMA user_version = {version}"))
+        .context("Failed to write schema version")
+}
+
+/// Checks if a table (regular or virtual) exists.
+pub(super) fn table_exists(conn: &Connection, name: &str) -> Result<bool> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+            [name],
+            |row| row.get(0),
+        )
+        .context("Failed to check table existence")?;
+
+    Ok(count > 0)
+}
+
+/// Checks if a table has a column with the given name.
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("Failed to inspect {table} table"))?;
+
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_connection;
+    use tempfile::TempDir;
+
+    /// Creates the schema written by ulm before versioning was introduced.
+    fn create_unversioned_schema(conn: &Connection) {
+        conn.execute_batch(
+            "CREATE TABLE manpages (
+                id INTEGER PRIMARY KEY,
+                tool_name TEXT NOT NULL,
+                section TEXT NOT NULL,
+                description TEXT NOT NULL,
+                source_path TEXT NOT NULL UNIQUE
+            );
+            CREATE VIRTUAL TABLE manpages_vec USING vec0(
+                id INTEGER PRIMARY KEY,
+                embedding FLOAT[8]
+            );
+            INSERT INTO manpages (tool_name, section, description, source_path)
+            VALUES ('ls', '1', 'list directory contents', '/man1/ls.1');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_schema_status_missing() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        assert_eq!(schema_status(&conn).unwrap(), SchemaStatus::Missing);
+    }
+
+    #[test]
+    fn test_create_tables_sets_current_version() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        create_tables(&conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(schema_status(&conn).unwrap(), SchemaStatus::Current);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_schema() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_unversioned_schema(&conn);
+
+        assert_eq!(schema_status(&conn).unwrap(), SchemaStatus::Outdated(0));
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&conn, "manpages", "content").unwrap());
+        assert!(table_exists(&conn, "indexed_files").unwrap());
+        assert!(table_exists(&conn, "chunks").unwrap());
+        assert!(!table_exists(&conn, "manpages_vec").unwrap());
+
+        let fts_rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM manpages_fts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fts_rows, 1);
+    }
+
+    #[test]
+    fn test_vector_dimension() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_unversioned_schema(&conn);
+
+        assert_eq!(vector_dimension(&conn, "manpages_vec").unwrap(), 8);
+    }
+
+    #[test]
+    fn test_migrate_v1_resets_file_records() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_unversioned_schema(&conn);
+        migrate_v0_to_v1(&conn).unwrap();
+        set_schema_version(&conn, 1).unwrap();
+        conn.execute(
+            "INSERT INTO indexed_files VALUES ('/man1/ls.1', 'abc', NULL, 'model', 0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let records: i64 = conn
+            .query_row("SELECT COUNT(*) FROM indexed_files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(records, 0);
+        assert!(table_exists(&conn, "chunks").unwrap());
+    }
+
+    #[test]
+    fn test_migrate_v2_removes_duplicate_pages() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_unversioned_schema(&conn);
+        migrate_v0_to_v1(&conn).unwrap();
+        migrate_v1_to_v2(&conn).unwrap();
+        set_schema_version(&conn, 2).unwrap();
+        conn.execute(
+            "INSERT INTO manpages (tool_name, section, description, source_path)
+             VALUES ('ls', '1', 'list directory contents', '/local/man1/ls.1')",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let path: String = conn
+            .query_row("SELECT source_path FROM manpages", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(path, "/man1/ls.1");
+        assert!(conn
+            .execute(
+                "INSERT INTO manpages (tool_name, section, description, source_path)
+                 VALUES ('ls', '1', 'duplicate', '/other/man1/ls.1')",
+                [],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_migrate_v3_moves_vectors_to_model_tables() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_unversioned_schema(&conn);
+        migrate_v0_to_v1(&conn).unwrap();
+        migrate_v1_to_v2(&conn).unwrap();
+        migrate_v2_to_v3(&conn).unwrap();
+        set_schema_version(&conn, 3).unwrap();
+        conn.execute(
+            "INSERT INTO manpages_vec (id, embedding) VALUES (1, ?1)",
+            [zerocopy::AsBytes::as_bytes(&[0.5_f32; 8][..])],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO indexed_files VALUES ('/man1/ls.1', 'abc', NULL, 'nomic-embed-text', 0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let tables = registry::find(&conn, "nomic-embed-text").unwrap().unwrap();
+        assert_eq!(tables.dimension, 8);
+        assert_eq!(tables.settings, LEGACY_VECTOR_SETTINGS);
+        let vectors: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", tables.manpages()),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(vectors, 1);
+        assert!(!table_exists(&conn, "manpages_vec").unwrap());
+        assert!(!table_exists(&conn, "chunks_vec").unwrap());
+    }
+
+    #[test]
+    fn test_migrate_v4_adds_vector_settings() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TABLE embedding_models;
+            CREATE TABLE embedding_models (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                dimension INTEGER NOT NULL
+            );
+            INSERT INTO embedding_models (name, dimension) VALUES ('nomic-embed-text', 8);",
+        )
+        .unwrap();
+        set_schema_version(&conn, 4).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let tables = registry::find(&conn, "nomic-embed-text").unwrap().unwrap();
+        assert_eq!(tables.settings, LEGACY_VECTOR_SETTINGS);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v6_adds_aliases() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute("DROP TABLE aliases", []).unwrap();
+        set_schema_version(&conn, 6).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert!(table_exists(&conn, "aliases").unwrap());
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v7_adds_language() {
+        let temp = TempDir::new().unwrap();
+        let conn = open_connection(&temp.path().join("index.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE manpages (
+                id INTEGER PRIMARY KEY,
+                tool_name TEXT NOT NULL,
+                section TEXT NOT NULL,
+                description TEXT NOT NULL,
+                source_path TEXT NOT NULL UNIQUE,
+                content TEXT
+            );
+            CREATE UNIQUE INDEX manpages_identity ON manpages (tool_name, section);
+            INSERT INTO manpages (tool_name, section, description, source_path)
+                VALUES ('ls', '1', 'list', '/man1/ls.1');",
+        )
+        .unwrap();
+
+        migrate_v7_to_v8(&conn).unwrap();
+
+        let language: String = conn
+            .query_row("SELECT language FROM manpages", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(language, DEFAULT_LANGUAGE);
+        conn.execute(
+            "INSERT INTO manpages (tool_name, section, description, source_path, language)
+             VALUES ('ls', '1', 'auflisten', '/de/man1/ls.1', 'de')",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_requires_rebuild_without_source_paths() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        conn.execute(
+            "CREATE TABLE manpages (id INTEGER PRIMARY KEY, tool_name TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(schema_status(&conn).unwrap(), SchemaStatus::RebuildRequired);
+        let err = migrate(&mut conn).unwrap_err();
+        assert!(err.to_string().contains("rebuild required"));
+    }
+
+    #[test]
+    fn test_migrate_requires_rebuild_for_newer_schema() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+        create_tables(&conn).unwrap();
+        set_schema_version(&conn, SCHEMA_VERSION + 1).unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn test_migrations_cover_all_versions() {
+        assert_eq!(MIGRATIONS.len(), usize::try_from(SCHEMA_VERSION).unwrap());
+    }
+}
@@ -0,0 +1,825 @@
+//! Hybrid lexical + semantic search over the index.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use tracing::debug;
+use zerocopy::AsBytes;
+
+use super::quantize;
+use super::registry::{self, VectorTables};
+use super::{get_database_path, open_index};
+use crate::setup::locale::{self, DEFAULT_LANGUAGE};
+use crate::setup::SearchConfig;
+
+/// Search result from hybrid lexical + semantic search.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// Tool name.
+    pub tool_name: String,
+    /// Man section.
+    pub section: String,
+    /// Language of the page (e.g., "de"; "en" for untranslated pages).
+    pub language: String,
+    /// Description text.
+    pub description: String,
+    /// Fused relevance score (higher is better).
+    pub score: f32,
+    /// Vector similarity in `0..=1` (higher is closer), if the entry was
+    /// among the nearest neighbors.
+    pub similarity: Option<f32>,
+    /// Chunks of the manpage that matched the query, closest first.
+    pub chunks: Vec<ChunkMatch>,
+}
+
+/// Chunk of a manpage that matched the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkMatch {
+    /// Section header the chunk was taken from (e.g., "OPTIONS").
+    pub heading: String,
+    /// Chunk text.
+    pub text: String,
+    /// Vector similarity to the query in `0..=1` (higher is closer).
+    pub similarity: f32,
+}
+
+/// Candidate from one of the rankings, keyed by manpage id.
+#[derive(Debug, Clone)]
+struct Candidate {
+    /// Row id in the manpages table.
+    id: i64,
+    /// Tool name.
+    tool_name: String,
+    /// Man section.
+    section: String,
+    /// Description text.
+    description: String,
+    /// Language of the page.
+    language: String,
+    /// Vector similarity (only set for vector candidates).
+    similarity: Option<f32>,
+    /// Matched chunks (only set for chunk candidates).
+    chunks: Vec<ChunkMatch>,
+}
+
+/// Number of candidates fetched per ranking for each requested result.
+const CANDIDATES_PER_RESULT: usize = 10;
+
+/// Minimum number of candidates fetched per ranking.
+const MIN_CANDIDATES: usize = 50;
+
+/// Maximum number of matched chunks returned per result.
+const MAX_CHUNKS_PER_RESULT: usize = 3;
+
+/// Rank within the vector or chunk ranking up to which a page named in the
+/// query by a common word counts as semantically supported.
+const EXACT_NAME_SUPPORT_RANK: usize = 10;
+
+/// Tool names that are also everyday words ("make a directory", "which
+/// files changed").
+///
+/// Named in a longer query, they only get the exact name bonus with support
+/// from the embeddings, instead of being ranked first.
+const COMMON_WORD_TOOLS: &[&str] = &[
+    "at", "column", "cut", "date", "expand", "expect", "false", "file", "find", "fold", "free",
+    "groups", "head", "host", "info", "install", "join", "kill", "last", "less", "link", "locate",
+    "look", "make", "man", "more", "mount", "nice", "paste", "patch", "print", "read", "rename",
+    "script", "sleep", "sort", "split", "tail", "test", "time", "top", "touch", "true", "units",
+    "users", "wait", "watch", "which", "who", "write", "yes",
+];
+
+/// Performs hybrid search on the index.
+///
+/// Combines vector KNN over the manpage and chunk vectors of the embedding
+/// model `model` with BM25 over `manpages_fts` using weighted reciprocal
+/// rank fusion. Tools named directly in the query get a bonus (see
+/// [`fuse_rankings`]).
+/// Each result carries the chunks of its manpage that matched the query.
+///
+/// # Errors
+///
+/// Returns an error if database operations fail, the index doesn't exist,
+/// or it has no vectors for `model`.
+#[allow(clippy::unused_async)] // Keep async for API compatibility
+pub async fn search(
+    model: &str,
+    query_vector: &[f32],
+    query_text: &str,
+    limit: usize,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let db_path = get_database_path()?;
+
+    let conn = open_index(&db_path)?;
+    let Some(tables) = registry::find(&conn, model)? else {
+        anyhow::bail!(
+            "Index has no embeddings for '{model}'.\n\
+             Run 'ulm update' to embed manpages with it."
+        );
+    };
+
+    hybrid_search(&conn, &tables, query_vector, query_text, limit, config)
+}
+
+/// Runs the hybrid search against an open connection.
+fn hybrid_search(
+    conn: &Connection,
+    tables: &VectorTables,
+    query_vector: &[f32],
+    query_text: &str,
+    limit: usize,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let candidate_count = limit
+        .saturating_mul(CANDIDATES_PER_RESULT)
+        .max(MIN_CANDIDATES);
+
+    // Stored vectors may be normalized; the query must match them
+    let query_vector = tables.prepare(query_vector);
+
+    // A common word on its own still names the tool
+    let names = query_tool_names(query_text);
+    let (common, distinctive): (Vec<String>, Vec<String>) = names
+        .into_iter()
+        .partition(|name| query_text.split_whitespace().count() > 1 && is_common_word(name));
+
+    let rankings = Rankings {
+        vector: vector_candidates(conn, tables, &query_vector, candidate_count)?,
+        chunk: chunk_candidates(conn, tables, &query_vector, candidate_count)?,
+        lexical: lexical_candidates(conn, query_text, candidate_count)?,
+        exact: exact_name_candidates(conn, &distinctive)?,
+        common_exact: exact_name_candidates(conn, &common)?,
+    };
+
+    let results = fuse_rankings(rankings, limit, config);
+
+    debug!(count = results.len(), "Hybrid search completed");
+
+    Ok(results)
+}
+
+/// Returns the nearest neighbors of the query vector.
+fn vector_candidates(
+    conn: &Connection,
+    tables: &VectorTables,
+    query_vector: &[f32],
+    limit: usize,
+) -> Result<Vec<Candidate>> {
+    // Convert query vector to blob
+    let query_blob = query_vector.as_bytes();
+
+    // The vec0 KNN query requires 'k = ?' constraint instead of LIMIT
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH knn AS MATERIALIZED ({})
+            SELECT
+                m.id,
+                m.tool_name,
+                m.section,
+                m.description,
+                m.language,
+                knn.distance
+            FROM knn
+            JOIN manpages m ON m.id = knn.id
+            ORDER BY knn.distance",
+            quantize::knn_query(tables, &tables.manpages(), &tables.full_manpages())
+        ))
+        .context("Failed to prepare search query")?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![query_blob, limit], |row| {
+            Ok(Candidate {
+                id: row.get(0)?,
+                tool_name: row.get(1)?,
+                section: row.get(2)?,
+                description: row.get(3)?,
+                language: row.get(4)?,
+                similarity: Some(tables.similarity(row.get(5)?)),
+                chunks: Vec::new(),
+            })
+        })
+        .context("Failed to execute search")?;
+
+    rows.map(|r| r.context("Failed to read search result"))
+        .collect()
+}
+
+/// Returns the manpages owning the chunks nearest to the query vector.
+///
+/// Manpages are ranked by their closest chunk and carry up to
+/// [`MAX_CHUNKS_PER_RESULT`] matched chunks each.
+fn chunk_candidates(
+    conn: &Connection,
+    tables: &VectorTables,
+    query_vector: &[f32],
+    limit: usize,
+) -> Result<Vec<Candidate>> {
+    let query_blob = query_vector.as_bytes();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH knn AS MATERIALIZED ({})
+            SELECT
+                m.id,
+                m.tool_name,
+                m.section,
+                m.description,
+                m.language,
+                c.heading,
+                c.text,
+                knn.distance
+            FROM knn
+            JOIN chunks c ON c.id = knn.id
+            JOIN manpages m ON m.id = c.manpage_id
+            ORDER BY knn.distance",
+            quantize::knn_query(tables, &tables.chunks(), &tables.full_chunks())
+        ))
+        .context("Failed to prepare chunk search query")?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![query_blob, limit], |row| {
+            let candidate = row_to_candidate(row)?;
+            let chunk = ChunkMatch {
+                heading: row.get(5)?,
+                text: row.get(6)?,
+                similarity: tables.similarity(row.get(7)?),
+            };
+            Ok((candidate, chunk))
+        })
+        .context("Failed to execute chunk search")?;
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for row in rows {
+        let (candidate, chunk) = row.context("Failed to read chunk search result")?;
+        let index = candidates
+            .iter()
+            .position(|c| c.id == candidate.id)
+            .unwrap_or_else(|| {
+                candidates.push(candidate);
+                candidates.len() - 1
+            });
+        if candidates[index].chunks.len() < MAX_CHUNKS_PER_RESULT {
+            candidates[index].chunks.push(chunk);
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Returns BM25-ranked matches of the query terms in tool names and descriptions.
+fn lexical_candidates(conn: &Connection, query_text: &str, limit: usize) -> Result<Vec<Candidate>> {
+    let Some(fts_query) = build_fts_query(query_text) else {
+        return Ok(Vec::new());
+    };
+
+    // bm25() is lower-is-better; tool name matches weigh more than descriptions
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.tool_name, m.section, m.description, m.language
+            FROM manpages_fts f
+            JOIN manpages m ON m.id = f.rowid
+            WHERE manpages_fts MATCH ?1
+            ORDER BY bm25(manpages_fts, 10.0, 1.0)
+            LIMIT ?2",
+        )
+        .context("Failed to prepare full-text query")?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![fts_query, limit], row_to_candidate)
+        .context("Failed to execute full-text search")?;
+
+    rows.map(|r| r.context("Failed to read full-text result"))
+        .collect()
+}
+
+/// Returns entries whose tool name or alias is one of `names`.
+fn exact_name_candidates(conn: &Connection, names: &[String]) -> Result<Vec<Candidate>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, tool_name, section, description, language
+            FROM manpages
+            WHERE tool_name = ?1
+                OR id IN (SELECT manpage_id FROM aliases WHERE name = ?1)
+            ORDER BY section",
+        )
+        .context("Failed to prepare tool name query")?;
+
+    let mut candidates = Vec::new();
+    for name in names {
+        let rows = stmt
+            .query_map([name], row_to_candidate)
+            .context("Failed to execute tool name query")?;
+        for row in rows {
+            candidates.push(row.context("Failed to read tool name result")?);
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Maps a `(id, tool_name, section, description, language)` row to a candidate.
+fn row_to_candidate(row: &rusqlite::Row<'_>) -> rusqlite::Result<Candidate> {
+    Ok(Candidate {
+        id: row.get(0)?,
+        tool_name: row.get(1)?,
+        section: row.get(2)?,
+        description: row.get(3)?,
+        language: row.get(4)?,
+        similarity: None,
+        chunks: Vec::new(),
+    })
+}
+
+/// Builds an FTS5 query matching any of the terms in the user query.
+///
+/// Each term is quoted so FTS5 operators in the query are taken literally.
+fn build_fts_query(query_text: &str) -> Option<String> {
+    let terms: Vec<String> = query_text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2)
+        .map(|t| format!("\"{}\"", t.to_lowercase()))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" OR "))
+    }
+}
+
+/// Extracts words from the query that could name a tool (e.g., "git-commit").
+fn query_tool_names(query_text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for word in query_text.split_whitespace() {
+        let name = word
+            .trim_matches(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')))
+            .trim_end_matches('.');
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Checks if a tool name is also an everyday word (see [`COMMON_WORD_TOOLS`]).
+fn is_common_word(name: &str) -> bool {
+    COMMON_WORD_TOOLS.contains(&name.to_lowercase().as_str())
+}
+
+/// Candidates from each of the rankings combined by hybrid search.
+#[derive(Debug, Default)]
+struct Rankings {
+    /// Nearest neighbors of the manpage summaries.
+    vector: Vec<Candidate>,
+    /// Manpages ordered by their nearest chunk.
+    chunk: Vec<Candidate>,
+    /// BM25 matches of the query terms.
+    lexical: Vec<Candidate>,
+    /// Tools named directly in the query, which are ranked first.
+    exact: Vec<Candidate>,
+    /// Tools named in the query by a common word (see [`COMMON_WORD_TOOLS`]).
+    common_exact: Vec<Candidate>,
+}
+
+/// Combines vector, chunk and lexical rankings with weighted reciprocal rank fusion.
+///
+/// Each ranking contributes `weight / (rrf_k + rank)` for every candidate it
+/// contains; the vector and chunk rankings both use the semantic weight.
+/// A tool named in the query gets the contribution of one more first rank
+/// and is ranked first. A tool named by a common word only gets the
+/// contribution if its page is among the first [`EXACT_NAME_SUPPORT_RANK`]
+/// vector or chunk candidates; otherwise the name match is dropped.
+/// Candidates outside the configured sections are dropped and the fused
+/// score is scaled by the section boost. Of a page indexed in several
+/// languages only one result is kept (see [`prefer_language`]).
+#[allow(clippy::cast_precision_loss)]
+fn fuse_rankings(rankings: Rankings, limit: usize, config: &SearchConfig) -> Vec<SearchResult> {
+    let rrf_k = config.rrf_k as f32;
+    let mut fused: Vec<(Candidate, f32)> = Vec::new();
+
+    let mut add = |candidate: Candidate, contribution: f32| {
+        if !config.includes_section(&candidate.section) {
+            return;
+        }
+        if let Some(existing) = fused.iter_mut().find(|(c, _)| c.id == candidate.id) {
+            existing.1 += contribution;
+            if existing.0.similarity.is_none() {
+                existing.0.similarity = candidate.similarity;
+            }
+            if existing.0.chunks.is_empty() {
+                existing.0.chunks = candidate.chunks;
+            }
+        } else {
+            fused.push((candidate, contribution));
+        }
+    };
+
+    let supported: Vec<i64> = rankings
+        .vector
+        .iter()
+        .take(EXACT_NAME_SUPPORT_RANK)
+        .chain(rankings.chunk.iter().take(EXACT_NAME_SUPPORT_RANK))
+        .map(|c| c.id)
+        .collect();
+
+    for (rank, candidate) in rankings.vector.into_iter().enumerate() {
+        add(
+            candidate,
+            config.semantic_weight / (rrf_k + rank as f32 + 1.0),
+        );
+    }
+    for (rank, candidate) in rankings.chunk.into_iter().enumerate() {
+        add(
+            candidate,
+            config.semantic_weight / (rrf_k + rank as f32 + 1.0),
+        );
+    }
+    for (rank, candidate) in rankings.lexical.into_iter().enumerate() {
+        add(
+            candidate,
+            config.lexical_weight / (rrf_k + rank as f32 + 1.0),
+        );
+    }
+
+    let exact_bonus = config.semantic_weight.max(config.lexical_weight) / (rrf_k + 1.0);
+    let named: Vec<(String, String)> = rankings
+        .exact
+        .iter()
+        .map(|c| (c.tool_name.clone(), c.section.clone()))
+        .collect();
+    for candidate in rankings.exact {
+        add(candidate, exact_bonus);
+    }
+    for candidate in rankings.common_exact {
+        if supported.contains(&candidate.id) {
+            add(candidate, exact_bonus);
+        }
+    }
+
+    for (candidate, score) in &mut fused {
+        *score *= config.section_boost(&candidate.section);
+    }
+
+    // Pages are matched by name and section, since prefer_language may
+    // pick another language version of a named page
+    let is_named = |c: &Candidate| {
+        named
+            .iter()
+            .any(|(tool_name, section)| *tool_name == c.tool_name && *section == c.section)
+    };
+    let mut fused = prefer_language(fused, &config.preferred_language());
+    fused.sort_by(|a, b| {
+        is_named(&b.0)
+            .cmp(&is_named(&a.0))
+            .then_with(|| b.1.total_cmp(&a.1))
+    });
+
+    fused
+        .into_iter()
+        .take(limit)
+        .map(|(c, score)| SearchResult {
+            tool_name: c.tool_name,
+            section: c.section,
+            language: c.language,
+            description: c.description,
+            score,
+            similarity: c.similarity,
+            chunks: c.chunks,
+        })
+        .collect()
+}
+
+/// Keeps one result per page (tool name and section) across languages.
+///
+/// The version in the preferred language wins, then the untranslated one.
+/// The result takes the best score of all versions.
+fn prefer_language(fused: Vec<(Candidate, f32)>, preferred: &str) -> Vec<(Candidate, f32)> {
+    let mut pages: Vec<(Candidate, f32)> = Vec::with_capacity(fused.len());
+
+    for (candidate, score) in fused {
+        let existing = pages
+            .iter_mut()
+            .find(|(c, _)| c.tool_name == candidate.tool_name && c.section == candidate.section);
+        if let Some(existing) = existing {
+            existing.1 = existing.1.max(score);
+            if language_rank(&candidate.language, preferred)
+                < language_rank(&existing.0.language, preferred)
+            {
+                existing.0 = candidate;
+            }
+        } else {
+            pages.push((candidate, score));
+        }
+    }
+
+    pages
+}
+
+/// Ranks a page language: preferred first, then untranslated, then others.
+fn language_rank(language: &str, preferred: &str) -> u8 {
+    if locale::matches_language(language, preferred) {
+        0
+    } else if language == DEFAULT_LANGUAGE {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_connection;
+    use crate::db::tests::{create_test_entries, TEST_MODEL};
+    use crate::db::{rebuild_tables, replace_alias_rows};
+    use crate::setup::{ChunkEntry, DistanceMetric, ManpageAlias, Quantization, VectorSettings};
+    use tempfile::TempDir;
+
+    /// Raw vectors with L2 distance, so the collinear test vectors differ.
+    const RAW_L2: VectorSettings = VectorSettings {
+        metric: DistanceMetric::L2,
+        normalize: false,
+        quantization: Quantization::None,
+    };
+
+    #[test]
+    fn test_build_fts_query() {
+        assert_eq!(
+            build_fts_query("rsync exclude").as_deref(),
+            Some("\"rsync\" OR \"exclude\"")
+        );
+        // Operators and punctuation are not passed through
+        assert_eq!(
+            build_fts_query("jq -s AND \"x").as_deref(),
+            Some("\"jq\" OR \"and\"")
+        );
+        assert_eq!(build_fts_query("? !"), None);
+    }
+
+    #[test]
+    fn test_query_tool_names() {
+        assert_eq!(
+            query_tool_names("how do I use git-commit, jq?"),
+            vec!["how", "do", "I", "use", "git-commit", "jq"]
+        );
+    }
+
+    #[test]
+    fn test_hybrid_search_ranks_named_tool_first() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(20);
+        entries[7].tool_name = "rsync".to_string();
+        entries[7].description = "a fast, versatile, remote file-copying tool".to_string();
+        rebuild_tables(&mut conn, TEST_MODEL, RAW_L2, &entries, &[]).unwrap();
+        let tables = registry::find(&conn, TEST_MODEL).unwrap().unwrap();
+
+        // Query vector is closest to tool0, far from rsync
+        let results = hybrid_search(
+            &conn,
+            &tables,
+            &[0.0; 8],
+            "rsync exclude",
+            3,
+            &SearchConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].tool_name, "rsync");
+        assert!(results[0].similarity.is_some());
+        assert_eq!(results[1].tool_name, "tool0");
+        assert_eq!(results[1].chunks[0].text, "--option0 enables feature 0");
+    }
+
+    #[test]
+    fn test_hybrid_search_common_word_tool_does_not_win() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(20);
+        entries[0].tool_name = "mkdir".to_string();
+        entries[0].description = "make directories".to_string();
+        entries[19].tool_name = "make".to_string();
+        entries[19].description = "GNU make utility to maintain groups of programs".to_string();
+        rebuild_tables(&mut conn, TEST_MODEL, RAW_L2, &entries, &[]).unwrap();
+        let tables = registry::find(&conn, TEST_MODEL).unwrap().unwrap();
+
+        // Query vector is closest to mkdir, farthest from make
+        let search = |query: &str| {
+            hybrid_search(
+                &conn,
+                &tables,
+                &[0.0; 8],
+                query,
+                3,
+                &SearchConfig::default(),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(search("make a directory")[0].tool_name, "mkdir");
+        assert!(is_common_word("Make"));
+        // Naming only the tool still finds it
+        assert_eq!(search("make")[0].tool_name, "make");
+    }
+
+    #[test]
+    fn test_exact_name_candidates_match_aliases() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let entries = create_test_entries(3);
+        rebuild_tables(&mut conn, TEST_MODEL, RAW_L2, &entries, &[]).unwrap();
+        let alias = ManpageAlias {
+            name: "vi".to_string(),
+            section: "1".to_string(),
+            target: entries[1].source_path.clone(),
+        };
+        assert_eq!(replace_alias_rows(&mut conn, &[alias]).unwrap(), 1);
+
+        let candidates = exact_name_candidates(&conn, &query_tool_names("vi open file")).unwrap();
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].tool_name, "tool1");
+    }
+
+    #[test]
+    fn test_chunk_candidates_group_by_manpage() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(3);
+        let extra = ChunkEntry {
+            heading: "EXAMPLES".to_string(),
+            text: "tool2 --option2 file".to_string(),
+            vector: vec![0.25; 8],
+        };
+        entries[2].chunks.push(extra);
+        rebuild_tables(&mut conn, TEST_MODEL, RAW_L2, &entries, &[]).unwrap();
+        let tables = registry::find(&conn, TEST_MODEL).unwrap().unwrap();
+
+        let candidates = chunk_candidates(&conn, &tables, &[0.2; 8], 10).unwrap();
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].tool_name, "tool2");
+        assert_eq!(candidates[0].chunks.len(), 2);
+        assert_eq!(candidates[0].chunks[1].heading, "EXAMPLES");
+    }
+
+    #[test]
+    fn test_vector_candidates_cosine_similarity() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = open_connection(&temp.path().join("index.db")).unwrap();
+
+        let mut entries = create_test_entries(2);
+        entries[0].vector = vec![0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        entries[1].vector = vec![5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        rebuild_tables(
+            &mut conn,
+            TEST_MODEL,
+            VectorSettings::default(),
+            &entries,
+            &[],
+        )
+        .unwrap();
+        let tables = registry::find(&conn, TEST_MODEL).unwrap().unwrap();
+
+        // Length doesn't matter, only direction
+        let query = tables.prepare(&[0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let candidates = vector_candidates(&conn, &tables, &query, 2).unwrap();
+
+        assert_eq!(candidates[0].tool_name, "tool1");
+        assert!((candidates[0].similarity.unwrap() - 1.0).abs() < 1e-6);
+        assert!((candidates[1].similarity.unwrap() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fuse_rankings_weights() {
+        let candidate = |id: i64| Candidate {
+            id,
+            tool_name: format!("tool{id}"),
+            section: "1".to_string(),
+            description: String::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            similarity: None,
+            chunks: Vec::new(),
+        };
+
+        let config = SearchConfig {
+            semantic_weight: 0.0,
+            lexical_weight: 1.0,
+            ..SearchConfig::default()
+        };
+        let rankings = Rankings {
+            vector: vec![candidate(1), candidate(2)],
+            lexical: vec![candidate(2), candidate(1)],
+            ..Rankings::default()
+        };
+        let results = fuse_rankings(rankings, 2, &config);
+        assert_eq!(results[0].tool_name, "tool2");
+
+        // Appearing in both rankings beats appearing in one
+        let rankings = Rankings {
+            vector: vec![candidate(1), candidate(2)],
+            lexical: vec![candidate(3), candidate(2)],
+            ..Rankings::default()
+        };
+        let results = fuse_rankings(rankings, 3, &SearchConfig::default());
+        assert_eq!(results[0].tool_name, "tool2");
+    }
+
+    #[test]
+    fn test_fuse_rankings_exact_names() {
+        let candidate = |id: i64| Candidate {
+            id,
+            tool_name: format!("tool{id}"),
+            section: "1".to_string(),
+            description: String::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            similarity: None,
+            chunks: Vec::new(),
+        };
+
+        // A named tool is ranked first even without any other support
+        let rankings = Rankings {
+            vector: vec![candidate(1), candidate(2)],
+            lexical: vec![candidate(1), candidate(2)],
+            exact: vec![candidate(3)],
+            ..Rankings::default()
+        };
+        let results = fuse_rankings(rankings, 3, &SearchConfig::default());
+        let names: Vec<&str> = results.iter().map(|r| r.tool_name.as_str()).collect();
+        assert_eq!(names, vec!["tool3", "tool1", "tool2"]);
+
+        // A tool named by a common word needs support and isn't pinned
+        let rankings = Rankings {
+            vector: vec![candidate(1), candidate(2)],
+            lexical: vec![candidate(1)],
+            common_exact: vec![candidate(2), candidate(3)],
+            ..Rankings::default()
+        };
+        let results = fuse_rankings(rankings, 3, &SearchConfig::default());
+        let names: Vec<&str> = results.iter().map(|r| r.tool_name.as_str()).collect();
+        assert_eq!(names, vec!["tool1", "tool2"]);
+    }
+
+    #[test]
+    fn test_fuse_rankings_sections() {
+        let candidate = |id: i64, section: &str| Candidate {
+            id,
+            tool_name: format!("tool{id}"),
+            section: section.to_string(),
+            description: String::new(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            similarity: None,
+            chunks: Vec::new(),
+        };
+        let rankings = || Rankings {
+            vector: vec![candidate(1, "3"), candidate(2, "5"), candidate(3, "1p")],
+            ..Rankings::default()
+        };
+
+        let mut config = SearchConfig {
+            sections: vec!["1".to_string(), "5".to_string()],
+            ..SearchConfig::default()
+        };
+        let results = fuse_rankings(rankings(), 3, &config);
+        let names: Vec<&str> = results.iter().map(|r| r.tool_name.as_str()).collect();
+        assert_eq!(names, vec!["tool2", "tool3"]);
+
+        config.section_boost.insert("1".to_string(), 2.0);
+        let results = fuse_rankings(rankings(), 3, &config);
+        assert_eq!(results[0].tool_name, "tool3");
+    }
+
+    #[test]
+    fn test_fuse_rankings_prefers_language() {
+        let candidate = |id: i64, language: &str| Candidate {
+            id,
+            tool_name: "ls".to_string(),
+            section: "1".to_string(),
+            description: String::new(),
+            language: language.to_string(),
+            similarity: None,
+            chunks: Vec::new(),
+        };
+        let rankings = || Rankings {
+            vector: vec![candidate(1, DEFAULT_LANGUAGE), candidate(2, "de")],
+            lexical: vec![candidate(3, "fr")],
+            ..Rankings::default()
+        };
+
+        let mut config = SearchConfig {
+            language: Some("de_DE".to_string()),
+            ..SearchConfig::default()
+        };
+        let results = fuse_rankings(rankings(), 3, &config);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].language, "de");
+
+        config.language = Some("ja".to_string());
+        let results = fuse_rankings(rankings(), 3, &config);
+        assert_eq!(results[0].language, DEFAULT_LANGUAGE);
+    }
+}
@@ -8,6 +8,18 @@
 //! - Terminal UI for suggestion display
 //! - Command execution
 
+// Tests fail loudly on purpose and compare exact fixture values
+#![cfg_attr(
+    test,
+    allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::float_cmp,
+        clippy::unnecessary_cast
+    )
+)]
+
 // Re-export anyhow::Result for convenience
 pub use anyhow::Result;
 
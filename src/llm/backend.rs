@@ -0,0 +1,300 @@
+//! Backend traits for text generation and embeddings.
+//!
+//! The query pipeline and the indexer only talk to a [`TextGenerator`] and
+//! an [`Embedder`]; [`text_generator`] and [`embedder`] pick the
+//! implementation from the `backend` key of the config.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::Value;
+
+use crate::llm::ollama::{GenerateOptions, OllamaClient};
+use crate::llm::openai::{OpenAiClient, SamplingOptions};
+use crate::llm::prompt::ChatMessage;
+use crate::llm::stream::TokenStream;
+use crate::setup::config::{Backend, Config, ModelOptions};
+
+/// Generates text with an LLM.
+pub trait TextGenerator: Debug + Send + Sync {
+    /// Generates the complete reply to a chat conversation.
+    ///
+    /// With a `format`, the output is constrained to JSON (`"json"`) or to a
+    /// JSON schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    fn chat<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<String>>;
+
+    /// Generates the reply to a chat conversation, streaming the tokens as
+    /// they arrive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails. Errors while reading the
+    /// stream are yielded by the stream.
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<TokenStream>>;
+
+    /// Looks up the context length the model was trained with.
+    ///
+    /// Backends that can't report it return `None`, the default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    fn context_length<'a>(&'a self, _model: &'a str) -> BoxFuture<'a, Result<Option<u32>>> {
+        async { Ok(None) }.boxed()
+    }
+}
+
+/// Generates embedding vectors.
+pub trait Embedder: Debug + Send + Sync {
+    /// Generates the embedding of a text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    fn embed<'a>(&'a self, model: &'a str, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>>;
+
+    /// Generates the embeddings of several texts, in input order.
+    ///
+    /// Backends that accept several inputs per request send one request;
+    /// the default embeds the texts one by one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any request fails or response cannot be parsed.
+    fn embed_batch<'a>(
+        &'a self,
+        model: &'a str,
+        texts: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        async move {
+            let mut vectors = Vec::with_capacity(texts.len());
+            for text in texts {
+                vectors.push(self.embed(model, text).await?);
+            }
+            Ok(vectors)
+        }
+        .boxed()
+    }
+}
+
+impl TextGenerator for OllamaClient {
+    fn chat<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<String>> {
+        Self::chat(self, model, messages, format).boxed()
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<TokenStream>> {
+        Self::chat_stream(self, model, messages, format).boxed()
+    }
+
+    fn context_length<'a>(&'a self, model: &'a str) -> BoxFuture<'a, Result<Option<u32>>> {
+        async move { Ok(self.show_model(model).await?.context_length()) }.boxed()
+    }
+}
+
+// Single texts also go through `/api/embed`, so query and index vectors come
+// from the same endpoint
+impl Embedder for OllamaClient {
+    fn embed<'a>(&'a self, model: &'a str, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>> {
+        async move {
+            self.generate_embeddings(model, &[text.to_string()])
+                .await?
+                .into_iter()
+                .next()
+                .context("Ollama returned no embedding")
+        }
+        .boxed()
+    }
+
+    fn embed_batch<'a>(
+        &'a self,
+        model: &'a str,
+        texts: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        self.generate_embeddings(model, texts).boxed()
+    }
+}
+
+impl TextGenerator for OpenAiClient {
+    fn chat<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<String>> {
+        Self::chat(self, model, messages, format).boxed()
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        format: Option<&'a Value>,
+    ) -> BoxFuture<'a, Result<TokenStream>> {
+        Self::chat_stream(self, model, messages, format).boxed()
+    }
+}
+
+impl Embedder for OpenAiClient {
+    fn embed<'a>(&'a self, model: &'a str, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>> {
+        self.generate_embedding(model, text).boxed()
+    }
+
+    fn embed_batch<'a>(
+        &'a self,
+        model: &'a str,
+        texts: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        self.generate_embeddings(model, texts).boxed()
+    }
+}
+
+/// Creates the Ollama client configured in `config`.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client cannot be created.
+fn ollama_client(config: &Config) -> Result<OllamaClient> {
+    OllamaClient::with_config(
+        config.ollama_url(),
+        config.generate_timeout_secs(),
+        config.embedding_timeout_secs(),
+    )
+    .map(|client| {
+        let options = &config.models.options;
+        client
+            .with_think(config.models.think(config.llm_model()))
+            .with_options(generate_options(options), options.keep_alive.clone())
+    })
+    .context("Failed to create Ollama client")
+}
+
+/// Converts the configured model options to Ollama's request options.
+const fn generate_options(options: &ModelOptions) -> GenerateOptions {
+    GenerateOptions {
+        num_ctx: Some(options.num_ctx),
+        temperature: Some(options.temperature),
+        top_p: options.top_p,
+        seed: options.seed,
+        num_predict: options.num_predict,
+    }
+}
+
+/// Converts the configured model options to the sampling options of an
+/// OpenAI-compatible server.
+///
+/// The context window and `keep_alive` are set on the server instead; a
+/// negative `num_predict` (no limit) leaves out `max_tokens`.
+fn sampling_options(options: &ModelOptions) -> SamplingOptions {
+    SamplingOptions {
+        temperature: Some(options.temperature),
+        top_p: options.top_p,
+        seed: options.seed,
+        max_tokens: options
+            .num_predict
+            .and_then(|tokens| u32::try_from(tokens).ok()),
+    }
+}
+
+/// Creates the OpenAI-compatible client configured in `config`.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client cannot be created.
+fn openai_client(config: &Config) -> Result<OpenAiClient> {
+    OpenAiClient::with_config(
+        &config.openai.url,
+        config.openai.api_key.as_deref(),
+        config.generate_timeout_secs(),
+        config.embedding_timeout_secs(),
+    )
+    .map(|client| client.with_sampling(sampling_options(&config.models.options)))
+    .context("Failed to create OpenAI-compatible client")
+}
+
+/// Creates the text generator of the configured backend.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client cannot be created.
+pub fn text_generator(config: &Config) -> Result<Arc<dyn TextGenerator>> {
+    Ok(match config.backend {
+        Backend::Ollama => Arc::new(ollama_client(config)?),
+        Backend::OpenAi => Arc::new(openai_client(config)?),
+    })
+}
+
+/// Creates the embedder of the configured backend.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client cannot be created.
+pub fn embedder(config: &Config) -> Result<Arc<dyn Embedder>> {
+    Ok(match config.backend {
+        Backend::Ollama => Arc::new(ollama_client(config)?),
+        Backend::OpenAi => Arc::new(openai_client(config)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_selection() {
+        let mut config = Config::default();
+        assert!(format!("{:?}", text_generator(&config).unwrap()).contains("OllamaClient"));
+        assert!(format!("{:?}", embedder(&config).unwrap()).contains("OllamaClient"));
+
+        config.backend = Backend::OpenAi;
+        config.openai.url = "http://localhost:8000/v1".to_string();
+        let generator = format!("{:?}", text_generator(&config).unwrap());
+        assert!(generator.contains("OpenAiClient"));
+        assert!(generator.contains("http://localhost:8000/v1"));
+        assert!(format!("{:?}", embedder(&config).unwrap()).contains("OpenAiClient"));
+    }
+
+    #[test]
+    fn test_model_options_conversion() {
+        let options = ModelOptions {
+            seed: Some(42),
+            num_predict: Some(-1),
+            ..ModelOptions::default()
+        };
+
+        let ollama = generate_options(&options);
+        assert_eq!(ollama.num_ctx, Some(options.num_ctx));
+        assert_eq!(ollama.seed, Some(42));
+        assert_eq!(ollama.num_predict, Some(-1));
+
+        let openai = sampling_options(&options);
+        assert_eq!(openai.temperature, Some(options.temperature));
+        assert_eq!(openai.seed, Some(42));
+        assert!(openai.max_tokens.is_none());
+    }
+}
@@ -0,0 +1,306 @@
+//! Cache of model metadata.
+//!
+//! The context length of a model comes from the backend (Ollama's
+//! `/api/show`), which costs a round-trip. The answers are kept in a JSON
+//! file in the cache directory, so queries only ask again once an entry is
+//! older than [`MAX_AGE_SECS`]. Entries are keyed by backend, base URL and
+//! model name, since two servers can serve different models under one name.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::llm::backend::TextGenerator;
+use crate::setup::config::Backend;
+
+/// Name of the model metadata cache file.
+const CACHE_FILENAME: &str = "models.json";
+
+/// How long a cached entry is used before the backend is asked again
+/// (a re-pulled model may have changed).
+const MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Metadata of a model, as reported by the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    /// Context length the model was trained with.
+    pub context_length: u32,
+    /// Time of the lookup in seconds since the Unix epoch.
+    pub fetched_at: i64,
+}
+
+/// Model metadata cache, keyed by [`cache_key`].
+#[derive(Debug, Default)]
+pub struct ModelMetadataCache {
+    /// Path of the cache file.
+    path: PathBuf,
+    /// Cached metadata by cache key.
+    entries: HashMap<String, ModelMetadata>,
+}
+
+impl ModelMetadataCache {
+    /// Loads the cache from a file.
+    ///
+    /// A missing or unreadable file gives an empty cache; it's rebuilt by
+    /// the next lookups.
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .inspect_err(|e| debug!(error = %e, "Ignoring unreadable model cache"))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    /// Returns the metadata cached under `key` if it was cached less than
+    /// [`MAX_AGE_SECS`] before `now`.
+    #[must_use]
+    pub fn get(&self, key: &str, now: i64) -> Option<ModelMetadata> {
+        self.entries
+            .get(key)
+            .filter(|metadata| now - metadata.fetched_at < MAX_AGE_SECS)
+            .copied()
+    }
+
+    /// Adds or replaces the metadata cached under `key`.
+    pub fn insert(&mut self, key: &str, metadata: ModelMetadata) {
+        self.entries.insert(key.to_string(), metadata);
+    }
+
+    /// Writes the cache to its file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.entries)
+            .context("Failed to serialize model cache")?;
+        fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write model cache: {}", self.path.display()))
+    }
+}
+
+/// Gets the path to the model metadata cache.
+///
+/// Uses XDG Base Directory specification:
+/// - Linux: ~/.cache/ulm/models.json
+/// - macOS: ~/Library/Caches/ulm/models.json
+///
+/// # Errors
+///
+/// Returns an error if the cache directory cannot be determined or created.
+pub fn get_model_cache_path() -> Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "ulm")
+        .context("Could not determine cache directory")?;
+
+    let cache_dir = dirs.cache_dir();
+    fs::create_dir_all(cache_dir)
+        .with_context(|| format!("Failed to create cache directory: {}", cache_dir.display()))?;
+
+    Ok(cache_dir.join(CACHE_FILENAME))
+}
+
+/// Returns the cache key of a model served by `backend` at `base_url`.
+#[must_use]
+pub fn cache_key(backend: Backend, base_url: &str, model: &str) -> String {
+    format!("{} {base_url} {model}", backend.as_str())
+}
+
+/// Returns the context length of a model, from the cache or the backend.
+///
+/// `backend` and `base_url` name the server `generator` talks to. Only
+/// known context lengths are cached: a backend that can't report one is
+/// asked again by the next query. Lookup failures are logged and give
+/// `None`, so the caller falls back to the configured context window.
+pub async fn context_length(
+    generator: &dyn TextGenerator,
+    backend: Backend,
+    base_url: &str,
+    model: &str,
+) -> Option<u32> {
+    let path = match get_model_cache_path() {
+        Ok(path) => path,
+        Err(e) => {
+            warn!(error = %e, "Model cache unavailable");
+            return lookup_context_length(generator, model).await;
+        }
+    };
+
+    let mut cache = ModelMetadataCache::load(&path);
+    let key = cache_key(backend, base_url, model);
+    let now = unix_now();
+    if let Some(metadata) = cache.get(&key, now) {
+        debug!(
+            model = %model,
+            context_length = metadata.context_length,
+            "Using cached model metadata"
+        );
+        return Some(metadata.context_length);
+    }
+
+    let context_length = lookup_and_cache(&mut cache, &key, generator, model, now).await?;
+    if let Err(e) = cache.save() {
+        warn!(error = %e, "Failed to save model cache");
+    }
+
+    Some(context_length)
+}
+
+/// Asks the backend for the context length of a model and caches it under
+/// `key` if the backend knows it.
+async fn lookup_and_cache(
+    cache: &mut ModelMetadataCache,
+    key: &str,
+    generator: &dyn TextGenerator,
+    model: &str,
+    now: i64,
+) -> Option<u32> {
+    let context_length = lookup_context_length(generator, model).await?;
+    cache.insert(
+        key,
+        ModelMetadata {
+            context_length,
+            fetched_at: now,
+        },
+    );
+    Some(context_length)
+}
+
+/// Asks the backend for the context length of a model, without the cache.
+async fn lookup_context_length(generator: &dyn TextGenerator, model: &str) -> Option<u32> {
+    generator
+        .context_length(model)
+        .await
+        .inspect_err(|e| warn!(model = %model, error = %e, "Failed to look up context length"))
+        .ok()
+        .flatten()
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|elapsed| i64::try_from(elapsed.as_secs()).ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::prompt::ChatMessage;
+    use crate::llm::stream::TokenStream;
+    use futures::future::{BoxFuture, FutureExt};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CACHE_FILENAME);
+
+        let mut cache = ModelMetadataCache::load(&path);
+        assert!(cache.get("qwen3:8b", 1000).is_none());
+
+        let metadata = ModelMetadata {
+            context_length: 40960,
+            fetched_at: 1000,
+        };
+        cache.insert("qwen3:8b", metadata);
+        cache.save().unwrap();
+
+        let cache = ModelMetadataCache::load(&path);
+        assert_eq!(cache.get("qwen3:8b", 2000), Some(metadata));
+    }
+
+    #[test]
+    fn test_cache_entries_expire() {
+        let mut cache = ModelMetadataCache::default();
+        cache.insert(
+            "llama3",
+            ModelMetadata {
+                context_length: 8192,
+                fetched_at: 0,
+            },
+        );
+
+        assert!(cache.get("llama3", MAX_AGE_SECS - 1).is_some());
+        assert!(cache.get("llama3", MAX_AGE_SECS).is_none());
+    }
+
+    #[test]
+    fn test_unreadable_cache_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CACHE_FILENAME);
+        fs::write(&path, "not json").unwrap();
+
+        assert!(ModelMetadataCache::load(&path).get("llama3", 0).is_none());
+    }
+
+    /// Generator reporting a fixed context length.
+    #[derive(Debug)]
+    struct FixedContext(Option<u32>);
+
+    impl TextGenerator for FixedContext {
+        fn chat<'a>(
+            &'a self,
+            _model: &'a str,
+            _messages: &'a [ChatMessage],
+            _format: Option<&'a serde_json::Value>,
+        ) -> BoxFuture<'a, Result<String>> {
+            async { anyhow::bail!("not supported") }.boxed()
+        }
+
+        fn chat_stream<'a>(
+            &'a self,
+            _model: &'a str,
+            _messages: &'a [ChatMessage],
+            _format: Option<&'a serde_json::Value>,
+        ) -> BoxFuture<'a, Result<TokenStream>> {
+            async { anyhow::bail!("not supported") }.boxed()
+        }
+
+        fn context_length<'a>(&'a self, _model: &'a str) -> BoxFuture<'a, Result<Option<u32>>> {
+            let length = self.0;
+            async move { Ok(length) }.boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_context_length_is_not_cached() {
+        let mut cache = ModelMetadataCache::default();
+        let key = cache_key(Backend::OpenAi, "http://localhost:8080/v1", "qwen3:8b");
+
+        let length = lookup_and_cache(&mut cache, &key, &FixedContext(None), "qwen3:8b", 0).await;
+        assert_eq!(length, None);
+        assert!(cache.get(&key, 0).is_none());
+
+        let length =
+            lookup_and_cache(&mut cache, &key, &FixedContext(Some(40960)), "qwen3:8b", 0).await;
+        assert_eq!(length, Some(40960));
+        assert_eq!(cache.get(&key, 0).map(|m| m.context_length), Some(40960));
+    }
+
+    #[test]
+    fn test_cache_key_includes_server() {
+        let local = cache_key(Backend::Ollama, "http://localhost:11434", "qwen3:8b");
+        let remote = cache_key(Backend::Ollama, "http://gpu-box:11434", "qwen3:8b");
+        let openai = cache_key(Backend::OpenAi, "http://localhost:11434", "qwen3:8b");
+
+        assert_eq!(local, "ollama http://localhost:11434 qwen3:8b");
+        assert_ne!(local, remote);
+        assert_ne!(local, openai);
+    }
+}
@@ -1,14 +1,36 @@
-//! LLM interaction via Ollama API.
+//! LLM interaction via Ollama or OpenAI-compatible APIs.
 //!
 //! This module handles all LLM-related operations including:
-//! - Ollama API client
-//! - Prompt building
-//! - Response parsing
+//! - Backend traits for text generation and embeddings
+//! - Ollama and OpenAI-compatible API clients
+//! - Prompt building within the model's context window, from
+//!   user-overridable templates
+//! - Cached model metadata
+//! - Response parsing and schema validation
+//! - Separation of reasoning output from the answer
 
+pub mod backend;
+pub mod metadata;
 pub mod ollama;
+pub mod openai;
 pub mod prompt;
+pub mod reasoning;
 pub mod response;
+pub mod schema;
+pub mod stream;
+pub mod template;
 
+pub use backend::{embedder, text_generator, Embedder, TextGenerator};
+pub use metadata::context_length;
 pub use ollama::{OllamaClient, DEFAULT_MODEL, DEFAULT_OLLAMA_URL, EMBEDDING_MODEL};
-pub use prompt::build_prompt;
-pub use response::{parse_suggestions, CommandSuggestion, RiskLevel};
+pub use openai::OpenAiClient;
+pub use prompt::{
+    build_correction, build_messages, ChatMessage, PromptBudget, Role, DEFAULT_RESPONSE_TOKENS,
+};
+pub use reasoning::split_reasoning;
+pub use response::{
+    parse_suggestions, suggestions_schema, CommandSuggestion, RiskLevel, SuggestionParser,
+    MAX_SUGGESTIONS,
+};
+pub use stream::{Token, TokenStream};
+pub use template::{get_template_path, PromptTemplate};
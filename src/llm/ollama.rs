@@ -6,6 +6,11 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::timeout;
+
+use crate::llm::prompt::ChatMessage;
+use crate::llm::stream::{token_stream, DecodedLine, TokenStream};
 
 /// Default Ollama API base URL.
 pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
@@ -27,6 +32,36 @@ pub struct OllamaClient {
     generate_timeout: Duration,
     /// Timeout for embedding requests.
     embedding_timeout: Duration,
+    /// Whether chat requests ask the model to reason first (`think`); the
+    /// model's default if unset.
+    think: Option<bool>,
+    /// Model options sent with every generate and chat request.
+    options: GenerateOptions,
+    /// How long the model stays loaded after a request; Ollama's default
+    /// if unset.
+    keep_alive: Option<String>,
+}
+
+/// Model options of a generate or chat request (`options`).
+///
+/// Unset options use the model's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GenerateOptions {
+    /// Context window in tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// Sampling temperature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling probability.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Random seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
 }
 
 /// Request for generating embeddings.
@@ -45,6 +80,22 @@ pub struct EmbeddingResponse {
     pub embedding: Vec<f32>,
 }
 
+/// Request for embeddings of several texts (`/api/embed`).
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedRequest {
+    /// Model to use for embedding generation.
+    pub model: String,
+    /// Texts to generate embeddings for.
+    pub input: Vec<String>,
+}
+
+/// Response from `/api/embed`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbedResponse {
+    /// One vector embedding per input, in input order.
+    pub embeddings: Vec<Vec<f32>>,
+}
+
 /// Request for LLM text generation.
 #[derive(Debug, Clone, Serialize)]
 pub struct GenerateRequest {
@@ -54,9 +105,14 @@ pub struct GenerateRequest {
     pub prompt: String,
     /// Whether to stream the response.
     pub stream: bool,
-    /// Output format (e.g., "json").
+    /// Output format: "json" or a JSON schema the output must match.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub format: Option<String>,
+    pub format: Option<Value>,
+    /// Model options.
+    pub options: GenerateOptions,
+    /// How long the model stays loaded after the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
 }
 
 /// Response from LLM text generation.
@@ -66,6 +122,54 @@ pub struct GenerateResponse {
     pub response: String,
 }
 
+/// Request for a chat completion.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatRequest {
+    /// Model to use for generation.
+    pub model: String,
+    /// Conversation to complete.
+    pub messages: Vec<ChatMessage>,
+    /// Whether to stream the response.
+    pub stream: bool,
+    /// Output format: "json" or a JSON schema the output must match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Value>,
+    /// Whether a thinking model reasons before answering; the model's
+    /// default if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub think: Option<bool>,
+    /// Model options.
+    pub options: GenerateOptions,
+    /// How long the model stays loaded after the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+/// Response from a chat request, or one line of a streaming one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatResponse {
+    /// Message generated (since the previous line, when streaming).
+    #[serde(default)]
+    pub message: Option<ChatReply>,
+    /// Whether generation has finished.
+    #[serde(default)]
+    pub done: bool,
+    /// Error reported by Ollama in the middle of the stream.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Message generated by a chat request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatReply {
+    /// Generated text.
+    #[serde(default)]
+    pub content: String,
+    /// Reasoning of a thinking model, sent apart from the content.
+    #[serde(default)]
+    pub thinking: Option<String>,
+}
+
 /// Response from /api/tags endpoint.
 #[derive(Debug, Clone, Deserialize)]
 pub struct TagsResponse {
@@ -82,6 +186,37 @@ pub struct ModelInfo {
     pub size: u64,
 }
 
+/// Request for the details of a model (`/api/show`).
+#[derive(Debug, Clone, Serialize)]
+struct ShowRequest {
+    /// Model name.
+    model: String,
+}
+
+/// Response from `/api/show`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShowResponse {
+    /// Architecture metadata of the model, keyed by GGUF name (e.g.,
+    /// `llama.context_length`).
+    #[serde(default)]
+    pub model_info: serde_json::Map<String, Value>,
+}
+
+impl ShowResponse {
+    /// Returns the context length the model was trained with, if known.
+    ///
+    /// The key is prefixed with the architecture (`llama.context_length`,
+    /// `qwen2.context_length`).
+    #[must_use]
+    pub fn context_length(&self) -> Option<u32> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .and_then(|length| u32::try_from(length).ok())
+    }
+}
+
 /// Request to pull a model.
 #[derive(Debug, Clone, Serialize)]
 struct PullRequest {
@@ -149,9 +284,49 @@ impl OllamaClient {
             base_url: base_url.trim_end_matches('/').to_string(),
             generate_timeout: Duration::from_secs(generate_timeout_secs),
             embedding_timeout: Duration::from_secs(embedding_timeout_secs),
+            think: None,
+            options: GenerateOptions::default(),
+            keep_alive: None,
         })
     }
 
+    /// Builds a chat request for the messages with the client's settings.
+    fn chat_request(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        format: Option<&Value>,
+        stream: bool,
+    ) -> ChatRequest {
+        ChatRequest {
+            model: model.to_string(),
+            messages: messages.to_vec(),
+            stream,
+            format: format.cloned(),
+            think: self.think,
+            options: self.options.clone(),
+            keep_alive: self.keep_alive.clone(),
+        }
+    }
+
+    /// Sets the model options and `keep_alive` of generate and chat requests.
+    #[must_use]
+    pub fn with_options(mut self, options: GenerateOptions, keep_alive: Option<String>) -> Self {
+        self.options = options;
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Sets whether chat requests ask the model to reason before answering.
+    ///
+    /// `None` leaves it to the model; `Some(false)` saves the time thinking
+    /// models spend on reasoning.
+    #[must_use]
+    pub const fn with_think(mut self, think: Option<bool>) -> Self {
+        self.think = think;
+        self
+    }
+
     /// Returns the base URL of this client.
     #[must_use]
     pub fn base_url(&self) -> &str {
@@ -211,6 +386,45 @@ impl OllamaClient {
         Ok(tags.models)
     }
 
+    /// Gets the details of an installed model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the model is not installed or
+    /// the response cannot be parsed.
+    pub async fn show_model(&self, model: &str) -> Result<ShowResponse> {
+        let url = format!("{}/api/show", self.base_url);
+
+        let request = ShowRequest {
+            model: model.to_string(),
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .timeout(Duration::from_secs(5))
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| {
+                format!(
+                    "Cannot connect to Ollama at {}. Start with: ollama serve",
+                    self.base_url
+                )
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama show request failed ({status}): {body}");
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse model details response")
+    }
+
     /// Generates an embedding for the given text.
     ///
     /// # Errors
@@ -253,23 +467,83 @@ impl OllamaClient {
         Ok(embedding_response.embedding)
     }
 
+    /// Generates embeddings for several texts in one request.
+    ///
+    /// Uses `/api/embed`, which takes an `input` array and returns the
+    /// vectors in input order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the response cannot be parsed,
+    /// or it has a different number of vectors than `texts`.
+    pub async fn generate_embeddings(
+        &self,
+        model: &str,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url);
+
+        let request = EmbedRequest {
+            model: model.to_string(),
+            input: texts.to_vec(),
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .timeout(self.embedding_timeout)
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| {
+                format!(
+                    "Cannot connect to Ollama at {} (timeout: {}s). Start with: ollama serve",
+                    self.base_url,
+                    self.embedding_timeout.as_secs()
+                )
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama embed request failed ({status}): {body}");
+        }
+
+        let embed_response: EmbedResponse = response
+            .json()
+            .await
+            .context("Failed to parse embed response")?;
+
+        anyhow::ensure!(
+            embed_response.embeddings.len() == texts.len(),
+            "Ollama returned {} embeddings for {} texts",
+            embed_response.embeddings.len(),
+            texts.len()
+        );
+
+        Ok(embed_response.embeddings)
+    }
+
     /// Generates text using the LLM.
     ///
     /// # Errors
     ///
     /// Returns an error if the request fails or response cannot be parsed.
-    pub async fn generate(&self, model: &str, prompt: &str, json_format: bool) -> Result<String> {
+    pub async fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        format: Option<&Value>,
+    ) -> Result<String> {
         let url = format!("{}/api/generate", self.base_url);
 
         let request = GenerateRequest {
             model: model.to_string(),
             prompt: prompt.to_string(),
             stream: false,
-            format: if json_format {
-                Some("json".to_string())
-            } else {
-                None
-            },
+            format: format.cloned(),
+            options: self.options.clone(),
+            keep_alive: self.keep_alive.clone(),
         };
 
         let response = self
@@ -301,6 +575,107 @@ impl OllamaClient {
         Ok(generate_response.response)
     }
 
+    /// Completes a chat conversation using the LLM.
+    ///
+    /// The model applies its chat template to the messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    pub async fn chat(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        format: Option<&Value>,
+    ) -> Result<String> {
+        let url = format!("{}/api/chat", self.base_url);
+        let request = self.chat_request(model, messages, format, false);
+
+        let response = self
+            .client
+            .post(&url)
+            .timeout(self.generate_timeout)
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| {
+                format!(
+                    "Cannot connect to Ollama at {} (timeout: {}s). Start with: ollama serve",
+                    self.base_url,
+                    self.generate_timeout.as_secs()
+                )
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama chat request failed ({status}): {body}");
+        }
+
+        let chat_response: ChatResponse = response
+            .json()
+            .await
+            .context("Failed to parse chat response")?;
+
+        if let Some(error) = chat_response.error {
+            anyhow::bail!("Ollama chat request failed: {error}");
+        }
+
+        // The reasoning of thinking models (`thinking`) is not part of the answer
+        Ok(chat_response
+            .message
+            .map(|message| message.content)
+            .unwrap_or_default())
+    }
+
+    /// Completes a chat conversation, streaming the tokens as they arrive.
+    ///
+    /// Ollama answers a streaming request with one JSON object per line
+    /// (NDJSON); the stream yields the text of each line. The generate
+    /// timeout applies to the wait for each line rather than the whole
+    /// response, so long answers don't time out while tokens keep coming.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails. Errors while reading the
+    /// stream are yielded by the stream.
+    pub async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        format: Option<&Value>,
+    ) -> Result<TokenStream> {
+        let url = format!("{}/api/chat", self.base_url);
+        let request = self.chat_request(model, messages, format, true);
+
+        let connect_error = || {
+            format!(
+                "Cannot connect to Ollama at {} (timeout: {}s). Start with: ollama serve",
+                self.base_url,
+                self.generate_timeout.as_secs()
+            )
+        };
+        let response = timeout(
+            self.generate_timeout,
+            self.client.post(&url).json(&request).send(),
+        )
+        .await
+        .with_context(connect_error)?
+        .with_context(connect_error)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama chat request failed ({status}): {body}");
+        }
+
+        Ok(token_stream(
+            response,
+            self.generate_timeout,
+            decode_chat_line,
+        ))
+    }
+
     /// Pulls a model from Ollama registry.
     ///
     /// Note: This is a simplified implementation that doesn't show streaming progress.
@@ -336,10 +711,103 @@ impl OllamaClient {
     }
 }
 
+/// Decodes one NDJSON line of a streaming chat response.
+///
+/// # Errors
+///
+/// Returns an error if the line is not a chat response or reports an error.
+fn decode_chat_line(line: &str) -> Result<DecodedLine> {
+    let chunk: ChatResponse =
+        serde_json::from_str(line).context("Failed to parse chat stream line")?;
+
+    if let Some(error) = chunk.error {
+        anyhow::bail!("Ollama chat request failed: {error}");
+    }
+
+    let (token, reasoning) = chunk.message.map_or((None, None), |message| {
+        (Some(message.content), message.thinking)
+    });
+    Ok(DecodedLine {
+        token,
+        reasoning,
+        done: chunk.done,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_show_response_context_length() {
+        let show: ShowResponse = serde_json::from_str(
+            r#"{"details":{"family":"qwen2"},"model_info":{"general.architecture":"qwen2","qwen2.context_length":32768,"qwen2.embedding_length":3584}}"#,
+        )
+        .unwrap();
+        assert_eq!(show.context_length(), Some(32768));
+
+        let show: ShowResponse = serde_json::from_str(r#"{"details":{}}"#).unwrap();
+        assert_eq!(show.context_length(), None);
+    }
+
+    #[test]
+    fn test_chat_request_serialization() {
+        let messages = [ChatMessage::system("Be brief"), ChatMessage::user("Hi")];
+        let schema = serde_json::json!({"type": "object"});
+        let options = GenerateOptions {
+            num_ctx: Some(16384),
+            temperature: Some(0.5),
+            ..GenerateOptions::default()
+        };
+        let client = OllamaClient::new()
+            .unwrap()
+            .with_think(Some(false))
+            .with_options(options, Some("10m".to_string()));
+        let json =
+            serde_json::to_string(&client.chat_request("llama3", &messages, Some(&schema), false))
+                .unwrap();
+        assert!(json.contains(
+            r#""messages":[{"role":"system","content":"Be brief"},{"role":"user","content":"Hi"}]"#
+        ));
+        assert!(json.contains(r#""stream":false"#));
+        assert!(json.contains(r#""format":{"type":"object"}"#));
+        assert!(json.contains(r#""think":false"#));
+        assert!(json.contains(r#""options":{"num_ctx":16384,"temperature":0.5}"#));
+        assert!(json.contains(r#""keep_alive":"10m""#));
+
+        let client = OllamaClient::new().unwrap();
+        let json =
+            serde_json::to_string(&client.chat_request("llama3", &messages, None, true)).unwrap();
+        assert!(!json.contains("format"));
+        assert!(!json.contains("think"));
+        assert!(json.contains(r#""options":{}"#));
+        assert!(!json.contains("keep_alive"));
+    }
+
+    #[test]
+    fn test_decode_chat_line() {
+        let decoded = decode_chat_line(
+            r#"{"model":"llama3","message":{"role":"assistant","content":"ls"},"done":false}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.token.as_deref(), Some("ls"));
+        assert!(!decoded.done);
+
+        let decoded = decode_chat_line(r#"{"model":"llama3","done":true}"#).unwrap();
+        assert!(decoded.token.is_none());
+        assert!(decoded.done);
+
+        // Thinking models send their reasoning apart from the content
+        let decoded = decode_chat_line(
+            r#"{"message":{"role":"assistant","content":"","thinking":"The user"},"done":false}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.reasoning.as_deref(), Some("The user"));
+        assert_eq!(decoded.token.as_deref(), Some(""));
+
+        assert!(decode_chat_line(r#"{"error":"model not found"}"#).is_err());
+    }
+
     #[test]
     fn test_embedding_request_serialization() {
         let request = EmbeddingRequest {
@@ -352,6 +820,24 @@ mod tests {
         assert!(json.contains("\"prompt\":\"test prompt\""));
     }
 
+    #[test]
+    fn test_embed_request_serialization() {
+        let request = EmbedRequest {
+            model: "nomic-embed-text".to_string(),
+            input: vec!["ls".to_string(), "cp".to_string()],
+        };
+
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(json.contains(r#""input":["ls","cp"]"#));
+    }
+
+    #[test]
+    fn test_embed_response_deserialization() {
+        let json = r#"{"model":"nomic-embed-text","embeddings":[[0.1,0.2],[0.3,0.4]]}"#;
+        let response: EmbedResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
     #[test]
     fn test_embedding_response_deserialization() {
         let json = r#"{"embedding": [0.1, 0.2, 0.3]}"#;
@@ -366,13 +852,19 @@ mod tests {
             model: "llama3".to_string(),
             prompt: "Hello".to_string(),
             stream: false,
-            format: Some("json".to_string()),
+            format: Some(Value::from("json")),
+            options: GenerateOptions {
+                seed: Some(7),
+                ..GenerateOptions::default()
+            },
+            keep_alive: None,
         };
 
         let json = serde_json::to_string(&request).expect("Failed to serialize");
         assert!(json.contains("\"model\":\"llama3\""));
         assert!(json.contains("\"stream\":false"));
         assert!(json.contains("\"format\":\"json\""));
+        assert!(json.contains(r#""options":{"seed":7}"#));
     }
 
     #[test]
@@ -382,6 +874,8 @@ mod tests {
             prompt: "Hello".to_string(),
             stream: false,
             format: None,
+            options: GenerateOptions::default(),
+            keep_alive: None,
         };
 
         let json = serde_json::to_string(&request).expect("Failed to serialize");
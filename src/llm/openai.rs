@@ -0,0 +1,585 @@
+//! OpenAI-compatible API client for embeddings and LLM inference.
+//!
+//! Talks to servers implementing `/v1/chat/completions` and `/v1/embeddings`,
+//! such as llama.cpp's `llama-server` or vLLM.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::timeout;
+
+use crate::llm::prompt::ChatMessage;
+use crate::llm::stream::{token_stream, DecodedLine, TokenStream};
+
+/// Default timeout for LLM generation in seconds.
+const DEFAULT_GENERATE_TIMEOUT_SECS: u64 = 120;
+
+/// Default timeout for embedding requests in seconds.
+const DEFAULT_EMBEDDING_TIMEOUT_SECS: u64 = 60;
+
+/// Prefix of the data lines of a server-sent event stream.
+const SSE_DATA_PREFIX: &str = "data:";
+
+/// Data of the last event of a streaming chat completion.
+const SSE_DONE: &str = "[DONE]";
+
+/// Client for an OpenAI-compatible API.
+#[derive(Clone)]
+pub struct OpenAiClient {
+    /// HTTP client.
+    client: reqwest::Client,
+    /// Base URL of the API, including the version (e.g., `.../v1`).
+    base_url: String,
+    /// API key sent as a bearer token.
+    api_key: Option<String>,
+    /// Timeout for LLM generation requests.
+    generate_timeout: Duration,
+    /// Timeout for embedding requests.
+    embedding_timeout: Duration,
+    /// Sampling options sent with every chat request.
+    sampling: SamplingOptions,
+}
+
+/// Sampling options of a chat completion.
+///
+/// Unset options use the server's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SamplingOptions {
+    /// Sampling temperature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling probability.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Random seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// Output format of a chat completion.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseFormat {
+    /// Format type (`json_object` or `json_schema`).
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Schema the output must match, for `json_schema`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<JsonSchemaFormat>,
+}
+
+/// Named JSON schema of a `json_schema` response format.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonSchemaFormat {
+    /// Name of the schema.
+    pub name: String,
+    /// The JSON schema.
+    pub schema: Value,
+    /// Whether the server must follow the schema exactly.
+    pub strict: bool,
+}
+
+impl ResponseFormat {
+    /// Converts a generation format to a response format.
+    ///
+    /// A schema object becomes `json_schema`; anything else (e.g., `"json"`)
+    /// becomes `json_object`.
+    #[must_use]
+    pub fn from_format(format: &Value) -> Self {
+        if format.is_object() {
+            Self {
+                kind: "json_schema".to_string(),
+                json_schema: Some(JsonSchemaFormat {
+                    name: "response".to_string(),
+                    schema: format.clone(),
+                    strict: true,
+                }),
+            }
+        } else {
+            Self {
+                kind: "json_object".to_string(),
+                json_schema: None,
+            }
+        }
+    }
+}
+
+/// Request for a chat completion.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatRequest {
+    /// Model to use for generation.
+    pub model: String,
+    /// Conversation to complete.
+    pub messages: Vec<ChatMessage>,
+    /// Whether to stream the response as server-sent events.
+    pub stream: bool,
+    /// Output format; constrains the output to JSON or a JSON schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Sampling options.
+    #[serde(flatten)]
+    pub sampling: SamplingOptions,
+}
+
+/// Response of a chat completion.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatResponse {
+    /// Generated completions (one unless more were requested).
+    pub choices: Vec<ChatChoice>,
+}
+
+/// One completion of a chat completion response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatChoice {
+    /// Generated message.
+    pub message: ChatReply,
+}
+
+/// Message generated by a chat completion.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatReply {
+    /// Generated text, absent when the model only called tools.
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// One event of a streaming chat completion.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatChunk {
+    /// Completions updated by this event.
+    #[serde(default)]
+    pub choices: Vec<ChatChunkChoice>,
+}
+
+/// Update of one completion in a streaming chat completion.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatChunkChoice {
+    /// Text generated since the previous event.
+    #[serde(default)]
+    pub delta: ChatDelta,
+    /// Why generation stopped, on the last event.
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// Text added to a message by a streaming event.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatDelta {
+    /// Generated text, absent on role-only events.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Reasoning of a thinking model, as sent by llama.cpp and vLLM.
+    #[serde(default)]
+    pub reasoning_content: Option<String>,
+}
+
+/// Request for embeddings.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingsRequest {
+    /// Model to use for embedding generation.
+    pub model: String,
+    /// Texts to generate embeddings for.
+    pub input: Vec<String>,
+}
+
+/// Response of an embeddings request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingsResponse {
+    /// One embedding per input.
+    pub data: Vec<EmbeddingData>,
+}
+
+/// Embedding of one input.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingData {
+    /// Position of the input in the request.
+    #[serde(default)]
+    pub index: usize,
+    /// Vector embedding.
+    pub embedding: Vec<f32>,
+}
+
+// Keeps the API key out of logs
+impl std::fmt::Debug for OpenAiClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAiClient")
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("generate_timeout", &self.generate_timeout)
+            .field("embedding_timeout", &self.embedding_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpenAiClient {
+    /// Creates a new client with default timeouts and no API key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    pub fn with_url(base_url: &str) -> Result<Self> {
+        Self::with_config(
+            base_url,
+            None,
+            DEFAULT_GENERATE_TIMEOUT_SECS,
+            DEFAULT_EMBEDDING_TIMEOUT_SECS,
+        )
+    }
+
+    /// Creates a new client with custom URL, API key and timeouts.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The base URL of the API, including the version
+    /// * `api_key` - Bearer token for servers that require one
+    /// * `generate_timeout_secs` - Timeout for LLM generation requests in seconds
+    /// * `embedding_timeout_secs` - Timeout for embedding requests in seconds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    pub fn with_config(
+        base_url: &str,
+        api_key: Option<&str>,
+        generate_timeout_secs: u64,
+        embedding_timeout_secs: u64,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.map(ToString::to_string),
+            generate_timeout: Duration::from_secs(generate_timeout_secs),
+            embedding_timeout: Duration::from_secs(embedding_timeout_secs),
+            sampling: SamplingOptions::default(),
+        })
+    }
+
+    /// Sets the sampling options of chat requests.
+    #[must_use]
+    pub const fn with_sampling(mut self, sampling: SamplingOptions) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Returns the base URL of this client.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Builds a POST request to an API path, with the API key if set.
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.post(format!("{}{path}", self.base_url));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Returns the error message for a request that could not be sent.
+    fn connect_error(&self, timeout: Duration) -> String {
+        format!(
+            "Cannot connect to LLM server at {} (timeout: {}s)",
+            self.base_url,
+            timeout.as_secs()
+        )
+    }
+
+    /// Generates an embedding for the given text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    pub async fn generate_embedding(&self, model: &str, text: &str) -> Result<Vec<f32>> {
+        self.generate_embeddings(model, &[text.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .context("Embedding response contains no embedding")
+    }
+
+    /// Generates embeddings for several texts in one request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the response cannot be parsed,
+    /// or it has a different number of vectors than `texts`.
+    pub async fn generate_embeddings(
+        &self,
+        model: &str,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>> {
+        let request = EmbeddingsRequest {
+            model: model.to_string(),
+            input: texts.to_vec(),
+        };
+
+        let response = self
+            .post("/embeddings")
+            .timeout(self.embedding_timeout)
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| self.connect_error(self.embedding_timeout))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Embedding request failed ({status}): {body}");
+        }
+
+        let mut embeddings: EmbeddingsResponse = response
+            .json()
+            .await
+            .context("Failed to parse embedding response")?;
+
+        anyhow::ensure!(
+            embeddings.data.len() == texts.len(),
+            "Server returned {} embeddings for {} texts",
+            embeddings.data.len(),
+            texts.len()
+        );
+
+        // The API doesn't guarantee input order
+        embeddings.data.sort_by_key(|data| data.index);
+
+        Ok(embeddings
+            .data
+            .into_iter()
+            .map(|data| data.embedding)
+            .collect())
+    }
+
+    /// Completes a chat conversation using the LLM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or response cannot be parsed.
+    pub async fn chat(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        format: Option<&Value>,
+    ) -> Result<String> {
+        let request = chat_request(model, messages, format, &self.sampling, false);
+
+        let response = self
+            .post("/chat/completions")
+            .timeout(self.generate_timeout)
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| self.connect_error(self.generate_timeout))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat completion request failed ({status}): {body}");
+        }
+
+        let chat: ChatResponse = response
+            .json()
+            .await
+            .context("Failed to parse chat completion response")?;
+
+        chat.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.unwrap_or_default())
+            .context("Chat completion response contains no choices")
+    }
+
+    /// Completes a chat conversation, streaming the tokens as they arrive.
+    ///
+    /// The server answers with server-sent events; the stream yields the
+    /// content of each event. The generate timeout applies to the wait for
+    /// each event rather than the whole response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails. Errors while reading the
+    /// stream are yielded by the stream.
+    pub async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        format: Option<&Value>,
+    ) -> Result<TokenStream> {
+        let request = chat_request(model, messages, format, &self.sampling, true);
+
+        let response = timeout(
+            self.generate_timeout,
+            self.post("/chat/completions").json(&request).send(),
+        )
+        .await
+        .with_context(|| self.connect_error(self.generate_timeout))?
+        .with_context(|| self.connect_error(self.generate_timeout))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat completion request failed ({status}): {body}");
+        }
+
+        Ok(token_stream(
+            response,
+            self.generate_timeout,
+            decode_sse_line,
+        ))
+    }
+}
+
+/// Builds a chat completion request for the messages.
+fn chat_request(
+    model: &str,
+    messages: &[ChatMessage],
+    format: Option<&Value>,
+    sampling: &SamplingOptions,
+    stream: bool,
+) -> ChatRequest {
+    ChatRequest {
+        model: model.to_string(),
+        messages: messages.to_vec(),
+        stream,
+        response_format: format.map(ResponseFormat::from_format),
+        sampling: sampling.clone(),
+    }
+}
+
+/// Decodes one line of a streaming chat completion.
+///
+/// Only `data:` lines carry content; comments and other fields are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a data line is not a chat completion chunk.
+fn decode_sse_line(line: &str) -> Result<DecodedLine> {
+    let Some(data) = line.strip_prefix(SSE_DATA_PREFIX).map(str::trim) else {
+        return Ok(DecodedLine::default());
+    };
+    if data == SSE_DONE {
+        return Ok(DecodedLine {
+            token: None,
+            reasoning: None,
+            done: true,
+        });
+    }
+
+    let chunk: ChatChunk =
+        serde_json::from_str(data).context("Failed to parse chat completion stream event")?;
+    let Some(choice) = chunk.choices.into_iter().next() else {
+        return Ok(DecodedLine::default());
+    };
+
+    Ok(DecodedLine {
+        token: choice.delta.content,
+        reasoning: choice.delta.reasoning_content,
+        done: choice.finish_reason.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_serialization() {
+        let messages = [ChatMessage::user("Hello")];
+        let format = Value::from("json");
+        let json = serde_json::to_string(&chat_request(
+            "qwen",
+            &messages,
+            Some(&format),
+            &SamplingOptions::default(),
+            false,
+        ))
+        .unwrap();
+        assert!(json.contains(r#""messages":[{"role":"user","content":"Hello"}]"#));
+        assert!(json.contains(r#""response_format":{"type":"json_object"}"#));
+        assert!(json.contains(r#""stream":false"#));
+        assert!(!json.contains("temperature"));
+
+        let sampling = SamplingOptions {
+            temperature: Some(0.5),
+            seed: Some(42),
+            ..SamplingOptions::default()
+        };
+        let json =
+            serde_json::to_string(&chat_request("qwen", &messages, None, &sampling, true)).unwrap();
+        assert!(!json.contains("response_format"));
+        assert!(json.contains(r#""temperature":0.5,"seed":42"#));
+    }
+
+    #[test]
+    fn test_chat_request_json_schema() {
+        let messages = [ChatMessage::user("Hello")];
+        let schema = serde_json::json!({"type": "object"});
+        let json = serde_json::to_string(&chat_request(
+            "qwen",
+            &messages,
+            Some(&schema),
+            &SamplingOptions::default(),
+            false,
+        ))
+        .unwrap();
+        assert!(json.contains(
+            r#""response_format":{"type":"json_schema","json_schema":{"name":"response","schema":{"type":"object"},"strict":true}}"#
+        ));
+    }
+
+    #[test]
+    fn test_chat_response_deserialization() {
+        let json = r#"{"id":"x","choices":[{"index":0,"message":{"role":"assistant","content":"{}"},"finish_reason":"stop"}]}"#;
+        let response: ChatResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn test_embeddings_response_deserialization() {
+        let json = r#"{"object":"list","data":[{"index":1,"embedding":[0.3]},{"index":0,"embedding":[0.1,0.2]}]}"#;
+        let response: EmbeddingsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.data[0].index, 1);
+        assert_eq!(response.data[1].embedding, vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_decode_sse_line() {
+        let decoded = decode_sse_line(r#"data: {"choices":[{"delta":{"content":"ls"}}]}"#).unwrap();
+        assert_eq!(decoded.token.as_deref(), Some("ls"));
+        assert!(!decoded.done);
+
+        let decoded =
+            decode_sse_line(r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#).unwrap();
+        assert_eq!(decoded, DecodedLine::default());
+
+        let decoded =
+            decode_sse_line(r#"data: {"choices":[{"delta":{"reasoning_content":"Hmm"}}]}"#)
+                .unwrap();
+        assert_eq!(decoded.reasoning.as_deref(), Some("Hmm"));
+
+        let decoded =
+            decode_sse_line(r#"data: {"choices":[{"delta":{},"finish_reason":"stop"}]}"#).unwrap();
+        assert!(decoded.done);
+
+        assert!(decode_sse_line("data: [DONE]").unwrap().done);
+        assert_eq!(
+            decode_sse_line(": keep-alive").unwrap(),
+            DecodedLine::default()
+        );
+        assert!(decode_sse_line("data: not json").is_err());
+    }
+
+    #[test]
+    fn test_client_custom_url() {
+        let client = OpenAiClient::with_url("http://localhost:8080/v1/").unwrap();
+        assert_eq!(client.base_url(), "http://localhost:8080/v1");
+    }
+}
@@ -1,17 +1,19 @@
 //! Prompt building for LLM queries.
 //!
-//! This module constructs prompts that combine system instructions,
-//! manpage content, directory context, and user queries.
+//! This module constructs the chat messages of a query: system instructions,
+//! then manpage content, matched manpage excerpts, directory context, and
+//! the user query. The messages are rendered from a [`PromptTemplate`] and
+//! fitted into a [`PromptBudget`] derived from the model's context window.
 
-use crate::query::DirectoryContext;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-/// System instructions for the command-line assistant.
-const SYSTEM_INSTRUCTIONS: &str = r#"You are a command-line expert assistant. Your task is to suggest relevant commands based on the user's query and the provided manpage documentation.
+use crate::llm::template::PromptTemplate;
+use crate::query::{DirectoryContext, SearchMatch};
 
-Analyze the manpage content and generate practical command suggestions that solve the user's problem. Consider the working directory context when suggesting commands.
-
-IMPORTANT: Respond ONLY with valid JSON in the following format:
-{
+/// Expected shape of the response, shown in the instructions and in
+/// correction requests.
+pub(crate) const RESPONSE_FORMAT: &str = r#"{
   "suggestions": [
     {
       "command": "the exact command to run",
@@ -20,104 +22,225 @@ IMPORTANT: Respond ONLY with valid JSON in the following format:
       "risk_level": "safe|moderate|destructive"
     }
   ]
-}
-
-Risk levels:
-- "safe": Read-only operations, no side effects
-- "moderate": Modifies files but recoverable (e.g., creates/edits files)
-- "destructive": Irreversible operations (e.g., rm -rf, force push)
-
-Provide 1-3 command suggestions, ordered by relevance. If the query cannot be answered with the provided manpage, respond with an empty suggestions array."#;
+}"#;
 
-/// Maximum total prompt length in characters (~12000 tokens).
-const MAX_PROMPT_LENGTH: usize = 48000;
-
-/// Builds a complete prompt for the LLM.
-///
-/// Combines system instructions, manpage content, directory context,
-/// and user query into a formatted prompt string.
-///
-/// # Arguments
+/// Average number of bytes per token, used to estimate prompt sizes.
 ///
-/// * `query` - The user's natural language query
-/// * `manpage_content` - Full or truncated manpage text
-/// * `context` - Directory context information
-///
-/// # Returns
-///
-/// A formatted prompt string ready for LLM consumption.
-#[must_use]
-pub fn build_prompt(query: &str, manpage_content: &str, context: &DirectoryContext) -> String {
-    let context_str = context.format_for_prompt();
-
-    // Build initial prompt
-    let mut prompt = format!(
-        "{SYSTEM_INSTRUCTIONS}
+/// Lower than the ~4 of English prose, since manpages are dense with
+/// options and punctuation; overestimating keeps the prompt in the window.
+const BYTES_PER_TOKEN: usize = 3;
 
----
+/// Tokens kept free for the response if the config doesn't limit it.
+pub const DEFAULT_RESPONSE_TOKENS: u32 = 1024;
 
-## Context
+/// Share of the reference budget that the directory context may use, in
+/// percent.
+const CONTEXT_SHARE: usize = 10;
 
-{context_str}
+/// Share of the reference budget that manpage excerpts may use, in percent.
+/// The manpage gets the rest, including what the others leave unused.
+const EXCERPT_SHARE: usize = 30;
 
----
+/// Marker appended to truncated reference material.
+const TRUNCATION_MARKER: &str = "\n\n[Content truncated for length]";
 
-## Manpage Content
-
-{manpage_content}
-
----
-
-## User Query
-
-{query}
-
----
-
-Respond with JSON only:"
-    );
-
-    // Truncate if too long
-    if prompt.len() > MAX_PROMPT_LENGTH {
-        // Calculate how much to truncate from manpage content
-        let overhead = prompt.len() - manpage_content.len();
-        let max_content = MAX_PROMPT_LENGTH
-            .saturating_sub(overhead)
-            .saturating_sub(100);
+/// Number of tokens the messages of a query may use.
+///
+/// The system instructions and the query are always sent; the remaining
+/// tokens are split across directory context, manpage excerpts and the
+/// manpage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptBudget {
+    /// Tokens available for the messages.
+    tokens: usize,
+}
 
-        // Truncate manpage content
-        let truncated_content = truncate_at_boundary(manpage_content, max_content);
+impl PromptBudget {
+    /// Creates the budget of a context window, keeping `response_tokens`
+    /// free for the response.
+    #[must_use]
+    pub fn new(context_length: u32, response_tokens: u32) -> Self {
+        let free = context_length.saturating_sub(response_tokens);
+        Self {
+            tokens: usize::try_from(free).unwrap_or(usize::MAX),
+        }
+    }
 
-        prompt = format!(
-            "{SYSTEM_INSTRUCTIONS}
+    /// Returns the number of tokens available for the messages.
+    #[must_use]
+    pub const fn tokens(self) -> usize {
+        self.tokens
+    }
+}
 
----
+/// Estimates the number of tokens of a text.
+#[must_use]
+pub const fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(BYTES_PER_TOKEN)
+}
 
-## Context
+/// Author of a chat message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Instructions that set up the assistant.
+    System,
+    /// Input from the user.
+    User,
+    /// Output of the model.
+    Assistant,
+}
 
-{context_str}
+/// Message of a chat conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Author of the message.
+    pub role: Role,
+    /// Text of the message.
+    pub content: String,
+}
 
----
+impl ChatMessage {
+    /// Creates a system message.
+    #[must_use]
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::System,
+            content: content.into(),
+        }
+    }
 
-## Manpage Content
+    /// Creates a user message.
+    #[must_use]
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
 
-{truncated_content}
+    /// Creates an assistant message.
+    #[must_use]
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
 
-[Content truncated for length]
+/// Builds the chat messages for an LLM query.
+///
+/// With the built-in template, the system message holds the instructions.
+/// The user message holds the directory context, manpage content and the
+/// manpage chunks that matched the query, each in its own tag, followed by
+/// the query. Keeping the
+/// untrusted manpage text out of the system message lets the model's chat
+/// template separate instructions from data; a single user message keeps
+/// templates that require alternating roles working.
+///
+/// The reference material is fitted into the tokens of `budget` left after
+/// the instructions and the query: the directory context and the excerpts
+/// get capped shares, in that order, and the manpage is truncated to the
+/// rest.
+///
+/// # Arguments
+///
+/// * `query` - The user's natural language query
+/// * `manpage_content` - Full manpage text
+/// * `matches` - Search matches whose chunks are included as excerpts
+/// * `context` - Directory context information
+/// * `budget` - Tokens the messages may use
+/// * `template` - Templates of the system and user messages
+///
+/// # Returns
+///
+/// The system and user messages, ready for a chat API.
+#[must_use]
+pub fn build_messages(
+    query: &str,
+    manpage_content: &str,
+    matches: &[SearchMatch],
+    context: &DirectoryContext,
+    budget: PromptBudget,
+    template: &PromptTemplate,
+) -> Vec<ChatMessage> {
+    let system = template.render_system();
+
+    let fixed =
+        estimate_tokens(&system) + estimate_tokens(&template.render_user("", "", "", query));
+    let available = budget.tokens().saturating_sub(fixed);
+
+    let context_str = fit_to_budget(
+        &context.format_for_prompt(),
+        available * CONTEXT_SHARE / 100,
+    );
+    let excerpts = format_excerpts(matches, available * EXCERPT_SHARE / 100);
+    let manpage_budget = available
+        .saturating_sub(estimate_tokens(&context_str))
+        .saturating_sub(estimate_tokens(&excerpts));
+    let manpage = fit_to_budget(manpage_content, manpage_budget);
+
+    debug!(
+        budget = budget.tokens(),
+        fixed,
+        context = estimate_tokens(&context_str),
+        excerpts = estimate_tokens(&excerpts),
+        manpage = estimate_tokens(&manpage),
+        "Fitted prompt into token budget"
+    );
 
----
+    let user = template.render_user(&context_str, &manpage, &excerpts, query);
+    vec![ChatMessage::system(system), ChatMessage::user(user)]
+}
 
-## User Query
+/// Builds the follow-up messages that ask the model to correct a response.
+///
+/// The invalid response is replayed as the assistant's turn, followed by a
+/// user message with the parse error and the expected shape, so the model
+/// can see what it got wrong.
+#[must_use]
+pub fn build_correction(response: &str, error: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::assistant(response),
+        ChatMessage::user(format!(
+            "Your previous response could not be used: {error}\n\n\
+             Respond again with JSON only, in exactly this format:\n{RESPONSE_FORMAT}"
+        )),
+    ]
+}
 
-{query}
+/// Formats the matched chunks of all search matches as tagged excerpts,
+/// closest match first, up to `max_tokens`.
+///
+/// Chunks are included whole; those that don't fit are left out. Returns an
+/// empty string if no chunks matched.
+fn format_excerpts(matches: &[SearchMatch], max_tokens: usize) -> String {
+    let mut excerpts = String::new();
+
+    for m in matches {
+        for chunk in &m.chunks {
+            let excerpt = format!(
+                "\n<excerpt tool=\"{}\" heading=\"{}\">\n{}\n</excerpt>\n",
+                m.tool_name, chunk.heading, chunk.text
+            );
+            if estimate_tokens(&excerpts) + estimate_tokens(&excerpt) <= max_tokens {
+                excerpts.push_str(&excerpt);
+            }
+        }
+    }
 
----
+    excerpts
+}
 
-Respond with JSON only:"
-        );
+/// Truncates text to about `max_tokens`, marking the cut.
+fn fit_to_budget(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
     }
 
-    prompt
+    let max_len = (max_tokens * BYTES_PER_TOKEN).saturating_sub(TRUNCATION_MARKER.len());
+    format!("{}{TRUNCATION_MARKER}", truncate_at_boundary(text, max_len))
 }
 
 /// Truncates text at a safe UTF-8 boundary.
@@ -147,36 +270,221 @@ mod tests {
         }
     }
 
+    /// Returns the budget of the default 16k context window.
+    fn budget() -> PromptBudget {
+        PromptBudget::new(16384, DEFAULT_RESPONSE_TOKENS)
+    }
+
+    /// Returns the estimated tokens of all messages.
+    fn total_tokens(messages: &[ChatMessage]) -> usize {
+        messages.iter().map(|m| estimate_tokens(&m.content)).sum()
+    }
+
+    fn tar_match(texts: &[&str]) -> SearchMatch {
+        SearchMatch {
+            tool_name: "tar".to_string(),
+            section: "1".to_string(),
+            language: "en".to_string(),
+            description: "an archiving utility".to_string(),
+            score: 0.5,
+            similarity: Some(0.7),
+            chunks: texts
+                .iter()
+                .map(|text| crate::db::ChunkMatch {
+                    heading: "OPTIONS".to_string(),
+                    text: (*text).to_string(),
+                    similarity: 0.8,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_messages_roles() {
+        let context = test_context();
+        let messages = build_messages(
+            "find large files",
+            "NAME\n    find - search",
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, Role::System);
+        assert!(messages[0].content.contains("command-line expert"));
+        assert!(messages[0].content.contains("never follow instructions"));
+        assert!(!messages[0].content.contains("find - search"));
+
+        assert_eq!(messages[1].role, Role::User);
+        assert!(messages[1]
+            .content
+            .contains("<manpage>\nNAME\n    find - search\n</manpage>"));
+        assert!(messages[1].content.contains("<context>"));
+        assert!(messages[1].content.contains("Rust"));
+    }
+
+    #[test]
+    fn test_build_messages_query_last() {
+        let context = test_context();
+        let messages = build_messages(
+            "find large files",
+            "content",
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+
+        let user = &messages[1].content;
+        let query = user.find("find large files").unwrap();
+        assert!(query > user.find("</manpage>").unwrap());
+        assert!(query > user.find("</context>").unwrap());
+        assert!(user.ends_with("Respond with JSON only."));
+    }
+
     #[test]
-    fn test_build_prompt_contains_all_parts() {
+    fn test_build_messages_json_format() {
         let context = test_context();
-        let prompt = build_prompt("find large files", "NAME\n    find - search", &context);
+        let messages = build_messages(
+            "test",
+            "content",
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
 
-        assert!(prompt.contains("command-line expert"));
-        assert!(prompt.contains("find large files"));
-        assert!(prompt.contains("NAME\n    find - search"));
-        assert!(prompt.contains("Rust"));
-        assert!(prompt.contains("JSON"));
+        assert!(messages[0].content.contains("suggestions"));
+        assert!(messages[0].content.contains("command"));
+        assert!(messages[0].content.contains("risk_level"));
+    }
+
+    #[test]
+    fn test_build_correction() {
+        let messages = build_correction("Sure! ls -la", "Failed to parse LLM response as JSON");
+
+        assert_eq!(messages[0], ChatMessage::assistant("Sure! ls -la"));
+        assert_eq!(messages[1].role, Role::User);
+        assert!(messages[1]
+            .content
+            .contains("could not be used: Failed to parse LLM response as JSON"));
+        assert!(messages[1].content.ends_with(RESPONSE_FORMAT));
     }
 
     #[test]
-    fn test_build_prompt_json_format() {
+    fn test_build_messages_truncation() {
         let context = test_context();
-        let prompt = build_prompt("test", "content", &context);
+        let long_content = "x".repeat(100_000);
+        let messages = build_messages(
+            "query",
+            &long_content,
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
 
-        assert!(prompt.contains("suggestions"));
-        assert!(prompt.contains("command"));
-        assert!(prompt.contains("risk_level"));
+        assert!(total_tokens(&messages) <= budget().tokens());
+        assert!(messages[1]
+            .content
+            .contains("[Content truncated for length]"));
+        assert!(messages[1]
+            .content
+            .ends_with("query\n\nRespond with JSON only."));
     }
 
     #[test]
-    fn test_build_prompt_truncation() {
+    fn test_build_messages_follows_budget() {
         let context = test_context();
         let long_content = "x".repeat(100_000);
-        let prompt = build_prompt("query", &long_content, &context);
 
-        assert!(prompt.len() <= MAX_PROMPT_LENGTH + 100);
-        assert!(prompt.contains("[Content truncated"));
+        let small = build_messages(
+            "query",
+            &long_content,
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+        let large = build_messages(
+            "query",
+            &long_content,
+            &[],
+            &context,
+            PromptBudget::new(131_072, DEFAULT_RESPONSE_TOKENS),
+            &PromptTemplate::default(),
+        );
+        assert!(total_tokens(&large) > total_tokens(&small));
+        assert!(!large[1].content.contains("[Content truncated"));
+    }
+
+    #[test]
+    fn test_build_messages_includes_matched_chunks() {
+        let context = test_context();
+        let matches = vec![tar_match(&["-p, --preserve-permissions"])];
+
+        let messages = build_messages(
+            "tar preserve permissions",
+            "content",
+            &matches,
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+
+        assert!(messages[1].content.contains(
+            "<excerpt tool=\"tar\" heading=\"OPTIONS\">\n-p, --preserve-permissions\n</excerpt>"
+        ));
+    }
+
+    #[test]
+    fn test_excerpts_are_capped() {
+        let context = test_context();
+        let long_chunk = "y".repeat(20_000);
+        let matches = vec![tar_match(&[&long_chunk, "-p, --preserve-permissions"])];
+
+        let messages = build_messages(
+            "tar",
+            "content",
+            &matches,
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+
+        // The long chunk exceeds the excerpt share and is left out whole
+        assert!(!messages[1].content.contains(&long_chunk));
+        assert!(messages[1].content.contains("--preserve-permissions"));
+    }
+
+    #[test]
+    fn test_prompt_budget() {
+        assert_eq!(PromptBudget::new(8192, 1024).tokens(), 7168);
+        assert_eq!(PromptBudget::new(512, 1024).tokens(), 0);
+        assert_eq!(estimate_tokens("abcd"), 2);
+    }
+
+    #[test]
+    fn test_build_messages_without_chunks_has_no_excerpts() {
+        let context = test_context();
+        let messages = build_messages(
+            "test",
+            "content",
+            &[],
+            &context,
+            budget(),
+            &PromptTemplate::default(),
+        );
+
+        assert!(!messages[1].content.contains("<excerpt"));
+    }
+
+    #[test]
+    fn test_chat_message_serialization() {
+        let json = serde_json::to_string(&ChatMessage::system("Be brief")).unwrap();
+        assert_eq!(json, r#"{"role":"system","content":"Be brief"}"#);
     }
 
     #[test]
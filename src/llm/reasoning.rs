@@ -0,0 +1,167 @@
+//! Separation of reasoning output from the answer.
+//!
+//! Reasoning models (deepseek-r1, qwen3) write their chain of thought
+//! before the answer, either in a separate `thinking` field or inline in
+//! `<think>...</think>` tags. The answer is parsed without it, and the
+//! reasoning is only shown on request.
+
+use crate::llm::stream::Token;
+
+/// Tag that opens inline reasoning.
+const THINK_OPEN: &str = "<think>";
+
+/// Tag that closes inline reasoning.
+const THINK_CLOSE: &str = "</think>";
+
+/// Splits a complete response into its reasoning and its answer.
+///
+/// Removes every `<think>...</think>` block. A closing tag without an
+/// opening one (chat templates that open the block in the prompt) ends
+/// reasoning that started with the response; an unclosed block runs to the
+/// end of the response.
+#[must_use]
+pub fn split_reasoning(text: &str) -> (String, String) {
+    let mut filter = ReasoningFilter::default();
+    // Only the leading block can lack its opening tag
+    if let Some(end) = text.find(THINK_CLOSE) {
+        if !text[..end].contains(THINK_OPEN) {
+            filter.in_reasoning = true;
+        }
+    }
+
+    let mut reasoning = String::new();
+    let mut answer = String::new();
+    for token in filter.push(text).into_iter().chain(filter.finish()) {
+        match token {
+            Token::Reasoning(text) => reasoning.push_str(&text),
+            Token::Text(text) => answer.push_str(&text),
+        }
+    }
+
+    (reasoning.trim().to_string(), answer)
+}
+
+/// Incremental splitter for inline reasoning in a streamed response.
+///
+/// Tags may be split across fragments; text that could be the start of a
+/// tag is held back until the next fragment shows whether it is one.
+#[derive(Debug, Default)]
+pub struct ReasoningFilter {
+    /// Whether the text is inside a `<think>` block.
+    in_reasoning: bool,
+    /// Text held back because it may start a tag.
+    pending: String,
+}
+
+impl ReasoningFilter {
+    /// Adds a fragment and returns its reasoning and answer parts, in order.
+    pub fn push(&mut self, fragment: &str) -> Vec<Token> {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(fragment);
+
+        let mut tokens = Vec::new();
+        let mut rest = text.as_str();
+        loop {
+            let tag = if self.in_reasoning {
+                THINK_CLOSE
+            } else {
+                THINK_OPEN
+            };
+
+            if let Some(start) = rest.find(tag) {
+                self.emit(&mut tokens, &rest[..start]);
+                rest = &rest[start + tag.len()..];
+                self.in_reasoning = !self.in_reasoning;
+                continue;
+            }
+
+            // Hold back a suffix that may be the start of the tag
+            let held = (1..tag.len())
+                .rev()
+                .find(|&len| rest.ends_with(&tag[..len]))
+                .unwrap_or(0);
+            self.emit(&mut tokens, &rest[..rest.len() - held]);
+            self.pending = rest[rest.len() - held..].to_string();
+            return tokens;
+        }
+    }
+
+    /// Returns the text held back at the end of the response.
+    pub fn finish(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let pending = std::mem::take(&mut self.pending);
+        self.emit(&mut tokens, &pending);
+        tokens
+    }
+
+    /// Adds non-empty text as reasoning or answer, depending on the state.
+    fn emit(&self, tokens: &mut Vec<Token>, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        tokens.push(if self.in_reasoning {
+            Token::Reasoning(text.to_string())
+        } else {
+            Token::Text(text.to_string())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_reasoning() {
+        let (reasoning, answer) =
+            split_reasoning("<think>\nThe user wants {files}.\n</think>\n\n{\"suggestions\": []}");
+        assert_eq!(reasoning, "The user wants {files}.");
+        assert_eq!(answer.trim(), r#"{"suggestions": []}"#);
+
+        // No reasoning
+        let (reasoning, answer) = split_reasoning(r#"{"suggestions": []}"#);
+        assert!(reasoning.is_empty());
+        assert_eq!(answer, r#"{"suggestions": []}"#);
+    }
+
+    #[test]
+    fn test_split_reasoning_without_opening_tag() {
+        let (reasoning, answer) = split_reasoning("Let me think.</think>{}");
+        assert_eq!(reasoning, "Let me think.");
+        assert_eq!(answer, "{}");
+    }
+
+    #[test]
+    fn test_split_unclosed_reasoning() {
+        let (reasoning, answer) = split_reasoning("<think>Still thinking when the time ran out");
+        assert_eq!(reasoning, "Still thinking when the time ran out");
+        assert!(answer.is_empty());
+    }
+
+    #[test]
+    fn test_filter_tags_split_across_fragments() {
+        let mut filter = ReasoningFilter::default();
+        let mut tokens = Vec::new();
+        for fragment in [
+            "<th",
+            "ink>Hmm",
+            ", <b> ok</thi",
+            "nk>",
+            "{\"a\": \"<",
+            "\"}",
+        ] {
+            tokens.extend(filter.push(fragment));
+        }
+        tokens.extend(filter.finish());
+
+        assert_eq!(
+            tokens,
+            [
+                Token::Reasoning("Hmm".to_string()),
+                Token::Reasoning(", <b> ok".to_string()),
+                Token::Text("{\"a\": \"".to_string()),
+                Token::Text("<\"}".to_string()),
+            ]
+        );
+    }
+}
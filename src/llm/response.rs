@@ -1,10 +1,26 @@
 //! LLM response parsing.
 //!
 //! This module parses JSON responses from the LLM into structured
-//! command suggestions.
+//! command suggestions. Parsing is tolerant of the usual small-model
+//! mistakes: the JSON is taken from markdown fences or surrounding prose,
+//! a single suggestion is wrapped in the array, and missing or unknown
+//! fields are filled in before the response is checked against the schema.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::llm::reasoning::split_reasoning;
+use crate::llm::schema::validate;
+
+/// Maximum number of suggestions in a response.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Risk level of a suggestion whose risk level is missing or unknown.
+///
+/// Unknown risk must not be shown as safe.
+const FALLBACK_RISK_LEVEL: RiskLevel = RiskLevel::Moderate;
 
 /// Risk level for a command suggestion.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,6 +35,33 @@ pub enum RiskLevel {
     Destructive,
 }
 
+impl RiskLevel {
+    /// All risk levels, from least to most dangerous.
+    pub const ALL: [Self; 3] = [Self::Safe, Self::Moderate, Self::Destructive];
+
+    /// Parses a risk level name, accepting common synonyms ("low", "high").
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "safe" | "low" | "none" | "read-only" | "readonly" => Some(Self::Safe),
+            "moderate" | "medium" => Some(Self::Moderate),
+            "destructive" | "high" | "dangerous" | "critical" => Some(Self::Destructive),
+            _ => None,
+        }
+    }
+
+    /// Returns the JSON schema of a risk level: a string enum of the
+    /// serialized variants.
+    #[must_use]
+    pub fn json_schema() -> Value {
+        let names: Vec<Value> = Self::ALL
+            .iter()
+            .filter_map(|level| serde_json::to_value(level).ok())
+            .collect();
+        json!({ "type": "string", "enum": names })
+    }
+}
+
 /// A command suggestion from the LLM.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandSuggestion {
@@ -33,6 +76,42 @@ pub struct CommandSuggestion {
     pub risk_level: RiskLevel,
 }
 
+impl CommandSuggestion {
+    /// Returns the JSON schema of a suggestion; every field is required.
+    #[must_use]
+    pub fn json_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string" },
+                "title": { "type": "string" },
+                "explanation": { "type": "string" },
+                "risk_level": RiskLevel::json_schema(),
+            },
+            "required": ["command", "title", "explanation", "risk_level"],
+        })
+    }
+}
+
+/// Returns the JSON schema of a suggestions response.
+///
+/// Sent as the output format of generate calls, so the model can only
+/// produce this shape, and checked again when parsing.
+#[must_use]
+pub fn suggestions_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "suggestions": {
+                "type": "array",
+                "items": CommandSuggestion::json_schema(),
+                "maxItems": MAX_SUGGESTIONS,
+            },
+        },
+        "required": ["suggestions"],
+    })
+}
+
 /// Response wrapper for JSON parsing.
 #[derive(Debug, Deserialize)]
 struct SuggestionsResponse {
@@ -56,15 +135,23 @@ struct SuggestionsResponse {
 /// }
 /// ```
 ///
+/// Reasoning in `<think>` tags is removed first. The JSON is extracted from
+/// markdown fences or surrounding prose and repaired with
+/// [`repair_suggestions`], then checked against [`suggestions_schema`].
+///
 /// # Errors
 ///
 /// Returns an error if:
-/// - JSON parsing fails
+/// - No JSON object can be extracted
+/// - The repaired response doesn't match the schema
 /// - Command field is empty
 pub fn parse_suggestions(response: &str) -> Result<Vec<CommandSuggestion>> {
-    // Try to parse the JSON
+    let (_, answer) = split_reasoning(response);
+    let value = repair_suggestions(extract_json(&answer)?);
+    validate(&suggestions_schema(), &value)
+        .context("LLM response does not match the suggestions schema")?;
     let parsed: SuggestionsResponse =
-        serde_json::from_str(response).context("Failed to parse LLM response as JSON")?;
+        serde_json::from_value(value).context("Failed to parse LLM response as JSON")?;
 
     // Validate suggestions
     let mut suggestions = Vec::new();
@@ -79,6 +166,291 @@ pub fn parse_suggestions(response: &str) -> Result<Vec<CommandSuggestion>> {
     Ok(suggestions)
 }
 
+/// Extracts the JSON value from an LLM response.
+///
+/// Tries the whole response, then the contents of its first markdown code
+/// fence, then the first balanced JSON object in the fence or the whole
+/// response (a fence inside a JSON string cuts the fence short).
+///
+/// # Errors
+///
+/// Returns an error if none of them is valid JSON.
+fn extract_json(response: &str) -> Result<Value> {
+    let response = response.trim();
+    let fenced = strip_fences(response);
+
+    serde_json::from_str(response)
+        .or_else(|err| serde_json::from_str(fenced).map_err(|_| err))
+        .or_else(|err| {
+            find_object(fenced)
+                .or_else(|| find_object(response))
+                .ok_or(err)
+        })
+        .context("Failed to parse LLM response as JSON")
+}
+
+/// Returns the contents of the first markdown code fence, or the text itself.
+fn strip_fences(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    let fence = &text[start + 3..];
+    // Skip the language tag of the opening fence (```json)
+    let body = fence
+        .find('\n')
+        .map_or(fence, |newline| &fence[newline + 1..]);
+    body.find("```").map_or(body, |end| &body[..end]).trim()
+}
+
+/// Finds the first balanced JSON object in a text that parses.
+fn find_object(text: &str) -> Option<Value> {
+    text.match_indices('{')
+        .filter_map(|(start, _)| object_end(&text[start..]).map(|end| &text[start..=start + end]))
+        .find_map(|candidate| serde_json::from_str::<Value>(candidate).ok())
+        .filter(Value::is_object)
+}
+
+/// Returns the index of the brace closing the object that `text` starts with.
+fn object_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    // Structural characters are ASCII, so scanning bytes is UTF-8 safe
+    for (index, byte) in text.bytes().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Brings a parsed response into the shape of [`suggestions_schema`].
+///
+/// A bare array or a single suggestion object is wrapped in the
+/// `suggestions` array, suggestions beyond [`MAX_SUGGESTIONS`] are dropped,
+/// and each suggestion is repaired with [`repair_suggestion`]. Values that
+/// cannot be repaired are left for schema validation to report.
+#[must_use]
+pub fn repair_suggestions(value: Value) -> Value {
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut object) => match object.remove("suggestions") {
+            Some(Value::Array(items)) => items,
+            Some(item @ Value::Object(_)) => vec![item],
+            Some(other) => {
+                object.insert("suggestions".to_string(), other);
+                return Value::Object(object);
+            }
+            None if object.contains_key("command") => vec![Value::Object(object)],
+            None => return Value::Object(object),
+        },
+        other => return other,
+    };
+
+    let suggestions: Vec<Value> = items
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(repair_suggestion)
+        .collect();
+    json!({ "suggestions": suggestions })
+}
+
+/// Fills in the missing or invalid fields of one suggestion.
+///
+/// The title defaults to the command, the explanation to an empty string,
+/// and the risk level to [`RiskLevel::from_name`] of the given name or
+/// `moderate`. The command itself is never made up.
+#[must_use]
+pub fn repair_suggestion(value: Value) -> Value {
+    let Value::Object(mut object) = value else {
+        return value;
+    };
+
+    let command = object
+        .get("command")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    if !object.get("title").is_some_and(Value::is_string) {
+        object.insert("title".to_string(), Value::String(command));
+    }
+    if !object.get("explanation").is_some_and(Value::is_string) {
+        object.insert("explanation".to_string(), Value::String(String::new()));
+    }
+
+    let risk_level = object
+        .get("risk_level")
+        .and_then(Value::as_str)
+        .and_then(RiskLevel::from_name)
+        .unwrap_or(FALLBACK_RISK_LEVEL);
+    if let Ok(risk_level) = serde_json::to_value(risk_level) {
+        object.insert("risk_level".to_string(), risk_level);
+    }
+
+    Value::Object(object)
+}
+
+/// Incremental parser for a streamed suggestions response.
+///
+/// Text is fed in fragments as the LLM generates it. Each object of the
+/// `suggestions` array is returned as soon as its closing brace arrives,
+/// without waiting for the rest of the response.
+#[derive(Debug, Default)]
+pub struct SuggestionParser {
+    /// Response text received so far.
+    text: String,
+    /// Number of bytes of `text` already scanned.
+    scanned: usize,
+    /// Nesting depth of objects and arrays at the scan position.
+    depth: usize,
+    /// Whether the scan position is inside a string.
+    in_string: bool,
+    /// Whether the previous character was a backslash inside a string.
+    escaped: bool,
+    /// Start of the string being scanned.
+    string_start: usize,
+    /// Last string completed in the top-level object (the current key).
+    key: String,
+    /// Whether the scan position is inside the `suggestions` array.
+    in_suggestions: bool,
+    /// Start of the array element object being scanned.
+    object_start: Option<usize>,
+}
+
+impl SuggestionParser {
+    /// Creates a parser for a new response.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a fragment of the response and returns the suggestions it completes.
+    ///
+    /// Suggestions with an empty command are skipped.
+    pub fn push(&mut self, fragment: &str) -> Vec<CommandSuggestion> {
+        self.text.push_str(fragment);
+
+        let mut completed = Vec::new();
+        // Structural characters are ASCII, so scanning bytes is UTF-8 safe
+        while self.scanned < self.text.len() {
+            let index = self.scanned;
+            let byte = self.text.as_bytes()[index];
+            self.scanned += 1;
+
+            if self.in_string {
+                self.scan_string(byte, index);
+                continue;
+            }
+
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.string_start = index + 1;
+                }
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    if byte == b'[' && self.depth == 2 {
+                        self.in_suggestions = self.key == "suggestions";
+                    } else if byte == b'{' && self.depth == 3 && self.in_suggestions {
+                        self.object_start = Some(index);
+                    }
+                }
+                b'}' | b']' => {
+                    if byte == b'}' && self.depth == 3 {
+                        if let Some(start) = self.object_start.take() {
+                            completed.extend(Self::parse_object(&self.text[start..=index]));
+                        }
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth < 2 {
+                        self.in_suggestions = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        completed
+    }
+
+    /// Returns the response text received so far.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Parses the complete response with [`parse_suggestions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the complete response is not a valid suggestions
+    /// object.
+    pub fn finish(&self) -> Result<Vec<CommandSuggestion>> {
+        parse_suggestions(&self.text)
+    }
+
+    /// Advances the string state by one byte inside a string.
+    fn scan_string(&mut self, byte: u8, index: usize) {
+        if self.escaped {
+            self.escaped = false;
+        } else if byte == b'\\' {
+            self.escaped = true;
+        } else if byte == b'"' {
+            self.in_string = false;
+            if self.depth == 1 {
+                self.key = self.text[self.string_start..index].to_string();
+            }
+        }
+    }
+
+    /// Parses one element of the suggestions array.
+    ///
+    /// The element is repaired and checked against the suggestion schema
+    /// first.
+    fn parse_object(json: &str) -> Option<CommandSuggestion> {
+        let parsed = serde_json::from_str::<Value>(json)
+            .map_err(anyhow::Error::from)
+            .and_then(|value| {
+                let value = repair_suggestion(value);
+                validate(&CommandSuggestion::json_schema(), &value)?;
+                Ok(serde_json::from_value::<CommandSuggestion>(value)?)
+            });
+
+        match parsed {
+            Ok(suggestion) if !suggestion.command.trim().is_empty() => Some(suggestion),
+            Ok(_) => {
+                debug!("Skipping streamed suggestion with empty command");
+                None
+            }
+            Err(e) => {
+                debug!(error = %e, "Skipping invalid streamed suggestion");
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,7 +508,7 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_default_risk_level() {
+    fn test_parse_missing_risk_level() {
         let json = r#"{
             "suggestions": [
                 {
@@ -147,8 +519,116 @@ mod tests {
             ]
         }"#;
 
+        // A missing risk level must not silently become "safe"
         let suggestions = parse_suggestions(json).unwrap();
-        assert_eq!(suggestions[0].risk_level, RiskLevel::Safe);
+        assert_eq!(suggestions[0].risk_level, RiskLevel::Moderate);
+    }
+
+    #[test]
+    fn test_parse_risk_level_names() {
+        let json = r#"{"suggestions": [
+            {"command": "ls", "title": "List", "explanation": "Lists", "risk_level": "Low"},
+            {"command": "rm x", "title": "Remove", "explanation": "Removes", "risk_level": "high"},
+            {"command": "mv x y", "title": "Move", "explanation": "Moves", "risk_level": "unclear"}
+        ]}"#;
+
+        let levels: Vec<RiskLevel> = parse_suggestions(json)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.risk_level)
+            .collect();
+        assert_eq!(
+            levels,
+            [RiskLevel::Safe, RiskLevel::Destructive, RiskLevel::Moderate]
+        );
+    }
+
+    #[test]
+    fn test_parse_fenced_response() {
+        let response = "Here are some commands:\n```json\n{\"suggestions\": [{\"command\": \"ls\", \"title\": \"List\", \"explanation\": \"Uses ```\", \"risk_level\": \"safe\"}]}\n```\nLet me know!";
+
+        let suggestions = parse_suggestions(response).unwrap();
+        assert_eq!(suggestions[0].command, "ls");
+        assert_eq!(suggestions[0].explanation, "Uses ```");
+    }
+
+    #[test]
+    fn test_parse_response_with_prose() {
+        let response = r#"Sure {really}! {"suggestions": [{"command": "echo '}'", "title": "Echo", "explanation": "Prints", "risk_level": "safe"}]} Hope this helps."#;
+
+        let suggestions = parse_suggestions(response).unwrap();
+        assert_eq!(suggestions[0].command, "echo '}'");
+    }
+
+    #[test]
+    fn test_parse_unwrapped_suggestion() {
+        let single = r#"{"command": "pwd", "title": "Where", "explanation": "Prints the directory", "risk_level": "safe"}"#;
+        assert_eq!(parse_suggestions(single).unwrap()[0].command, "pwd");
+
+        let object = format!(r#"{{"suggestions": {single}}}"#);
+        assert_eq!(parse_suggestions(&object).unwrap()[0].command, "pwd");
+
+        let array = format!("[{single}, {single}, {single}, {single}]");
+        assert_eq!(parse_suggestions(&array).unwrap().len(), MAX_SUGGESTIONS);
+    }
+
+    #[test]
+    fn test_parse_response_with_reasoning() {
+        let response = format!(
+            "<think>\nThey want {{files}}. Maybe `ls`?\n</think>\n\n{}",
+            r#"{"suggestions": [{"command": "ls", "title": "List", "explanation": "Lists", "risk_level": "safe"}]}"#
+        );
+
+        assert_eq!(parse_suggestions(&response).unwrap()[0].command, "ls");
+    }
+
+    #[test]
+    fn test_parse_missing_fields() {
+        let json = r#"{"suggestions": [{"command": "du -sh", "title": null}]}"#;
+
+        let suggestion = &parse_suggestions(json).unwrap()[0];
+        assert_eq!(suggestion.title, "du -sh");
+        assert_eq!(suggestion.explanation, "");
+        assert_eq!(suggestion.risk_level, RiskLevel::Moderate);
+    }
+
+    #[test]
+    fn test_parse_unrepairable_response() {
+        // The command is never made up
+        let err = parse_suggestions(r#"{"suggestions": [{"title": "List"}]}"#).unwrap_err();
+        assert!(format!("{err:#}").contains(r#"missing required field "command""#));
+
+        let err = parse_suggestions(r#"{"suggestions": "ls"}"#).unwrap_err();
+        assert!(format!("{err:#}").contains("$.suggestions must be an array"));
+
+        // Truncated output has no balanced object
+        assert!(parse_suggestions(r#"{"suggestions": [{"command": "ls""#).is_err());
+    }
+
+    #[test]
+    fn test_suggestions_schema() {
+        let schema = suggestions_schema();
+        let item = &schema["properties"]["suggestions"]["items"];
+
+        // Every serialized field of a suggestion is in the schema
+        let suggestion = serde_json::to_value(CommandSuggestion {
+            command: "ls".to_string(),
+            title: "List".to_string(),
+            explanation: "Lists files".to_string(),
+            risk_level: RiskLevel::Safe,
+        })
+        .unwrap();
+        for key in suggestion.as_object().unwrap().keys() {
+            assert!(item["properties"].get(key).is_some(), "{key} missing");
+            assert!(item["required"].as_array().unwrap().contains(&json!(key)));
+        }
+
+        assert_eq!(
+            item["properties"]["risk_level"]["enum"],
+            json!(["safe", "moderate", "destructive"])
+        );
+        assert_eq!(schema["properties"]["suggestions"]["maxItems"], 3);
+        validate(&schema, &json!({"suggestions": [suggestion]})).unwrap();
     }
 
     #[test]
@@ -165,7 +645,8 @@ mod tests {
                 {
                     "command": "",
                     "title": "Empty",
-                    "explanation": "Bad"
+                    "explanation": "Bad",
+                    "risk_level": "safe"
                 }
             ]
         }"#;
@@ -182,7 +663,8 @@ mod tests {
                 {
                     "command": "   ",
                     "title": "Whitespace",
-                    "explanation": "Bad"
+                    "explanation": "Bad",
+                    "risk_level": "safe"
                 }
             ]
         }"#;
@@ -208,4 +690,36 @@ mod tests {
     fn test_risk_level_default() {
         assert_eq!(RiskLevel::default(), RiskLevel::Safe);
     }
+
+    #[test]
+    fn test_suggestion_parser_yields_completed_objects() {
+        let json = r#"{"suggestions": [{"command": "ls -la", "title": "List {all}", "explanation": "Shows \"hidden\" files", "risk_level": "safe"}, {"command": "du -sh *", "title": "Sizes", "explanation": "Disk usage", "risk_level": "safe"}]}"#;
+        let mut parser = SuggestionParser::new();
+
+        // Feed a few bytes at a time, as tokens arrive
+        let mut completed = Vec::new();
+        let mut counts = Vec::new();
+        for fragment in json.as_bytes().chunks(7) {
+            completed.extend(parser.push(std::str::from_utf8(fragment).unwrap()));
+            counts.push(completed.len());
+        }
+
+        assert_eq!(completed.len(), 2);
+        assert_eq!(completed[0].title, "List {all}");
+        assert_eq!(completed[1].command, "du -sh *");
+        // The first suggestion is available before the response is complete
+        assert!(counts.iter().position(|&c| c == 1) < counts.iter().position(|&c| c == 2));
+        assert_eq!(parser.finish().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_suggestion_parser_ignores_other_arrays() {
+        let mut parser = SuggestionParser::new();
+        let completed = parser.push(
+            r#"{"notes": [{"command": "x", "title": "", "explanation": ""}], "suggestions": [{"command": "", "title": "", "explanation": "", "risk_level": "safe"}, {"command": 42, "title": "List", "explanation": "Lists files", "risk_level": "safe"}, {"command": "pwd", "title": "Where", "explanation": "Prints the directory", "risk_level": "safe"}]}"#,
+        );
+
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].command, "pwd");
+    }
 }
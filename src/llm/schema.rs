@@ -0,0 +1,181 @@
+//! Validation of JSON values against JSON schemas.
+//!
+//! Supports the subset of JSON Schema used for constrained LLM output:
+//! `type`, `properties`, `required`, `items`, `minItems`, `maxItems` and
+//! `enum`. Other keywords are ignored.
+
+use anyhow::Result;
+use serde_json::Value;
+
+/// Checks a value against a schema.
+///
+/// # Errors
+///
+/// Returns an error naming the path of the first value that doesn't match
+/// (e.g., `$.suggestions[0].risk_level`).
+pub fn validate(schema: &Value, value: &Value) -> Result<()> {
+    validate_at(schema, value, "$")
+}
+
+/// Checks a value at `path` against a schema.
+///
+/// # Errors
+///
+/// Returns an error naming the path of the first value that doesn't match.
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<()> {
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        if !has_type(value, expected) {
+            anyhow::bail!(
+                "{path} must be {} but is {}",
+                article(expected),
+                type_name(value)
+            );
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let names: Vec<String> = allowed.iter().map(ToString::to_string).collect();
+            anyhow::bail!("{path} must be one of {} but is {value}", names.join(", "));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            for key in required(schema) {
+                if !object.contains_key(key) {
+                    anyhow::bail!("{path} is missing required field \"{key}\"");
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (key, property) in properties {
+                    if let Some(field) = object.get(key) {
+                        validate_at(property, field, &format!("{path}.{key}"))?;
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    anyhow::bail!("{path} must have at least {min} items");
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    anyhow::bail!("{path} must have at most {max} items");
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}[{i}]"))?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Returns the required property names of an object schema.
+fn required(schema: &Value) -> impl Iterator<Item = &str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Checks if a value has a JSON schema type.
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+/// Returns the JSON schema type name of a value.
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Returns a type name with its indefinite article ("an object").
+fn article(type_name: &str) -> String {
+    if type_name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        format!("an {type_name}")
+    } else {
+        format!("a {type_name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "items": {
+                    "type": "array",
+                    "maxItems": 2,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "level": { "type": "string", "enum": ["low", "high"] }
+                        },
+                        "required": ["name", "level"]
+                    }
+                }
+            },
+            "required": ["items"]
+        })
+    }
+
+    fn error(value: &Value) -> String {
+        validate(&schema(), value).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_value() {
+        let value = json!({"items": [{"name": "a", "level": "low", "extra": 1}]});
+        assert!(validate(&schema(), &value).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_path() {
+        assert_eq!(
+            error(&json!({"items": [{"name": "a", "level": "medium"}]})),
+            r#"$.items[0].level must be one of "low", "high" but is "medium""#
+        );
+        assert_eq!(
+            error(&json!({"items": [{"level": "low"}]})),
+            r#"$.items[0] is missing required field "name""#
+        );
+        assert_eq!(
+            error(&json!({"items": {}})),
+            "$.items must be an array but is object"
+        );
+    }
+
+    #[test]
+    fn test_validate_item_count() {
+        let item = json!({"name": "a", "level": "low"});
+        assert_eq!(
+            error(&json!({"items": [item, item, item]})),
+            "$.items must have at most 2 items"
+        );
+    }
+}
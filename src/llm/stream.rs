@@ -0,0 +1,189 @@
+//! Token streams read from line-delimited HTTP responses.
+//!
+//! Ollama streams one JSON object per line (NDJSON); OpenAI-compatible
+//! servers send server-sent events (`data: {...}` lines). Both are read line
+//! by line and decoded into text fragments by a backend-specific function.
+//! Reasoning, whether sent in its own field or inline in `<think>` tags, is
+//! yielded separately from the answer.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::time::timeout;
+
+use crate::llm::reasoning::ReasoningFilter;
+
+/// Stream of generated text fragments (tokens).
+pub type TokenStream = BoxStream<'static, Result<Token>>;
+
+/// Fragment of generated text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// Part of the answer.
+    Text(String),
+    /// Part of the model's reasoning before the answer.
+    Reasoning(String),
+}
+
+/// Text decoded from one line of a streaming response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedLine {
+    /// Text generated since the previous line, if any.
+    pub token: Option<String>,
+    /// Reasoning generated since the previous line, if sent separately.
+    pub reasoning: Option<String>,
+    /// Whether this line ends the response.
+    pub done: bool,
+}
+
+/// Decodes one non-empty line of a streaming response.
+pub type LineDecoder = fn(&str) -> Result<DecodedLine>;
+
+/// Turns a streaming response into a stream of tokens.
+///
+/// `idle_timeout` applies to the wait for each part of the body rather than
+/// the whole response, so long answers don't time out while tokens keep
+/// coming. Read errors, timeouts and decoding errors are yielded by the
+/// stream.
+pub fn token_stream(
+    response: reqwest::Response,
+    idle_timeout: Duration,
+    decode: LineDecoder,
+) -> TokenStream {
+    let state = LineStream {
+        response,
+        lines: LineBuffer::default(),
+        reasoning: ReasoningFilter::default(),
+        tokens: VecDeque::new(),
+        done: false,
+        idle_timeout,
+        decode,
+    };
+
+    stream::try_unfold(state, LineStream::next_token).boxed()
+}
+
+/// State of a streaming response.
+#[derive(Debug)]
+struct LineStream {
+    /// Response whose body is the line-delimited stream.
+    response: reqwest::Response,
+    /// Splits the body into lines.
+    lines: LineBuffer,
+    /// Splits inline reasoning from the answer.
+    reasoning: ReasoningFilter,
+    /// Tokens decoded but not yet yielded.
+    tokens: VecDeque<Token>,
+    /// Whether the last line or the end of the body was reached.
+    done: bool,
+    /// Maximum wait for the next part of the body.
+    idle_timeout: Duration,
+    /// Decodes the lines of the body.
+    decode: LineDecoder,
+}
+
+impl LineStream {
+    /// Returns the next token, reading from the body as needed.
+    async fn next_token(mut self) -> Result<Option<(Token, Self)>> {
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return Ok(Some((token, self)));
+            }
+            if self.done {
+                return Ok(None);
+            }
+
+            let chunk = timeout(self.idle_timeout, self.response.chunk())
+                .await
+                .with_context(|| {
+                    format!(
+                        "LLM server stopped responding (timeout: {}s)",
+                        self.idle_timeout.as_secs()
+                    )
+                })?
+                .context("Failed to read generate stream")?;
+
+            let lines = if let Some(bytes) = chunk {
+                self.lines.push(&bytes)
+            } else {
+                self.done = true;
+                self.lines.finish().into_iter().collect()
+            };
+            for line in lines {
+                let decoded = (self.decode)(&line)?;
+                if let Some(reasoning) = decoded.reasoning.filter(|text| !text.is_empty()) {
+                    self.tokens.push_back(Token::Reasoning(reasoning));
+                }
+                if let Some(token) = decoded.token {
+                    self.tokens.extend(self.reasoning.push(&token));
+                }
+                self.done |= decoded.done;
+            }
+            if self.done {
+                self.tokens.extend(self.reasoning.finish());
+            }
+        }
+    }
+}
+
+/// Splits a byte stream into newline-delimited lines.
+///
+/// Lines may be split across chunks, even inside a UTF-8 character.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    /// Bytes of the incomplete last line.
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Adds bytes and returns the non-empty lines they complete.
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+
+        let Some(end) = self.buffer.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = self.buffer.drain(..=end).collect();
+
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Returns the last line if the stream didn't end with a newline.
+    fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let line = String::from_utf8_lossy(&rest).trim().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(br#"{"response":"he"#).is_empty());
+        assert_eq!(
+            lines.push(b"llo\"}\n{\"response\":\"!\"}\n\n{\"done\""),
+            vec![r#"{"response":"hello"}"#, r#"{"response":"!"}"#]
+        );
+        assert_eq!(lines.finish().as_deref(), Some(r#"{"done""#));
+        assert!(lines.finish().is_none());
+    }
+
+    #[test]
+    fn test_lines_split_inside_character() {
+        let mut lines = LineBuffer::default();
+        let text = "{\"response\":\"ü\"}\n".as_bytes();
+        assert!(lines.push(&text[..14]).is_empty());
+        assert_eq!(lines.push(&text[14..]), vec!["{\"response\":\"ü\"}"]);
+    }
+}
@@ -4,18 +4,19 @@
 //! It provides an AI-powered bridge between what users want to accomplish and the
 //! thousands of powerful but cryptic Unix tools available on their system.
 
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::panic;
 use std::process::ExitCode;
 
 use crossterm::terminal::{disable_raw_mode, is_raw_mode_enabled};
+use tokio::sync::mpsc;
 use tracing::{debug, error, info};
 use tracing_subscriber::EnvFilter;
-use ulm::cli::{Args, Commands};
+use ulm::cli::{Args, Commands, IndexCommands, PromptCommands};
 use ulm::exec::{copy_to_clipboard, execute_command};
 use ulm::query;
 use ulm::setup;
-use ulm::tui::{display_error, run_tui, UserAction};
+use ulm::tui::{self, display_error, run_tui, run_tui_streaming, UserAction};
 use ulm::Result;
 
 /// Application entry point.
@@ -40,6 +41,7 @@ fn main() -> ExitCode {
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("ulm=info")),
         )
         .with_target(false)
+        .with_writer(log_writer)
         .init();
 
     debug!("ulm starting");
@@ -74,6 +76,18 @@ fn main() -> ExitCode {
     }
 }
 
+/// Returns the writer for log output.
+///
+/// The query pipeline keeps running while the TUI is open; its log output
+/// is dropped meanwhile so it doesn't draw over the TUI.
+fn log_writer() -> Box<dyn Write> {
+    if tui::is_active() {
+        Box::new(io::sink())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
 /// Main application logic.
 ///
 /// # Errors
@@ -101,11 +115,31 @@ async fn run() -> Result<u8> {
             setup::run_clean()?;
             Ok(0)
         }
+        Some(Commands::Index { command }) => {
+            match command {
+                IndexCommands::Export { file } => {
+                    info!(file = %file.display(), "exporting index");
+                    setup::bundle::run_export(&file).await?;
+                }
+                IndexCommands::Import { file } => {
+                    info!(file = %file.display(), "importing index");
+                    setup::bundle::run_import(&file).await?;
+                }
+            }
+            Ok(0)
+        }
+        Some(Commands::Prompt { command }) => match command {
+            PromptCommands::Show { query } => {
+                let query = query.join(" ");
+                info!(%query, "showing prompt");
+                show_prompt(&query).await
+            }
+        },
         None => {
             if args.has_query() {
                 let query = args.query_string();
                 info!(%query, "processing query");
-                process_query_flow(&query).await
+                process_query_flow(&query, args.seed).await
             } else {
                 println!("ulm - AI-powered manpage assistant");
                 println!("Run 'ulm --help' for usage information.");
@@ -116,17 +150,34 @@ async fn run() -> Result<u8> {
 }
 
 /// Processes a query and shows the TUI for user interaction.
-async fn process_query_flow(query: &str) -> Result<u8> {
-    // Get suggestions from the query pipeline
-    let suggestions = query::process_query(query).await?;
+///
+/// On a terminal, the TUI opens right away and suggestions appear in it
+/// while the query pipeline generates them in the background. `seed`
+/// overrides the configured LLM seed.
+async fn process_query_flow(query: &str, seed: Option<i64>) -> Result<u8> {
+    // Always show TUI for user confirmation
+    let action = if io::stdout().is_terminal() {
+        let (updates, receiver) = mpsc::unbounded_channel();
+        let query = query.to_string();
+        let pipeline =
+            tokio::spawn(async move { query::stream_query(&query, seed, updates).await });
 
-    if suggestions.is_empty() {
-        println!("No suggestions found for: {query}");
-        return Ok(0);
-    }
+        let action = run_tui_streaming(receiver);
 
-    // Always show TUI for user confirmation
-    let action = run_tui(suggestions)?;
+        // Nothing more to generate once the user has chosen
+        pipeline.abort();
+        action?
+    } else {
+        // Report pipeline errors before trying to open the TUI
+        let suggestions = query::process_query(query, seed).await?;
+
+        if suggestions.is_empty() {
+            println!("No suggestions found for: {query}");
+            return Ok(0);
+        }
+
+        run_tui(suggestions)?
+    };
 
     // Handle user action
     match action {
@@ -147,6 +198,21 @@ async fn process_query_flow(query: &str) -> Result<u8> {
     }
 }
 
+/// Prints the chat messages a query would send to the LLM.
+async fn show_prompt(query: &str) -> Result<u8> {
+    let messages = query::render_prompt(query).await?;
+
+    for (i, message) in messages.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("=== {:?} ===", message.role);
+        println!("{}", message.content);
+    }
+
+    Ok(0)
+}
+
 /// Prints a message to stdout and flushes.
 #[allow(dead_code)]
 fn print_flush(msg: &str) {
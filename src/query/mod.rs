@@ -3,11 +3,19 @@
 //! This module handles user queries by combining semantic search,
 //! directory context awareness, and LLM-powered response generation.
 
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
-use tracing::{debug, info};
+use futures::StreamExt;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, info, warn};
 
-use crate::llm::{build_prompt, parse_suggestions, OllamaClient};
-use crate::setup::load_config;
+use crate::llm::{
+    build_correction, build_messages, context_length, parse_suggestions, suggestions_schema,
+    text_generator, ChatMessage, PromptBudget, PromptTemplate, SuggestionParser, TextGenerator,
+    Token, DEFAULT_RESPONSE_TOKENS,
+};
+use crate::setup::{load_config, ModelOptions};
 
 pub mod context;
 pub mod search;
@@ -18,32 +26,301 @@ pub use search::{load_manpage_content, search_tools, SearchMatch};
 // Re-export for convenience
 pub use crate::llm::{CommandSuggestion, RiskLevel};
 
-/// Maximum number of tools to retrieve from vector search.
+/// Maximum number of tools to retrieve from hybrid search.
 const MAX_SEARCH_RESULTS: usize = 3;
 
+/// Progress of a streamed query, sent to the TUI as it happens.
+#[derive(Debug)]
+pub enum QueryUpdate {
+    /// The pipeline moved on to a new step (e.g., "Searching manpages...").
+    Status(String),
+    /// A suggestion whose JSON object is complete.
+    Suggestion(CommandSuggestion),
+    /// Reasoning of a thinking model, generated before the suggestions.
+    Reasoning(String),
+    /// Generation finished.
+    Done,
+    /// The query failed before any suggestion could be shown.
+    Failed(anyhow::Error),
+}
+
+/// Chat messages for a query together with the model and backend to send them to.
+struct PreparedQuery {
+    /// System and user messages of the query.
+    messages: Vec<ChatMessage>,
+    /// LLM model to generate with.
+    llm_model: String,
+    /// Text generator of the configured backend.
+    generator: Arc<dyn TextGenerator>,
+    /// Corrective requests allowed after a response that cannot be parsed.
+    max_retries: u32,
+}
+
+/// Result of streaming one response.
+enum StreamOutcome {
+    /// Suggestions were sent, or generation stopped; nothing left to do.
+    Finished,
+    /// The complete response is invalid and no suggestion was sent.
+    Invalid {
+        /// The invalid response text.
+        response: String,
+        /// Why the response could not be parsed.
+        error: anyhow::Error,
+    },
+}
+
 /// Processes a user query and returns command suggestions.
 ///
 /// Orchestrates the full query pipeline:
-/// 1. Search for relevant tools via vector similarity
-/// 2. Load manpage content for top match
+/// 1. Search for relevant tools via hybrid lexical + vector search
+/// 2. Load manpage content for top match (tool name and section)
 /// 3. Scan current directory context
-/// 4. Build LLM prompt
-/// 5. Generate response via Ollama
-/// 6. Parse JSON response
+/// 4. Build chat messages with the manpage and matched chunks
+/// 5. Generate response via the configured LLM backend
+/// 6. Parse JSON response, asking the model to correct an invalid response
+///    up to `generation.max_retries` times
 ///
 /// # Arguments
 ///
 /// * `query` - Natural language query describing desired functionality
+/// * `seed` - LLM seed overriding `models.options.seed`, to reproduce
+///   suggestions
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - No matching tools found in index
-/// - Ollama API call fails
-/// - Response parsing fails
-pub async fn process_query(query: &str) -> Result<Vec<CommandSuggestion>> {
+/// - LLM API call fails
+/// - Response parsing fails after all retries
+pub async fn process_query(query: &str, seed: Option<i64>) -> Result<Vec<CommandSuggestion>> {
+    let prepared = prepare_query(query, seed).await?;
+
+    info!(model = %prepared.llm_model, "Calling LLM backend for response generation");
+
+    generate_suggestions(&prepared).await
+}
+
+/// Builds the chat messages a query would send to the LLM, without sending
+/// them.
+///
+/// Runs the same steps as [`process_query`] up to the LLM call, so the
+/// result shows the effect of a prompt template.
+///
+/// # Errors
+///
+/// Returns an error if the prompt template is invalid, no matching tools
+/// are found, or the manpage cannot be loaded.
+pub async fn render_prompt(query: &str) -> Result<Vec<ChatMessage>> {
+    Ok(prepare_query(query, None).await?.messages)
+}
+
+/// Generates and parses the suggestions of a prepared query (steps 5-6).
+///
+/// # Errors
+///
+/// Returns an error if the LLM call fails, or the last response cannot be
+/// parsed.
+async fn generate_suggestions(prepared: &PreparedQuery) -> Result<Vec<CommandSuggestion>> {
+    let schema = suggestions_schema();
+    let mut messages = prepared.messages.clone();
+    let mut retries = 0;
+    loop {
+        let response = prepared
+            .generator
+            .chat(&prepared.llm_model, &messages, Some(&schema))
+            .await
+            .context("Failed to generate LLM response")?;
+
+        debug!(response_len = response.len(), "Received LLM response");
+
+        // Step 6: Parse response
+        match parse_suggestions(&response) {
+            Ok(suggestions) => {
+                info!(suggestions = suggestions.len(), "Query processing complete");
+                return Ok(suggestions);
+            }
+            Err(err) if retries < prepared.max_retries => {
+                retries += 1;
+                warn!(retry = retries, error = %err, "Invalid LLM response, asking for a correction");
+                messages.extend(build_correction(&response, &format!("{err:#}")));
+            }
+            Err(err) => return Err(err.context("Failed to parse LLM response")),
+        }
+    }
+}
+
+/// Processes a user query, reporting progress and suggestions as they arrive.
+///
+/// Runs the same pipeline as [`process_query`], but streams the LLM
+/// response and sends each suggestion to `updates` as soon as its JSON
+/// object is complete. Ends with [`QueryUpdate::Done`], or with
+/// [`QueryUpdate::Failed`] if no suggestion could be produced. Stops early
+/// when the receiver is dropped. `seed` overrides `models.options.seed`.
+pub async fn stream_query(query: &str, seed: Option<i64>, updates: UnboundedSender<QueryUpdate>) {
+    let update = match generate_streaming(query, seed, &updates).await {
+        Ok(()) => QueryUpdate::Done,
+        Err(err) => QueryUpdate::Failed(err),
+    };
+
+    // The receiver is gone once the user left the TUI
+    let _ = updates.send(update);
+}
+
+/// Runs the streaming pipeline, sending status updates and suggestions.
+///
+/// A response that cannot be parsed and produced no suggestion is sent
+/// back to the model for correction, up to `generation.max_retries` times.
+///
+/// # Errors
+///
+/// Returns an error if any step fails before a suggestion was sent, or the
+/// last complete response contains no valid suggestion.
+async fn generate_streaming(
+    query: &str,
+    seed: Option<i64>,
+    updates: &UnboundedSender<QueryUpdate>,
+) -> Result<()> {
+    send_status(updates, "Searching manpages...");
+    let prepared = prepare_query(query, seed).await?;
+
+    send_status(
+        updates,
+        &format!("Generating suggestions with {}...", prepared.llm_model),
+    );
+    info!(model = %prepared.llm_model, "Streaming response from LLM backend");
+
+    stream_suggestions(&prepared, updates).await
+}
+
+/// Streams the suggestions of a prepared query, retrying invalid responses.
+///
+/// # Errors
+///
+/// Returns an error if generation fails before a suggestion was sent, or
+/// the last complete response contains no valid suggestion.
+async fn stream_suggestions(
+    prepared: &PreparedQuery,
+    updates: &UnboundedSender<QueryUpdate>,
+) -> Result<()> {
+    let mut messages = prepared.messages.clone();
+    let mut retries = 0;
+    loop {
+        match stream_response(prepared, &messages, updates).await? {
+            StreamOutcome::Finished => return Ok(()),
+            StreamOutcome::Invalid { response, error } if retries < prepared.max_retries => {
+                retries += 1;
+                warn!(retry = retries, error = %error, "Invalid LLM response, asking for a correction");
+                send_status(
+                    updates,
+                    &format!(
+                        "Response was invalid, asking {} to correct it...",
+                        prepared.llm_model
+                    ),
+                );
+                messages.extend(build_correction(&response, &format!("{error:#}")));
+            }
+            StreamOutcome::Invalid { error, .. } => {
+                return Err(error.context("Failed to parse LLM response"))
+            }
+        }
+    }
+}
+
+/// Streams one response to `messages`, sending each suggestion as it completes.
+///
+/// Suggestions that only the complete response yields (e.g., a single
+/// suggestion outside the array) are sent at the end.
+///
+/// # Errors
+///
+/// Returns an error if the request fails, or the stream fails before a
+/// suggestion was sent.
+async fn stream_response(
+    prepared: &PreparedQuery,
+    messages: &[ChatMessage],
+    updates: &UnboundedSender<QueryUpdate>,
+) -> Result<StreamOutcome> {
+    let schema = suggestions_schema();
+    let mut tokens = prepared
+        .generator
+        .chat_stream(&prepared.llm_model, messages, Some(&schema))
+        .await
+        .context("Failed to generate LLM response")?;
+
+    let mut parser = SuggestionParser::new();
+    let mut sent = 0;
+    while let Some(token) = tokens.next().await {
+        let text = match token {
+            Ok(Token::Text(text)) => text,
+            Ok(Token::Reasoning(text)) => {
+                if updates.send(QueryUpdate::Reasoning(text)).is_err() {
+                    debug!("Query receiver dropped, stopping generation");
+                    return Ok(StreamOutcome::Finished);
+                }
+                continue;
+            }
+            // Keep what was shown if the stream breaks off
+            Err(err) if sent > 0 => {
+                warn!(error = %err, "LLM response stream failed");
+                send_status(updates, &format!("Generation stopped: {err}"));
+                return Ok(StreamOutcome::Finished);
+            }
+            Err(err) => return Err(err.context("Failed to generate LLM response")),
+        };
+
+        for suggestion in parser.push(&text) {
+            if updates.send(QueryUpdate::Suggestion(suggestion)).is_err() {
+                debug!("Query receiver dropped, stopping generation");
+                return Ok(StreamOutcome::Finished);
+            }
+            sent += 1;
+        }
+    }
+
+    debug!(response_len = parser.text().len(), "Received LLM response");
+
+    // Validate the complete response; streamed suggestions are kept either way
+    match parser.finish() {
+        Ok(suggestions) => {
+            info!(suggestions = suggestions.len(), "Query processing complete");
+            if sent == 0 {
+                for suggestion in suggestions {
+                    let _ = updates.send(QueryUpdate::Suggestion(suggestion));
+                }
+            }
+        }
+        Err(err) if sent > 0 => warn!(error = %err, "Complete LLM response is invalid"),
+        Err(error) => {
+            return Ok(StreamOutcome::Invalid {
+                response: parser.text().to_string(),
+                error,
+            })
+        }
+    }
+
+    Ok(StreamOutcome::Finished)
+}
+
+/// Sends a status update; the receiver is gone once the user left the TUI.
+fn send_status(updates: &UnboundedSender<QueryUpdate>, message: &str) {
+    let _ = updates.send(QueryUpdate::Status(message.to_string()));
+}
+
+/// Runs the pipeline up to the LLM call (steps 1-4) and builds the messages.
+///
+/// The text generator uses the configured model options, with `seed` (if
+/// given) replacing the configured seed.
+///
+/// # Errors
+///
+/// Returns an error if the prompt template is invalid, no matching tools are
+/// found, the manpage cannot be loaded, or the config cannot be read.
+async fn prepare_query(query: &str, seed: Option<i64>) -> Result<PreparedQuery> {
     info!(query = %query, "Processing query");
 
+    // Check the template before the slower steps
+    let template = PromptTemplate::load().context("Failed to load prompt template")?;
+
     // Step 1: Search for relevant tools
     let matches = search_tools(query, MAX_SEARCH_RESULTS)
         .await
@@ -63,13 +340,16 @@ pub async fn process_query(query: &str) -> Result<Vec<CommandSuggestion>> {
         "Found matching tools"
     );
 
-    // Step 2: Load manpage content for top match
+    // Step 2: Load manpage content for top match, from the section and language that matched
     let top_tool = &matches[0].tool_name;
-    let manpage_content =
-        load_manpage_content(top_tool).context("Failed to load manpage content")?;
+    let top_section = &matches[0].section;
+    let manpage_content = load_manpage_content(top_tool, top_section, &matches[0].language)
+        .await
+        .context("Failed to load manpage content")?;
 
     debug!(
         tool = %top_tool,
+        section = %top_section,
         content_len = manpage_content.len(),
         "Loaded manpage content"
     );
@@ -83,35 +363,236 @@ pub async fn process_query(query: &str) -> Result<Vec<CommandSuggestion>> {
         "Scanned directory context"
     );
 
-    // Step 4: Build prompt
-    let prompt = build_prompt(query, &manpage_content, &context);
+    // Step 4: Build chat messages that fit the model's context window; step 5
+    // is run by the caller with this generator
+    let mut config = load_config().context("Failed to load config")?;
+    if seed.is_some() {
+        config.models.options.seed = seed;
+    }
+    let llm_model = config.llm_model().to_string();
+    let mut generator = text_generator(&config).context("Failed to create LLM backend")?;
 
-    debug!(prompt_len = prompt.len(), "Built prompt");
+    // A window beyond the trained length only costs memory
+    let model_context = context_length(
+        generator.as_ref(),
+        config.backend,
+        config.backend_url(),
+        &llm_model,
+    )
+    .await;
+    if let Some(length) = model_context.filter(|&length| length < config.models.options.num_ctx) {
+        debug!(
+            num_ctx = length,
+            "Capping context window at the model's context length"
+        );
+        config.models.options.num_ctx = length;
+        generator = text_generator(&config).context("Failed to create LLM backend")?;
+    }
 
-    // Step 5: Call Ollama to generate response
-    let config = load_config().context("Failed to load config")?;
-    let llm_model = config.llm_model();
+    let budget = prompt_budget(&config.models.options, model_context);
+    let messages = build_messages(
+        query,
+        &manpage_content,
+        &matches,
+        &context,
+        budget,
+        &template,
+    );
 
-    let client = OllamaClient::with_config(
-        config.ollama_url(),
-        config.generate_timeout_secs(),
-        config.embedding_timeout_secs(),
-    )
-    .context("Failed to create Ollama client")?;
+    debug!(
+        prompt_len = messages.iter().map(|m| m.content.len()).sum::<usize>(),
+        budget = budget.tokens(),
+        "Built chat messages"
+    );
 
-    info!(model = %llm_model, "Calling Ollama for response generation");
+    Ok(PreparedQuery {
+        messages,
+        llm_model,
+        generator,
+        max_retries: config.generation.max_retries,
+    })
+}
 
-    let response = client
-        .generate(llm_model, &prompt, true)
-        .await
-        .context("Failed to generate LLM response")?;
+/// Returns the prompt budget of the configured context window.
+///
+/// The window is the configured `num_ctx`, capped at the context length the
+/// model was trained with if the backend reports it. The response keeps
+/// `num_predict` tokens free if it's limited.
+fn prompt_budget(options: &ModelOptions, model_context: Option<u32>) -> PromptBudget {
+    let window = model_context.map_or(options.num_ctx, |length| length.min(options.num_ctx));
+    let response_tokens = options
+        .num_predict
+        .and_then(|tokens| u32::try_from(tokens).ok())
+        .filter(|&tokens| tokens > 0)
+        .unwrap_or(DEFAULT_RESPONSE_TOKENS);
+
+    PromptBudget::new(window, response_tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
 
-    debug!(response_len = response.len(), "Received LLM response");
+    use futures::future::{BoxFuture, FutureExt};
+    use tokio::sync::mpsc::unbounded_channel;
 
-    // Step 6: Parse response
-    let suggestions = parse_suggestions(&response).context("Failed to parse LLM response")?;
+    use super::*;
+    use crate::llm::{split_reasoning, Role, TokenStream};
 
-    info!(suggestions = suggestions.len(), "Query processing complete");
+    /// Text generator that replies with canned responses, in order.
+    #[derive(Debug)]
+    struct FakeGenerator {
+        /// Responses still to give.
+        responses: Mutex<Vec<&'static str>>,
+        /// Messages of every request.
+        requests: Mutex<Vec<Vec<ChatMessage>>>,
+    }
+
+    impl FakeGenerator {
+        fn new(responses: &[&'static str]) -> Self {
+            Self {
+                responses: Mutex::new(responses.iter().rev().copied().collect()),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn reply(&self, messages: &[ChatMessage]) -> Result<String> {
+            self.requests.lock().unwrap().push(messages.to_vec());
+            let response = self.responses.lock().unwrap().pop();
+            response.map(str::to_string).context("no response left")
+        }
+    }
+
+    impl TextGenerator for FakeGenerator {
+        fn chat<'a>(
+            &'a self,
+            _model: &'a str,
+            messages: &'a [ChatMessage],
+            _format: Option<&'a serde_json::Value>,
+        ) -> BoxFuture<'a, Result<String>> {
+            let response = self.reply(messages);
+            async move { response }.boxed()
+        }
+
+        fn chat_stream<'a>(
+            &'a self,
+            _model: &'a str,
+            messages: &'a [ChatMessage],
+            _format: Option<&'a serde_json::Value>,
+        ) -> BoxFuture<'a, Result<TokenStream>> {
+            // Reasoning arrives apart from the answer, as from the clients
+            let tokens = match self.reply(messages) {
+                Ok(response) => {
+                    let (reasoning, answer) = split_reasoning(&response);
+                    [Token::Reasoning(reasoning), Token::Text(answer)]
+                        .into_iter()
+                        .map(Ok)
+                        .collect()
+                }
+                Err(err) => vec![Err(err)],
+            };
+            async move {
+                let tokens: TokenStream = futures::stream::iter(tokens).boxed();
+                Ok(tokens)
+            }
+            .boxed()
+        }
+    }
+
+    const VALID: &str = r#"{"suggestions": [{"command": "ls", "title": "List", "explanation": "Lists files", "risk_level": "safe"}]}"#;
+
+    fn prepared(generator: &Arc<FakeGenerator>, max_retries: u32) -> PreparedQuery {
+        PreparedQuery {
+            messages: vec![
+                ChatMessage::system("instructions"),
+                ChatMessage::user("list files"),
+            ],
+            llm_model: "test".to_string(),
+            generator: generator.clone(),
+            max_retries,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_response_is_corrected() {
+        let generator = Arc::new(FakeGenerator::new(&["Sure! Run ls.", VALID]));
+
+        let suggestions = generate_suggestions(&prepared(&generator, 2))
+            .await
+            .unwrap();
+        assert_eq!(suggestions[0].command, "ls");
+
+        // The retry shows the model its invalid output and the expected shape
+        let requests = generator.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].len(), 4);
+        assert_eq!(requests[1][2], ChatMessage::assistant("Sure! Run ls."));
+        assert_eq!(requests[1][3].role, Role::User);
+        assert!(requests[1][3].content.contains("\"suggestions\""));
+    }
 
-    Ok(suggestions)
+    #[tokio::test]
+    async fn test_retries_are_capped() {
+        let generator = Arc::new(FakeGenerator::new(&["nope", "still no", VALID]));
+
+        let err = generate_suggestions(&prepared(&generator, 1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Failed to parse LLM response"));
+        assert_eq!(generator.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_response_is_corrected() {
+        let generator = Arc::new(FakeGenerator::new(&[
+            "no json",
+            r#"<think>Maybe pwd.</think>{"command": "pwd", "title": "Where", "explanation": "Prints it", "risk_level": "safe"}"#,
+        ]));
+        let (sender, mut receiver) = unbounded_channel();
+
+        stream_suggestions(&prepared(&generator, 1), &sender)
+            .await
+            .unwrap();
+        drop(sender);
+
+        // A suggestion outside the array is sent once the response is complete
+        let mut suggestions = Vec::new();
+        let mut reasoning = String::new();
+        while let Some(update) = receiver.recv().await {
+            match update {
+                QueryUpdate::Suggestion(suggestion) => suggestions.push(suggestion.command),
+                QueryUpdate::Reasoning(text) => reasoning.push_str(&text),
+                _ => {}
+            }
+        }
+        assert_eq!(suggestions, ["pwd"]);
+        assert_eq!(reasoning, "Maybe pwd.");
+        assert_eq!(generator.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_prompt_budget() {
+        let options = ModelOptions::default();
+        assert_eq!(
+            prompt_budget(&options, None),
+            PromptBudget::new(options.num_ctx, DEFAULT_RESPONSE_TOKENS)
+        );
+        assert_eq!(
+            prompt_budget(&options, Some(4096)),
+            PromptBudget::new(4096, DEFAULT_RESPONSE_TOKENS)
+        );
+        assert_eq!(
+            prompt_budget(&options, Some(131_072)),
+            PromptBudget::new(options.num_ctx, DEFAULT_RESPONSE_TOKENS)
+        );
+
+        let options = ModelOptions {
+            num_predict: Some(256),
+            ..ModelOptions::default()
+        };
+        assert_eq!(
+            prompt_budget(&options, None),
+            PromptBudget::new(options.num_ctx, 256)
+        );
+    }
 }
@@ -1,19 +1,15 @@
-//! Vector search for finding relevant manpages.
+//! Hybrid search for finding relevant manpages.
 //!
-//! This module provides semantic search functionality using the manpage
-//! embedding index to find tools relevant to user queries.
-
-use std::process::Command;
+//! This module combines semantic search over the manpage embedding index
+//! with lexical search over tool names and descriptions to find tools
+//! relevant to user queries.
 
 use anyhow::{Context, Result};
 use tracing::{debug, info};
 
-use crate::db;
-use crate::llm::OllamaClient;
-use crate::setup::load_config;
-
-/// Maximum characters to include in manpage content for LLM context.
-const MAX_CONTENT_LENGTH: usize = 8000;
+use crate::db::{self, ChunkMatch};
+use crate::llm::embedder;
+use crate::setup::{load_config, ManpageScanner};
 
 /// A search result matching a user query.
 #[derive(Debug, Clone)]
@@ -22,16 +18,24 @@ pub struct SearchMatch {
     pub tool_name: String,
     /// Man section (e.g., "1").
     pub section: String,
+    /// Language of the page (e.g., "de"; "en" for untranslated pages).
+    pub language: String,
     /// Description text.
     pub description: String,
-    /// Similarity score (distance - lower is better).
+    /// Fused relevance score (higher is better).
     pub score: f32,
+    /// Vector similarity to the query in `0..=1`, if the tool was among
+    /// the nearest neighbors.
+    pub similarity: Option<f32>,
+    /// Chunks of the manpage that matched the query, closest first.
+    pub chunks: Vec<ChunkMatch>,
 }
 
 /// Searches for tools matching the given query.
 ///
-/// Generates an embedding for the query and performs vector similarity
-/// search against the manpage index.
+/// Generates an embedding for the query and performs hybrid search
+/// (vector similarity over manpage summaries and chunks fused with BM25)
+/// against the manpage index. Each match carries the chunks that matched.
 ///
 /// # Arguments
 ///
@@ -52,47 +56,41 @@ pub async fn search_tools(query: &str, limit: usize) -> Result<Vec<SearchMatch>>
         anyhow::bail!("Index not found. Please run 'ulm setup' first.");
     }
 
+    // An update without a reachable embedding model leaves an empty index
+    if db::count_entries()
+        .await
+        .context("Failed to count entries")?
+        == 0
+    {
+        anyhow::bail!("Index is empty. Please run 'ulm setup' first.");
+    }
+
     // Load config to get embedding model
     let config = load_config().context("Failed to load config")?;
     let embedding_model = config.embedding_model();
 
-    // Validate embedding model matches index
-    if config.needs_index_rebuild() {
-        let last_model = config
-            .index
-            .last_embedding_model
-            .as_deref()
-            .unwrap_or("unknown");
-        anyhow::bail!(
-            "Index was built with '{last_model}' but config uses '{embedding_model}'.\n\
-             Run 'ulm setup' to rebuild index with the current embedding model."
-        );
-    }
-
-    // If index exists but no model metadata, warn user to rebuild
-    if config.index.last_embedding_model.is_none() {
+    // The index keeps vectors per embedding model
+    if !db::has_vectors(embedding_model)
+        .await
+        .context("Failed to check index")?
+    {
         anyhow::bail!(
-            "Index was built with an unknown embedding model.\n\
-             Run 'ulm setup' to rebuild index with '{embedding_model}'."
+            "Index has no embeddings for '{embedding_model}'.\n\
+             Run 'ulm update' to embed manpages with it."
         );
     }
 
     // Generate query embedding
-    let client = OllamaClient::with_config(
-        config.ollama_url(),
-        config.generate_timeout_secs(),
-        config.embedding_timeout_secs(),
-    )
-    .context("Failed to create Ollama client")?;
-    let embedding = client
-        .generate_embedding(embedding_model, query)
+    let embedder = embedder(&config).context("Failed to create embedding backend")?;
+    let embedding = embedder
+        .embed(embedding_model, query)
         .await
         .context("Failed to generate query embedding")?;
 
     debug!(dimensions = embedding.len(), "Generated query embedding");
 
-    // Perform vector search
-    let results = db::search(&embedding, limit)
+    // Perform hybrid search
+    let results = db::search(embedding_model, &embedding, query, limit, &config.search)
         .await
         .context("Failed to search database")?;
 
@@ -102,8 +100,11 @@ pub async fn search_tools(query: &str, limit: usize) -> Result<Vec<SearchMatch>>
         .map(|r| SearchMatch {
             tool_name: r.tool_name,
             section: r.section,
+            language: r.language,
             description: r.description,
             score: r.score,
+            similarity: r.similarity,
+            chunks: r.chunks,
         })
         .collect();
 
@@ -117,6 +118,7 @@ pub async fn search_tools(query: &str, limit: usize) -> Result<Vec<SearchMatch>>
                 rank = i + 1,
                 tool = %m.tool_name,
                 score = m.score,
+                similarity = ?m.similarity,
                 "Search result"
             );
         }
@@ -125,118 +127,40 @@ pub async fn search_tools(query: &str, limit: usize) -> Result<Vec<SearchMatch>>
     Ok(matches)
 }
 
-/// Loads the full content of a manpage.
+/// Loads the full content of a manpage from the given section and language.
 ///
-/// Runs `man -P cat <tool>` to get the raw manpage content, cleans
-/// escape sequences, and truncates to fit LLM context limits.
+/// Reads the cleaned manpage text stored in the index. Falls back to
+/// running `man -P cat <section> <tool>` for entries indexed before the
+/// text was stored. The full text is returned; the prompt budget decides
+/// how much of it reaches the model.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - The manpage doesn't exist
-/// - The man command fails
+/// - The index cannot be read
+/// - The manpage is not stored and the man command fails
 /// - Output contains invalid UTF-8
-pub fn load_manpage_content(tool_name: &str) -> Result<String> {
-    debug!(tool = %tool_name, "Loading manpage content");
-
-    // Run man -P cat to get raw content
-    let output = Command::new("man")
-        .args(["-P", "cat", tool_name])
-        .output()
-        .with_context(|| format!("Failed to execute man command for '{tool_name}'"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Manpage for '{}' not found: {}", tool_name, stderr.trim());
-    }
-
-    // Convert to UTF-8
-    let content = String::from_utf8(output.stdout)
-        .with_context(|| format!("Manpage '{tool_name}' contains invalid UTF-8"))?;
-
-    // Clean escape sequences
-    let cleaned = clean_escape_sequences(&content);
-
-    // Truncate to max length
-    let truncated = truncate_content(&cleaned, MAX_CONTENT_LENGTH);
-
-    debug!(
-        original_len = content.len(),
-        cleaned_len = cleaned.len(),
-        final_len = truncated.len(),
-        "Loaded manpage content"
-    );
-
-    Ok(truncated)
-}
-
-/// Removes ANSI escape sequences from text.
-fn clean_escape_sequences(text: &str) -> String {
-    // Simple approach: remove common escape patterns
-    let mut result = String::with_capacity(text.len());
-    let mut chars = text.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '\x1b' {
-            // Skip escape sequence
-            if chars.peek() == Some(&'[') {
-                chars.next(); // consume '['
-                              // Skip until we hit a letter (end of sequence)
-                while let Some(&next) = chars.peek() {
-                    chars.next();
-                    if next.is_ascii_alphabetic() {
-                        break;
-                    }
-                }
-            }
-        } else {
-            result.push(c);
-        }
-    }
-
-    // Normalize whitespace: collapse multiple spaces/newlines
-    let mut normalized = String::with_capacity(result.len());
-    let mut prev_whitespace = false;
-    let mut prev_newline = false;
-
-    for c in result.chars() {
-        if c == '\n' {
-            if !prev_newline {
-                normalized.push('\n');
-                prev_newline = true;
-            }
-            prev_whitespace = true;
-        } else if c.is_whitespace() {
-            if !prev_whitespace {
-                normalized.push(' ');
-                prev_whitespace = true;
-            }
-            prev_newline = false;
-        } else {
-            normalized.push(c);
-            prev_whitespace = false;
-            prev_newline = false;
-        }
-    }
-
-    normalized
-}
+pub async fn load_manpage_content(
+    tool_name: &str,
+    section: &str,
+    language: &str,
+) -> Result<String> {
+    debug!(tool = %tool_name, section = %section, language = %language, "Loading manpage content");
+
+    let stored = db::load_content(tool_name, section, language)
+        .await
+        .context("Failed to read manpage from index")?;
 
-/// Truncates content to maximum length, respecting UTF-8 boundaries.
-fn truncate_content(text: &str, max_len: usize) -> String {
-    if text.len() <= max_len {
-        return text.to_string();
-    }
+    let content = if let Some(content) = stored {
+        content
+    } else {
+        debug!(tool = %tool_name, section = %section, "Manpage text not in index, running man");
+        ManpageScanner::render_manpage(tool_name, section, language)?
+    };
 
-    // Find last valid char boundary
-    let mut end = max_len;
-    while !text.is_char_boundary(end) && end > 0 {
-        end -= 1;
-    }
+    debug!(content_len = content.len(), "Loaded manpage content");
 
-    let mut result = text[..end].to_string();
-    result.push_str("\n\n[Content truncated...]");
-    result
+    Ok(content)
 }
 
 #[cfg(test)]
@@ -248,8 +172,11 @@ mod tests {
         let match_result = SearchMatch {
             tool_name: "ffmpeg".to_string(),
             section: "1".to_string(),
+            language: "en".to_string(),
             description: "video converter".to_string(),
             score: 0.5,
+            similarity: None,
+            chunks: Vec::new(),
         };
         let debug_str = format!("{match_result:?}");
         assert!(debug_str.contains("ffmpeg"));
@@ -260,8 +187,11 @@ mod tests {
         let match_result = SearchMatch {
             tool_name: "ls".to_string(),
             section: "1".to_string(),
+            language: "en".to_string(),
             description: "list directory".to_string(),
             score: 0.3,
+            similarity: None,
+            chunks: Vec::new(),
         };
         let cloned = match_result.clone();
         assert_eq!(cloned.tool_name, "ls");
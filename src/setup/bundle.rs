@@ -0,0 +1,124 @@
+//! Index export and import.
+//!
+//! This module handles the `ulm index export` and `ulm index import`
+//! commands, which copy a built index between machines so it doesn't have
+//! to be re-embedded everywhere.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::{load_config, run_indexing};
+use crate::db::{self, BundleInfo};
+
+/// Exports the index into a portable bundle file.
+///
+/// # Errors
+///
+/// Returns an error if the index doesn't exist or the bundle cannot be
+/// written.
+pub async fn run_export(path: &Path) -> Result<()> {
+    println!("ulm index export - Writing index bundle\n");
+
+    if !db::index_exists().await.context("Failed to check index")? {
+        anyhow::bail!("Index not found. Please run 'ulm setup' first.");
+    }
+
+    let info = db::export_index(path)
+        .await
+        .context("Failed to export index")?;
+
+    let count = db::count_entries()
+        .await
+        .context("Failed to count entries")?;
+    println!(
+        "✓ Exported {count} manpages ({}) to {}",
+        info.embedding_models.join(", "),
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Imports an index bundle and refreshes it against local manpages.
+///
+/// The bundle must have vectors for the configured embedding model. After
+/// import, an incremental update re-embeds only the manpages that differ on
+/// this machine.
+///
+/// # Errors
+///
+/// Returns an error if the bundle is invalid, has no vectors for the
+/// configured embedding model, or the refresh fails.
+pub async fn run_import(path: &Path) -> Result<()> {
+    println!("ulm index import - Loading index bundle\n");
+
+    let config = load_config().context("Failed to load config")?;
+    let info = db::read_bundle_info(path)
+        .await
+        .context("Failed to read bundle")?;
+    check_embedding_model(&info, config.embedding_model())?;
+
+    let _lock = db::lock_index().context("Failed to lock index")?;
+    db::import_index(path)
+        .await
+        .context("Failed to import index")?;
+
+    let imported = db::count_entries()
+        .await
+        .context("Failed to count entries")?;
+    println!("✓ Imported {imported} manpages from {}\n", path.display());
+
+    // Re-embed manpages that differ from the exporting machine
+    let count = run_indexing().await?;
+
+    println!("\n✓ Import complete! Indexed {count} manpages");
+    println!(
+        "  Database location: {}",
+        db::get_database_path()?.display()
+    );
+
+    Ok(())
+}
+
+/// Checks that a bundle has vectors for the configured embedding model.
+fn check_embedding_model(info: &BundleInfo, embedding_model: &str) -> Result<()> {
+    if !info.embedding_models.iter().any(|m| m == embedding_model) {
+        anyhow::bail!(
+            "Bundle has no embeddings for '{embedding_model}' (available: {}).\n\
+             Set models.embedding_model to one of them or export the bundle after \
+             running 'ulm update' with '{embedding_model}'.",
+            info.embedding_models.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_info(model: &str) -> BundleInfo {
+        BundleInfo {
+            embedding_models: vec![model.to_string(), "all-minilm".to_string()],
+            schema_version: db::SCHEMA_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_check_embedding_model_matches() {
+        let info = test_info("nomic-embed-text");
+        assert!(check_embedding_model(&info, "nomic-embed-text").is_ok());
+        assert!(check_embedding_model(&info, "all-minilm").is_ok());
+    }
+
+    #[test]
+    fn test_check_embedding_model_mismatch() {
+        let err =
+            check_embedding_model(&test_info("mxbai-embed-large"), "nomic-embed-text").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("mxbai-embed-large"));
+        assert!(message.contains("nomic-embed-text"));
+    }
+}
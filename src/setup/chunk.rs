@@ -0,0 +1,234 @@
+//! Splitting manpages into chunks for embedding.
+//!
+//! Besides the NAME + DESCRIPTION summary, each manpage is split into
+//! option-level chunks (one per `-x, --flag` item) and section-level chunks
+//! (e.g., EXAMPLES), so questions about a specific capability can match the
+//! text that documents it.
+
+use crate::setup::locale::canonical_header;
+
+/// Sections that are not worth embedding (English names of translated headers).
+const SKIPPED_SECTIONS: &[&str] = &[
+    "NAME",
+    "SYNOPSIS",
+    "SEE ALSO",
+    "AUTHOR",
+    "AUTHORS",
+    "REPORTING BUGS",
+    "COPYRIGHT",
+    "HISTORY",
+    "COLOPHON",
+];
+
+/// Maximum characters per chunk.
+const MAX_CHUNK_LENGTH: usize = 1000;
+
+/// Minimum characters for a chunk to be kept.
+const MIN_CHUNK_LENGTH: usize = 16;
+
+/// Maximum number of chunks per manpage.
+const MAX_CHUNKS_PER_PAGE: usize = 64;
+
+/// A piece of a manpage that is embedded on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManpageChunk {
+    /// Section header the chunk was taken from (e.g., "OPTIONS").
+    pub heading: String,
+    /// Chunk text.
+    pub text: String,
+}
+
+impl ManpageChunk {
+    /// Returns the text to embed, prefixed with tool name and section.
+    #[must_use]
+    pub fn embedding_text(&self, tool_name: &str) -> String {
+        format!("{tool_name} {}: {}", self.heading, self.text)
+    }
+}
+
+/// Chunk with its embedding vector for storage.
+#[derive(Debug, Clone)]
+pub struct ChunkEntry {
+    /// Section header the chunk was taken from.
+    pub heading: String,
+    /// Chunk text.
+    pub text: String,
+    /// Embedding vector.
+    pub vector: Vec<f32>,
+}
+
+impl ChunkEntry {
+    /// Creates an entry from a chunk and its embedding vector.
+    #[must_use]
+    pub fn from_chunk(chunk: &ManpageChunk, vector: Vec<f32>) -> Self {
+        Self {
+            heading: chunk.heading.clone(),
+            text: chunk.text.clone(),
+            vector,
+        }
+    }
+}
+
+/// Splits cleaned manpage text into option- and section-level chunks.
+///
+/// Each option item (a line starting with `-`) becomes one chunk together
+/// with its description. Remaining text of a section is split at line
+/// boundaries into chunks of at most [`MAX_CHUNK_LENGTH`] characters.
+#[must_use]
+pub fn split_chunks(content: &str) -> Vec<ManpageChunk> {
+    let mut chunks = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut current = String::new();
+
+    for line in content.lines() {
+        if is_section_header(line) {
+            flush(&mut chunks, heading.as_deref(), &mut current);
+            let name = line.trim().to_string();
+            let english = canonical_header(&name).unwrap_or(&name);
+            heading = (!SKIPPED_SECTIONS.contains(&english)).then_some(name);
+            continue;
+        }
+
+        if heading.is_none() {
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // Each option starts a new chunk; long text is split at line boundaries
+        if is_option_line(trimmed) || current.len() + trimmed.len() > MAX_CHUNK_LENGTH {
+            flush(&mut chunks, heading.as_deref(), &mut current);
+        }
+
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(trimmed);
+    }
+    flush(&mut chunks, heading.as_deref(), &mut current);
+
+    chunks.truncate(MAX_CHUNKS_PER_PAGE);
+    chunks
+}
+
+/// Moves the collected text into a new chunk if it is long enough.
+fn flush(chunks: &mut Vec<ManpageChunk>, heading: Option<&str>, current: &mut String) {
+    let text = std::mem::take(current);
+
+    if let Some(heading) = heading {
+        if text.len() >= MIN_CHUNK_LENGTH {
+            chunks.push(ManpageChunk {
+                heading: heading.to_string(),
+                text: truncate(text, MAX_CHUNK_LENGTH),
+            });
+        }
+    }
+}
+
+/// Checks if a line is a section header (unindented, all caps).
+fn is_section_header(line: &str) -> bool {
+    !line.starts_with(char::is_whitespace)
+        && line.trim().len() > 2
+        && line.chars().all(|c| c.is_uppercase() || c.is_whitespace())
+}
+
+/// Checks if a trimmed line starts an option item (e.g., "-a, --all").
+fn is_option_line(trimmed: &str) -> bool {
+    let mut chars = trimmed.chars();
+    chars.next() == Some('-')
+        && chars
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '-')
+}
+
+/// Truncates text to at most `max_len` bytes, respecting UTF-8 boundaries.
+fn truncate(mut text: String, max_len: usize) -> String {
+    if text.len() > max_len {
+        let mut end = max_len;
+        while !text.is_char_boundary(end) && end > 0 {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAR_PAGE: &str = "TAR(1) GNU TAR Manual TAR(1)
+NAME
+ tar - an archiving utility
+SYNOPSIS
+ tar [OPTION...] [FILE]...
+DESCRIPTION
+ GNU tar is an archiving program designed to store multiple files in a single file.
+OPTIONS
+ -p, --preserve-permissions
+ extract information about file permissions (default for superuser)
+ -v, --verbose
+ verbosely list files processed
+EXAMPLES
+ tar -cf archive.tar foo bar
+ Create archive.tar from files foo and bar.
+SEE ALSO
+ gzip(1)";
+
+    #[test]
+    fn test_split_chunks_options_and_examples() {
+        let chunks = split_chunks(TAR_PAGE);
+        let headings: Vec<&str> = chunks.iter().map(|c| c.heading.as_str()).collect();
+
+        assert_eq!(
+            headings,
+            vec!["DESCRIPTION", "OPTIONS", "OPTIONS", "EXAMPLES"]
+        );
+        assert_eq!(
+            chunks[1].text,
+            "-p, --preserve-permissions\nextract information about file permissions (default for superuser)"
+        );
+        assert!(chunks[3].text.starts_with("tar -cf archive.tar"));
+    }
+
+    #[test]
+    fn test_split_chunks_skips_name_and_see_also() {
+        let chunks = split_chunks(TAR_PAGE);
+        assert!(chunks
+            .iter()
+            .all(|c| c.heading != "NAME" && c.heading != "SEE ALSO"));
+    }
+
+    #[test]
+    fn test_split_chunks_limits_length() {
+        let line = format!(" {}\n", "word ".repeat(50));
+        let page = format!("EXAMPLES\n{}", line.repeat(20));
+
+        let chunks = split_chunks(&page);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.text.len() <= MAX_CHUNK_LENGTH));
+    }
+
+    #[test]
+    fn test_is_option_line() {
+        assert!(is_option_line("-a, --all"));
+        assert!(is_option_line("--color[=WHEN]"));
+        assert!(!is_option_line("- a bullet"));
+        assert!(!is_option_line("-"));
+    }
+
+    #[test]
+    fn test_embedding_text() {
+        let chunk = ManpageChunk {
+            heading: "OPTIONS".to_string(),
+            text: "-l use a long listing format".to_string(),
+        };
+        assert_eq!(
+            chunk.embedding_text("ls"),
+            "ls OPTIONS: -l use a long listing format"
+        );
+    }
+}
@@ -3,21 +3,60 @@
 //! This module handles loading and saving application configuration
 //! using XDG-compliant paths and TOML format.
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::setup::{locale, models};
+
 /// Application configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Config {
+    /// Server that generates text and embeddings.
+    #[serde(default)]
+    pub backend: Backend,
     /// Model configuration.
     pub models: ModelsConfig,
     /// Ollama server configuration.
     pub ollama: OllamaConfig,
     /// Index metadata.
     pub index: IndexConfig,
+    /// OpenAI-compatible server configuration, used with `backend = "openai"`.
+    #[serde(default)]
+    pub openai: OpenAiConfig,
+    /// Search ranking configuration.
+    #[serde(default)]
+    pub search: SearchConfig,
+    /// Response generation configuration.
+    #[serde(default)]
+    pub generation: GenerationConfig,
+}
+
+/// LLM and embedding server API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Ollama's native API (`/api/generate`, `/api/embeddings`).
+    #[default]
+    Ollama,
+    /// OpenAI-compatible API (`/v1/chat/completions`, `/v1/embeddings`), as
+    /// served by llama.cpp's `llama-server`, vLLM and others.
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+impl Backend {
+    /// Returns the name of the backend as used in the config.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ollama => "ollama",
+            Self::OpenAi => "openai",
+        }
+    }
 }
 
 /// Model configuration for embedding and LLM.
@@ -27,6 +66,85 @@ pub struct ModelsConfig {
     pub embedding_model: String,
     /// Model used for LLM response generation.
     pub llm_model: String,
+    /// Whether thinking models reason before answering, per model name
+    /// (e.g., `"qwen3" = false`); Ollama's `think` option.
+    ///
+    /// A name without a tag matches every tag of the model. Unlisted
+    /// models use their default.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub think: HashMap<String, bool>,
+    /// Generation options of the LLM model.
+    #[serde(default)]
+    pub options: ModelOptions,
+}
+
+/// Generation options sent with every request to the LLM model
+/// (`models.llm_model`); they don't apply to other models.
+///
+/// Unset options use the server's defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelOptions {
+    /// Context window in tokens; Ollama's default of 2048 cuts off the
+    /// manpage in the prompt. Ollama allocates the KV cache for the whole
+    /// window (about 2 GB for 16384 tokens of an 8B model), so machines
+    /// with little memory should lower it. Capped at the context length
+    /// the model was trained with.
+    #[serde(default = "default_num_ctx")]
+    pub num_ctx: u32,
+    /// Sampling temperature; low values give more predictable commands.
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    /// Nucleus sampling probability.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Random seed, to reproduce suggestions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Maximum number of tokens to generate (-1 for no limit).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    /// How long Ollama keeps the model loaded after a request (e.g., "10m";
+    /// negative durations keep it loaded).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+/// Default context window of the LLM model.
+const fn default_num_ctx() -> u32 {
+    16384
+}
+
+/// Default sampling temperature.
+const fn default_temperature() -> f32 {
+    0.2
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self {
+            num_ctx: default_num_ctx(),
+            temperature: default_temperature(),
+            top_p: None,
+            seed: None,
+            num_predict: None,
+            keep_alive: None,
+        }
+    }
+}
+
+impl ModelsConfig {
+    /// Returns the `think` option for a model.
+    ///
+    /// Uses the entry for the full name ("qwen3:8b"), then the one for the
+    /// name without its tag ("qwen3").
+    #[must_use]
+    pub fn think(&self, model: &str) -> Option<bool> {
+        self.think.get(model).copied().or_else(|| {
+            model
+                .split_once(':')
+                .and_then(|(name, _)| self.think.get(name).copied())
+        })
+    }
 }
 
 /// Ollama server configuration.
@@ -42,6 +160,39 @@ pub struct OllamaConfig {
     pub embedding_timeout_secs: u64,
 }
 
+/// OpenAI-compatible server configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenAiConfig {
+    /// Base URL of the API, including the version (e.g., `http://localhost:8080/v1`).
+    #[serde(default = "default_openai_url")]
+    pub url: String,
+    /// API key sent as a bearer token, if the server requires one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Timeout for LLM generation requests in seconds.
+    #[serde(default = "default_generate_timeout")]
+    pub generate_timeout_secs: u64,
+    /// Timeout for embedding requests in seconds.
+    #[serde(default = "default_embedding_timeout")]
+    pub embedding_timeout_secs: u64,
+}
+
+/// Default OpenAI-compatible API URL (`llama-server` on its default port).
+fn default_openai_url() -> String {
+    "http://localhost:8080/v1".to_string()
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            url: default_openai_url(),
+            api_key: None,
+            generate_timeout_secs: default_generate_timeout(),
+            embedding_timeout_secs: default_embedding_timeout(),
+        }
+    }
+}
+
 /// Default timeout for LLM generation (120 seconds).
 const fn default_generate_timeout() -> u64 {
     120
@@ -52,31 +203,342 @@ const fn default_embedding_timeout() -> u64 {
     60
 }
 
-/// Index metadata for validation.
+/// Index settings and metadata for validation.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct IndexConfig {
-    /// Embedding dimension used when building index.
+    /// Embedding dimension of the model last used to update the index.
     pub embedding_dimension: Option<u32>,
-    /// Model name used when building index.
+    /// Embedding model last used to update the index.
+    ///
+    /// Informational only: the index keeps vectors for every model it was
+    /// updated with.
     pub last_embedding_model: Option<String>,
+    /// Manpage sections to index (e.g., "1" scans `man1` for `.1`, `.1p`, ...).
+    #[serde(default = "default_sections")]
+    pub sections: Vec<String>,
+    /// Languages of localized manpages to index in addition to the
+    /// untranslated ones (e.g., "de" scans `/usr/share/man/de/man1`).
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Distance metric of the vector tables.
+    ///
+    /// Changing it re-embeds the index on the next `ulm update`.
+    #[serde(default)]
+    pub distance_metric: DistanceMetric,
+    /// Scale vectors to unit length at insert and query time.
+    #[serde(default = "default_normalize_vectors")]
+    pub normalize_vectors: bool,
+    /// Quantized storage of the vectors searched first.
+    ///
+    /// Quantization needs `normalize_vectors`. Changing it re-embeds the
+    /// index on the next `ulm update`.
+    #[serde(default)]
+    pub quantization: Quantization,
+    /// Maximum number of texts per embedding request while indexing.
+    ///
+    /// Manpages are embedded in batches of up to this many pages; a batch
+    /// that fails is retried one page at a time.
+    #[serde(default = "default_embedding_batch_size")]
+    pub embedding_batch_size: usize,
+}
+
+/// Distance metric used for vector search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Cosine distance (1 - cosine similarity).
+    #[default]
+    Cosine,
+    /// Euclidean distance.
+    L2,
+    /// Manhattan distance.
+    L1,
+}
+
+impl DistanceMetric {
+    /// Returns the name of the metric as used by sqlite-vec.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Cosine => "cosine",
+            Self::L2 => "l2",
+            Self::L1 => "l1",
+        }
+    }
+}
+
+impl std::str::FromStr for DistanceMetric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cosine" => Ok(Self::Cosine),
+            "l2" => Ok(Self::L2),
+            "l1" => Ok(Self::L1),
+            _ => anyhow::bail!("Unknown distance metric: {s}"),
+        }
+    }
+}
+
+/// Quantized vector storage mode.
+///
+/// Quantized vectors are searched first; the closest candidates are then
+/// rescored with full-precision vectors. The `float32` vectors are kept
+/// for rescoring, so quantization trades index size for search speed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantization {
+    /// Full-precision `float32` vectors only.
+    #[default]
+    None,
+    /// One signed byte per dimension (4x smaller than `float32`).
+    Int8,
+    /// One bit per dimension (32x smaller than `float32`).
+    Binary,
+}
+
+impl Quantization {
+    /// Returns the name of the mode as used in the config.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Int8 => "int8",
+            Self::Binary => "binary",
+        }
+    }
+}
+
+impl std::str::FromStr for Quantization {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "int8" => Ok(Self::Int8),
+            "binary" => Ok(Self::Binary),
+            _ => anyhow::bail!("Unknown quantization mode: {s}"),
+        }
+    }
+}
+
+/// How vectors are stored and compared in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectorSettings {
+    /// Distance metric of the vector tables.
+    pub metric: DistanceMetric,
+    /// Whether vectors are scaled to unit length.
+    pub normalize: bool,
+    /// Quantized storage mode.
+    pub quantization: Quantization,
+}
+
+impl Default for VectorSettings {
+    fn default() -> Self {
+        Self {
+            metric: DistanceMetric::default(),
+            normalize: default_normalize_vectors(),
+            quantization: Quantization::None,
+        }
+    }
+}
+
+impl VectorSettings {
+    /// Converts a distance under these settings into a similarity in `0..=1`.
+    ///
+    /// Cosine distance is mapped linearly. L2 distance between unit vectors
+    /// is converted back to cosine similarity; other distances are mapped
+    /// with `1 / (1 + d)`. Higher is more similar.
+    #[must_use]
+    pub fn similarity(self, distance: f32) -> f32 {
+        let similarity = match self.metric {
+            DistanceMetric::Cosine => 1.0 - distance / 2.0,
+            DistanceMetric::L2 if self.normalize => 1.0 - distance * distance / 4.0,
+            DistanceMetric::L2 | DistanceMetric::L1 => 1.0 / (1.0 + distance),
+        };
+        similarity.clamp(0.0, 1.0)
+    }
+}
+
+/// Default for vector normalization (enabled).
+const fn default_normalize_vectors() -> bool {
+    true
+}
+
+/// Default number of texts per embedding request.
+const fn default_embedding_batch_size() -> usize {
+    32
+}
+
+/// Default manpage sections: commands, file formats, overviews, administration.
+fn default_sections() -> Vec<String> {
+    ["1", "5", "7", "8"]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            embedding_dimension: None,
+            last_embedding_model: None,
+            sections: default_sections(),
+            languages: Vec::new(),
+            distance_metric: DistanceMetric::default(),
+            normalize_vectors: default_normalize_vectors(),
+            quantization: Quantization::None,
+            embedding_batch_size: default_embedding_batch_size(),
+        }
+    }
+}
+
+impl IndexConfig {
+    /// Returns the vector settings for new vector tables.
+    #[must_use]
+    pub const fn vector_settings(&self) -> VectorSettings {
+        VectorSettings {
+            metric: self.distance_metric,
+            normalize: self.normalize_vectors,
+            quantization: self.quantization,
+        }
+    }
+}
+
+/// Response generation configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenerationConfig {
+    /// Follow-up requests that ask the LLM to correct a response that
+    /// cannot be parsed (0 disables them).
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+/// Default number of corrective retries.
+const fn default_max_retries() -> u32 {
+    2
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+/// Search ranking configuration for hybrid lexical + semantic search.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchConfig {
+    /// Weight of the semantic (vector) ranking in reciprocal rank fusion.
+    #[serde(default = "default_semantic_weight")]
+    pub semantic_weight: f32,
+    /// Weight of the lexical (BM25) ranking in reciprocal rank fusion.
+    #[serde(default = "default_lexical_weight")]
+    pub lexical_weight: f32,
+    /// Rank constant `k` for reciprocal rank fusion (higher flattens ranks).
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: u32,
+    /// Only return results from these sections (empty means all).
+    ///
+    /// A section also matches its suffixed variants ("1" matches "1p").
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Score multipliers per section (e.g., `"1" = 1.5`).
+    #[serde(default)]
+    pub section_boost: HashMap<String, f32>,
+    /// Preferred language of manpages (e.g., "de"); defaults to the
+    /// language of `LANG`.
+    ///
+    /// When a page is indexed in several languages, only the preferred
+    /// one (or the untranslated one) is returned.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Default weight of the semantic ranking.
+const fn default_semantic_weight() -> f32 {
+    1.0
+}
+
+/// Default weight of the lexical ranking.
+const fn default_lexical_weight() -> f32 {
+    1.0
+}
+
+/// Default reciprocal rank fusion constant.
+const fn default_rrf_k() -> u32 {
+    60
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            semantic_weight: default_semantic_weight(),
+            lexical_weight: default_lexical_weight(),
+            rrf_k: default_rrf_k(),
+            sections: Vec::new(),
+            section_boost: HashMap::new(),
+            language: None,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Checks if results from a section pass the section filter.
+    #[must_use]
+    pub fn includes_section(&self, section: &str) -> bool {
+        self.sections.is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|s| section.starts_with(s.as_str()))
+    }
+
+    /// Returns the score multiplier for a section.
+    ///
+    /// Uses the most specific configured boost ("1p" before "1"), or 1.0.
+    #[must_use]
+    pub fn section_boost(&self, section: &str) -> f32 {
+        self.section_boost
+            .iter()
+            .filter(|(s, _)| section.starts_with(s.as_str()))
+            .max_by_key(|(s, _)| s.len())
+            .map_or(1.0, |(_, boost)| *boost)
+    }
+
+    /// Returns the preferred manpage language.
+    ///
+    /// Uses the configured language, then the user's locale, then the
+    /// language of untranslated pages.
+    #[must_use]
+    pub fn preferred_language(&self) -> String {
+        self.language
+            .clone()
+            .or_else(locale::user_language)
+            .unwrap_or_else(|| locale::DEFAULT_LANGUAGE.to_string())
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            backend: Backend::default(),
             models: ModelsConfig {
                 embedding_model: "nomic-embed-text".to_string(),
                 llm_model: "llama3.2:3b".to_string(),
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: "http://localhost:11434".to_string(),
                 generate_timeout_secs: default_generate_timeout(),
                 embedding_timeout_secs: default_embedding_timeout(),
             },
-            index: IndexConfig {
-                embedding_dimension: None,
-                last_embedding_model: None,
-            },
+            index: IndexConfig::default(),
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         }
     }
 }
@@ -100,16 +562,31 @@ impl Config {
         &self.ollama.url
     }
 
-    /// Get the generate timeout in seconds.
+    /// Get the base URL of the configured backend.
+    #[must_use]
+    pub fn backend_url(&self) -> &str {
+        match self.backend {
+            Backend::Ollama => &self.ollama.url,
+            Backend::OpenAi => &self.openai.url,
+        }
+    }
+
+    /// Get the generate timeout of the configured backend in seconds.
     #[must_use]
     pub const fn generate_timeout_secs(&self) -> u64 {
-        self.ollama.generate_timeout_secs
+        match self.backend {
+            Backend::Ollama => self.ollama.generate_timeout_secs,
+            Backend::OpenAi => self.openai.generate_timeout_secs,
+        }
     }
 
-    /// Get the embedding timeout in seconds.
+    /// Get the embedding timeout of the configured backend in seconds.
     #[must_use]
     pub const fn embedding_timeout_secs(&self) -> u64 {
-        self.ollama.embedding_timeout_secs
+        match self.backend {
+            Backend::Ollama => self.ollama.embedding_timeout_secs,
+            Backend::OpenAi => self.openai.embedding_timeout_secs,
+        }
     }
 
     /// Update index metadata after building index.
@@ -118,20 +595,55 @@ impl Config {
         self.index.last_embedding_model = Some(self.models.embedding_model.clone());
     }
 
-    /// Check if index needs rebuild due to model change.
-    #[must_use]
-    pub fn needs_index_rebuild(&self) -> bool {
-        match &self.index.last_embedding_model {
-            Some(last_model) => last_model != &self.models.embedding_model,
-            None => false, // No previous index, not a "rebuild"
-        }
-    }
-
     /// Get index dimension if available.
     #[must_use]
     pub const fn index_dimension(&self) -> Option<u32> {
         self.index.embedding_dimension
     }
+
+    /// Get the vector dimension of the embedding model if it's known.
+    ///
+    /// It's known from the last index update with the same model, or for the
+    /// recommended embedding models.
+    #[must_use]
+    pub fn embedding_dimension(&self) -> Option<u32> {
+        if self.index.last_embedding_model.as_deref() == Some(self.embedding_model()) {
+            if let Some(dimension) = self.index.embedding_dimension {
+                return Some(dimension);
+            }
+        }
+        models::recommended_dimension(self.embedding_model())
+    }
+
+    /// Checks that the index settings can be used with the embedding model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if quantization is enabled without
+    /// `normalize_vectors` (quantized vectors only hold components in
+    /// -1..1), or if binary quantization is used with a model whose known
+    /// dimension isn't divisible by 8.
+    pub fn validate(&self) -> Result<()> {
+        let quantization = self.index.quantization;
+        if quantization != Quantization::None && !self.index.normalize_vectors {
+            anyhow::bail!(
+                "index.quantization = \"{}\" needs index.normalize_vectors = true",
+                quantization.as_str()
+            );
+        }
+
+        if quantization == Quantization::Binary {
+            if let Some(dimension) = self.embedding_dimension().filter(|d| d % 8 != 0) {
+                anyhow::bail!(
+                    "index.quantization = \"binary\" needs a vector dimension divisible by 8 \
+                     ('{}' has {dimension})",
+                    self.embedding_model()
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Legacy configuration format for migration.
@@ -173,7 +685,8 @@ pub fn get_config_path() -> Result<PathBuf> {
 ///
 /// # Errors
 ///
-/// Returns an error if the file exists but cannot be read or parsed.
+/// Returns an error if the file exists but cannot be read or parsed, or if
+/// it fails [`Config::validate`].
 pub fn load_config() -> Result<Config> {
     let config_path = get_config_path()?;
 
@@ -186,6 +699,9 @@ pub fn load_config() -> Result<Config> {
 
     // Try to parse as new format first
     if let Ok(config) = toml::from_str::<Config>(&content) {
+        config
+            .validate()
+            .with_context(|| format!("Invalid config file: {}", config_path.display()))?;
         return Ok(config);
     }
 
@@ -194,20 +710,23 @@ pub fn load_config() -> Result<Config> {
         tracing::info!("Migrating legacy config to new multi-model format");
 
         let config = Config {
+            backend: Backend::default(),
             models: ModelsConfig {
                 // Use legacy model for both (user can change later)
                 embedding_model: legacy.model_name.clone(),
                 llm_model: legacy.model_name,
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: legacy.ollama_url,
                 generate_timeout_secs: default_generate_timeout(),
                 embedding_timeout_secs: default_embedding_timeout(),
             },
-            index: IndexConfig {
-                embedding_dimension: None,
-                last_embedding_model: None,
-            },
+            index: IndexConfig::default(),
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         };
 
         // Save migrated config
@@ -268,9 +787,12 @@ mod tests {
     #[test]
     fn test_config_serialization() {
         let config = Config {
+            backend: Backend::default(),
             models: ModelsConfig {
                 embedding_model: "nomic-embed-text".to_string(),
                 llm_model: "mistral:7b".to_string(),
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: "http://localhost:11434".to_string(),
@@ -280,7 +802,11 @@ mod tests {
             index: IndexConfig {
                 embedding_dimension: Some(768),
                 last_embedding_model: Some("nomic-embed-text".to_string()),
+                ..IndexConfig::default()
             },
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         };
 
         let toml_str = toml::to_string(&config).unwrap();
@@ -333,6 +859,273 @@ mod tests {
         let config: Config = toml::from_str(toml_str).unwrap();
         assert_eq!(config.ollama.generate_timeout_secs, 120);
         assert_eq!(config.ollama.embedding_timeout_secs, 60);
+        assert_eq!(config.index.sections, vec!["1", "5", "7", "8"]);
+        assert_eq!(config.search, SearchConfig::default());
+        assert_eq!(config.generation.max_retries, 2);
+        assert_eq!(config.models.options, ModelOptions::default());
+    }
+
+    #[test]
+    fn test_think_config() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "qwen3:8b"
+
+            [models.think]
+            "qwen3" = false
+            "deepseek-r1:7b" = true
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.models.think("qwen3:8b"), Some(false));
+        assert_eq!(config.models.think("deepseek-r1:7b"), Some(true));
+        assert_eq!(config.models.think("deepseek-r1:14b"), None);
+        assert_eq!(config.models.think("llama3"), None);
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("think"));
+    }
+
+    #[test]
+    fn test_model_options_config() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [models.options]
+            temperature = 0.0
+            seed = 42
+            keep_alive = "30m"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let options = &config.models.options;
+        assert_eq!(options.num_ctx, 16384);
+        assert!(options.temperature.abs() < f32::EPSILON);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.keep_alive.as_deref(), Some("30m"));
+        assert!(options.top_p.is_none());
+        assert!(options.num_predict.is_none());
+    }
+
+    #[test]
+    fn test_generation_config_deserialization() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+
+            [generation]
+            max_retries = 0
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.generation.max_retries, 0);
+    }
+
+    #[test]
+    fn test_search_config_deserialization() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+
+            [search]
+            lexical_weight = 2.0
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!((config.search.lexical_weight - 2.0).abs() < f32::EPSILON);
+        assert!((config.search.semantic_weight - 1.0).abs() < f32::EPSILON);
+        assert_eq!(config.search.rrf_k, 60);
+    }
+
+    #[test]
+    fn test_section_config_deserialization() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+            sections = ["1", "5"]
+
+            [search]
+            sections = ["1"]
+
+            [search.section_boost]
+            "1" = 1.5
+            "1p" = 0.5
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.index.sections, vec!["1", "5"]);
+        assert!(config.search.includes_section("1ssl"));
+        assert!(!config.search.includes_section("5"));
+        assert!((config.search.section_boost("1") - 1.5).abs() < f32::EPSILON);
+        assert!((config.search.section_boost("1p") - 0.5).abs() < f32::EPSILON);
+        assert!((config.search.section_boost("8") - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_language_config() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+            languages = ["de"]
+
+            [search]
+            language = "de"
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.index.languages, vec!["de"]);
+        assert_eq!(config.search.preferred_language(), "de");
+        assert!(Config::default().index.languages.is_empty());
+    }
+
+    #[test]
+    fn test_backend_config() {
+        let toml_str = r#"
+            backend = "openai"
+
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "qwen2.5-7b-instruct"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [openai]
+            url = "http://localhost:8000/v1"
+            api_key = "secret"
+            generate_timeout_secs = 300
+
+            [index]
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.backend, Backend::OpenAi);
+        assert_eq!(config.openai.url, "http://localhost:8000/v1");
+        assert_eq!(config.openai.api_key.as_deref(), Some("secret"));
+        assert_eq!(config.generate_timeout_secs(), 300);
+        assert_eq!(config.embedding_timeout_secs(), 60);
+
+        let default = Config::default();
+        assert_eq!(default.backend, Backend::Ollama);
+        assert_eq!(default.openai.url, "http://localhost:8080/v1");
+
+        // Written configs round-trip with the backend key
+        let reparsed: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(reparsed, config);
+    }
+
+    #[test]
+    fn test_vector_settings_deserialization() {
+        let toml_str = r#"
+            [models]
+            embedding_model = "nomic-embed-text"
+            llm_model = "llama3"
+
+            [ollama]
+            url = "http://localhost:11434"
+
+            [index]
+            distance_metric = "l2"
+            normalize_vectors = false
+            quantization = "binary"
+            embedding_batch_size = 8
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.index.vector_settings(),
+            VectorSettings {
+                metric: DistanceMetric::L2,
+                normalize: false,
+                quantization: Quantization::Binary,
+            }
+        );
+        assert_eq!(
+            Config::default().index.vector_settings(),
+            VectorSettings::default()
+        );
+        assert_eq!(config.index.embedding_batch_size, 8);
+        assert_eq!(Config::default().index.embedding_batch_size, 32);
+    }
+
+    #[test]
+    fn test_distance_metric_names() {
+        for metric in [
+            DistanceMetric::Cosine,
+            DistanceMetric::L2,
+            DistanceMetric::L1,
+        ] {
+            assert_eq!(metric.as_str().parse::<DistanceMetric>().unwrap(), metric);
+        }
+        assert!("dot".parse::<DistanceMetric>().is_err());
+    }
+
+    #[test]
+    fn test_quantization_names() {
+        for mode in [Quantization::None, Quantization::Int8, Quantization::Binary] {
+            assert_eq!(mode.as_str().parse::<Quantization>().unwrap(), mode);
+        }
+        assert!("float16".parse::<Quantization>().is_err());
+    }
+
+    #[test]
+    fn test_similarity_is_bounded() {
+        let cosine = VectorSettings::default();
+        assert!((cosine.similarity(0.0) - 1.0).abs() < f32::EPSILON);
+        assert!((cosine.similarity(1.0) - 0.5).abs() < f32::EPSILON);
+        assert!(cosine.similarity(2.0).abs() < f32::EPSILON);
+
+        // Orthogonal unit vectors are sqrt(2) apart
+        let l2 = VectorSettings {
+            metric: DistanceMetric::L2,
+            ..VectorSettings::default()
+        };
+        assert!((l2.similarity(2.0_f32.sqrt()) - 0.5).abs() < 1e-6);
+
+        let l1 = VectorSettings {
+            metric: DistanceMetric::L1,
+            normalize: false,
+            ..VectorSettings::default()
+        };
+        assert!((l1.similarity(1.0) - 0.5).abs() < f32::EPSILON);
+        assert!(l1.similarity(1000.0) > 0.0);
     }
 
     #[test]
@@ -347,19 +1140,22 @@ mod tests {
 
         // Migrate
         let config = Config {
+            backend: Backend::default(),
             models: ModelsConfig {
                 embedding_model: legacy.model_name.clone(),
                 llm_model: legacy.model_name,
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: legacy.ollama_url,
                 generate_timeout_secs: default_generate_timeout(),
                 embedding_timeout_secs: default_embedding_timeout(),
             },
-            index: IndexConfig {
-                embedding_dimension: None,
-                last_embedding_model: None,
-            },
+            index: IndexConfig::default(),
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         };
 
         assert_eq!(config.models.embedding_model, "llama3.1:8b");
@@ -369,9 +1165,12 @@ mod tests {
     #[test]
     fn test_config_roundtrip() {
         let original = Config {
+            backend: Backend::default(),
             models: ModelsConfig {
                 embedding_model: "nomic-embed-text".to_string(),
                 llm_model: "phi3:mini".to_string(),
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: "http://127.0.0.1:11434".to_string(),
@@ -381,7 +1180,11 @@ mod tests {
             index: IndexConfig {
                 embedding_dimension: Some(768),
                 last_embedding_model: Some("nomic-embed-text".to_string()),
+                ..IndexConfig::default()
             },
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         };
 
         let toml_str = toml::to_string(&original).unwrap();
@@ -405,9 +1208,12 @@ mod tests {
         let config_path = temp_dir.path().join("config.toml");
 
         let config = Config {
+            backend: Backend::default(),
             models: ModelsConfig {
                 embedding_model: "test-embed".to_string(),
                 llm_model: "test-llm".to_string(),
+                think: HashMap::new(),
+                options: ModelOptions::default(),
             },
             ollama: OllamaConfig {
                 url: "http://test:11434".to_string(),
@@ -417,7 +1223,11 @@ mod tests {
             index: IndexConfig {
                 embedding_dimension: Some(512),
                 last_embedding_model: Some("test-embed".to_string()),
+                ..IndexConfig::default()
             },
+            openai: OpenAiConfig::default(),
+            search: SearchConfig::default(),
+            generation: GenerationConfig::default(),
         };
 
         // Serialize and write
@@ -432,19 +1242,38 @@ mod tests {
     }
 
     #[test]
-    fn test_needs_index_rebuild() {
+    fn test_validate_quantization_settings() {
         let mut config = Config::default();
+        config.index.quantization = Quantization::Int8;
+        assert!(config.validate().is_ok());
+
+        config.index.normalize_vectors = false;
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("needs index.normalize_vectors = true"));
+
+        config.index.normalize_vectors = true;
+        config.index.quantization = Quantization::Binary;
+        assert!(config.validate().is_ok());
+
+        // The dimension of the last update is used for the same model only
+        config.index.last_embedding_model = Some("custom-embed".to_string());
+        config.index.embedding_dimension = Some(100);
+        assert!(config.validate().is_ok());
+        config.models.embedding_model = "custom-embed".to_string();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("divisible by 8 ('custom-embed' has 100)"));
+    }
 
-        // No previous index
-        assert!(!config.needs_index_rebuild());
+    #[test]
+    fn test_embedding_dimension() {
+        let mut config = Config::default();
+        assert_eq!(config.embedding_dimension(), Some(768));
 
-        // Set index metadata
-        config.update_index_metadata(768);
-        assert!(!config.needs_index_rebuild());
+        config.models.embedding_model = "unknown-embed".to_string();
+        assert_eq!(config.embedding_dimension(), None);
 
-        // Change embedding model
-        config.models.embedding_model = "different-model".to_string();
-        assert!(config.needs_index_rebuild());
+        config.update_index_metadata(512);
+        assert_eq!(config.embedding_dimension(), Some(512));
     }
 
     #[test]
@@ -3,24 +3,26 @@
 //! This module scans system directories to find all available manpages
 //! and prepares them for embedding generation.
 
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use futures::stream::{self, StreamExt};
+use futures::stream::{self, Stream, StreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, info, warn};
 
-use crate::llm::OllamaClient;
-use crate::setup::config::load_config;
+use crate::llm::{embedder, Embedder};
+use crate::setup::chunk::{split_chunks, ChunkEntry, ManpageChunk};
+use crate::setup::config::{load_config, IndexConfig};
+use crate::setup::locale::{self, DEFAULT_LANGUAGE};
 
 /// Extracted content from a manpage.
 #[derive(Debug, Clone)]
@@ -29,8 +31,39 @@ pub struct ManpageContent {
     pub tool_name: String,
     /// Section number (e.g., "1").
     pub section: String,
+    /// Language of the page (e.g., "de"; "en" for untranslated pages).
+    pub language: String,
     /// Combined NAME and DESCRIPTION text for embedding.
     pub description: String,
+    /// Full manpage text with escape sequences removed.
+    pub content: String,
+    /// Option- and section-level chunks of the manpage text.
+    pub chunks: Vec<ManpageChunk>,
+    /// Path of the manpage file this content was extracted from.
+    pub source_path: PathBuf,
+}
+
+/// Alternative name of an indexed manpage.
+///
+/// Aliases are symlinked pages (`vi.1` → `vim.1`) and `.so` roff redirects.
+/// They are not indexed themselves but stored with their target page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManpageAlias {
+    /// Alias name (e.g., "vi").
+    pub name: String,
+    /// Section of the alias page (e.g., "1").
+    pub section: String,
+    /// Path of the indexed page the alias resolves to.
+    pub target: PathBuf,
+}
+
+/// Manpages found by a scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanResult {
+    /// Manpage files to index, one per distinct page.
+    pub pages: Vec<PathBuf>,
+    /// Alias pages resolving to one of `pages`.
+    pub aliases: Vec<ManpageAlias>,
 }
 
 /// Manpage entry with embedding vector for storage.
@@ -40,67 +73,113 @@ pub struct ManpageEntry {
     pub tool_name: String,
     /// Section number (e.g., "1").
     pub section: String,
+    /// Language of the page (e.g., "de"; "en" for untranslated pages).
+    pub language: String,
     /// Combined NAME and DESCRIPTION text.
     pub description: String,
+    /// Full manpage text with escape sequences removed.
+    pub content: String,
+    /// Path of the manpage file this entry was built from.
+    pub source_path: PathBuf,
     /// Embedding vector.
     pub vector: Vec<f32>,
+    /// Embedded chunks of the manpage text.
+    pub chunks: Vec<ChunkEntry>,
 }
 
+impl ManpageEntry {
+    /// Creates an entry from extracted content and its embedding vectors.
+    ///
+    /// `chunk_vectors` holds one vector per chunk of `content`, in order.
+    #[must_use]
+    pub fn from_content(
+        content: &ManpageContent,
+        vector: Vec<f32>,
+        chunk_vectors: Vec<Vec<f32>>,
+    ) -> Self {
+        Self {
+            tool_name: content.tool_name.clone(),
+            section: content.section.clone(),
+            language: content.language.clone(),
+            description: content.description.clone(),
+            content: content.content.clone(),
+            source_path: content.source_path.clone(),
+            vector,
+            chunks: content
+                .chunks
+                .iter()
+                .zip(chunk_vectors)
+                .map(|(chunk, vector)| ChunkEntry::from_chunk(chunk, vector))
+                .collect(),
+        }
+    }
+}
+
+/// Maximum number of embedding batches requested concurrently.
+const BATCH_CONCURRENCY: usize = 4;
+
 /// Generator for creating embeddings from manpage content.
 #[derive(Debug)]
 pub struct EmbeddingGenerator {
-    /// Ollama client for API calls.
-    client: OllamaClient,
+    /// Embedder of the configured backend.
+    embedder: Arc<dyn Embedder>,
     /// Model to use for embeddings.
     model: String,
+    /// Maximum number of pages per batch and texts per embedding request.
+    batch_size: usize,
 }
 
 impl EmbeddingGenerator {
-    /// Creates a new embedding generator with model from config.
+    /// Creates a new embedding generator with backend, model and batch size from config.
     ///
     /// # Errors
     ///
     /// Returns an error if the client cannot be created or config cannot be loaded.
     pub fn new() -> Result<Self> {
         let config = load_config().context("Failed to load config")?;
-        let client = OllamaClient::with_config(
-            config.ollama_url(),
-            config.generate_timeout_secs(),
-            config.embedding_timeout_secs(),
-        )?;
-        Ok(Self {
-            client,
-            model: config.models.embedding_model.clone(),
-        })
+        Ok(
+            Self::with_embedder(embedder(&config)?, &config.models.embedding_model)
+                .with_batch_size(config.index.embedding_batch_size),
+        )
     }
 
-    /// Creates a new embedding generator with custom client and model.
+    /// Creates a new embedding generator with custom embedder and model.
     #[must_use]
-    pub fn with_client(client: OllamaClient, model: &str) -> Self {
+    pub fn with_embedder(embedder: Arc<dyn Embedder>, model: &str) -> Self {
         Self {
-            client,
+            embedder,
             model: model.to_string(),
+            batch_size: IndexConfig::default().embedding_batch_size,
         }
     }
 
+    /// Sets the maximum number of pages per batch and texts per request.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
     /// Generates embeddings for a list of manpage contents.
     ///
-    /// Processes concurrently with progress display and retry logic.
+    /// Embeds the pages in batches, several batches concurrently, with
+    /// progress display and retry logic.
     ///
     /// # Errors
     ///
-    /// Returns an error if embedding generation fails for any item after retries.
+    /// Returns an error if the progress bar cannot be set up. Pages that
+    /// still fail after retries are skipped.
     pub async fn generate_embeddings(
         &self,
         contents: Vec<ManpageContent>,
     ) -> Result<Vec<ManpageEntry>> {
         let total = contents.len();
-        let concurrency = 10; // Process 10 embeddings concurrently
 
         info!(
             total = total,
-            concurrency = concurrency,
-            "Starting parallel embedding generation"
+            batch_size = self.batch_size,
+            concurrency = BATCH_CONCURRENCY,
+            "Starting batched embedding generation"
         );
 
         // Setup progress bar
@@ -111,92 +190,152 @@ impl EmbeddingGenerator {
             .progress_chars("#>-");
         pb.set_style(style);
 
-        let completed = Arc::new(AtomicUsize::new(0));
-        let failed_items = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+        let batches = stream::iter(contents).chunks(self.batch_size);
+        let (mut entries, failed) = self.embed_batches(batches, &pb).await;
 
-        // Process embeddings concurrently
-        let results: Vec<Option<ManpageEntry>> = stream::iter(contents.into_iter().enumerate())
-            .map(|(idx, content)| {
-                let client = self.client.clone();
-                let model = self.model.clone();
-                let pb = pb.clone();
-                let completed = Arc::clone(&completed);
-                let failed_items = Arc::clone(&failed_items);
+        pb.finish_with_message("Initial pass complete");
 
-                async move {
-                    let result = Self::generate_single(&client, &model, &content).await;
+        entries.extend(self.retry_failed(failed).await);
 
-                    let count = completed.fetch_add(1, Ordering::SeqCst) + 1;
-                    pb.set_position(count as u64);
+        info!(count = entries.len(), "Embedding generation complete");
+        Ok(entries)
+    }
 
-                    match result {
-                        Ok(entry) => Some(entry),
-                        Err(e) => {
-                            warn!(idx = idx, error = %e, "Failed to generate embedding, will retry");
-                            failed_items.lock().await.push((idx, content));
-                            None
-                        }
-                    }
-                }
+    /// Embeds batches of pages concurrently, advancing `pb` per page.
+    ///
+    /// Returns the embedded pages and the pages that failed.
+    async fn embed_batches(
+        &self,
+        batches: impl Stream<Item = Vec<ManpageContent>>,
+        pb: &ProgressBar,
+    ) -> (Vec<ManpageEntry>, Vec<ManpageContent>) {
+        let results: Vec<(Vec<ManpageEntry>, Vec<ManpageContent>)> = batches
+            .map(|batch| async move {
+                let pages = batch.len() as u64;
+                let result = self.embed_batch_or_single(batch).await;
+                pb.inc(pages);
+                result
             })
-            .buffer_unordered(concurrency)
+            .buffer_unordered(BATCH_CONCURRENCY)
             .collect()
             .await;
 
-        pb.finish_with_message("Initial pass complete");
-
-        // Collect successful results
-        let mut entries: Vec<ManpageEntry> = results.into_iter().flatten().collect();
+        let mut entries = Vec::new();
+        let mut failed = Vec::new();
+        for (batch_entries, batch_failed) in results {
+            entries.extend(batch_entries);
+            failed.extend(batch_failed);
+        }
+        (entries, failed)
+    }
 
-        // Retry failed items sequentially
-        let failed = failed_items.lock().await;
-        if !failed.is_empty() {
-            info!(
-                count = failed.len(),
-                "Retrying failed embeddings sequentially"
-            );
-            println!("\nRetrying {} failed embeddings...", failed.len());
-
-            for (idx, content) in failed.iter() {
-                match self.generate_with_retry(&content.description).await {
-                    Ok(vector) => {
-                        entries.push(ManpageEntry {
-                            tool_name: content.tool_name.clone(),
-                            section: content.section.clone(),
-                            description: content.description.clone(),
-                            vector,
-                        });
-                    }
-                    Err(e) => {
-                        warn!(idx = idx, tool = %content.tool_name, error = %e, "Final retry failed");
-                        // Continue with other items instead of failing completely
+    /// Embeds a batch of pages, falling back to one page at a time.
+    ///
+    /// Returns the embedded pages and the pages that failed.
+    async fn embed_batch_or_single(
+        &self,
+        batch: Vec<ManpageContent>,
+    ) -> (Vec<ManpageEntry>, Vec<ManpageContent>) {
+        match self.embed_batch(&batch).await {
+            Ok(entries) => (entries, Vec::new()),
+            Err(e) => {
+                warn!(
+                    pages = batch.len(),
+                    error = %e,
+                    "Batch embedding failed, embedding pages one by one"
+                );
+
+                let mut entries = Vec::new();
+                let mut failed = Vec::new();
+                for content in batch {
+                    match Self::generate_single(self.embedder.as_ref(), &self.model, &content).await
+                    {
+                        Ok(entry) => entries.push(entry),
+                        Err(e) => {
+                            warn!(tool = %content.tool_name, error = %e, "Failed to embed, will retry");
+                            failed.push(content);
+                        }
                     }
                 }
+                (entries, failed)
             }
         }
+    }
+
+    /// Embeds the descriptions and chunks of a batch of pages.
+    ///
+    /// Texts are sent in requests of up to `batch_size` texts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any request fails.
+    async fn embed_batch(&self, batch: &[ManpageContent]) -> Result<Vec<ManpageEntry>> {
+        let texts: Vec<String> = batch.iter().flat_map(embedding_texts).collect();
+
+        let mut vectors = Vec::with_capacity(texts.len());
+        for request in texts.chunks(self.batch_size) {
+            let batch_vectors = self.embedder.embed_batch(&self.model, request).await?;
+            anyhow::ensure!(
+                batch_vectors.len() == request.len(),
+                "Embedding batch returned {} vectors for {} texts",
+                batch_vectors.len(),
+                request.len()
+            );
+            vectors.extend(batch_vectors);
+        }
+
+        // Vectors are in text order: each description, then its chunks
+        let mut vectors = vectors.into_iter();
+        let mut entries = Vec::with_capacity(batch.len());
+        for content in batch {
+            let vector = vectors.next().context("Missing description vector")?;
+            let chunk_vectors: Vec<Vec<f32>> =
+                vectors.by_ref().take(content.chunks.len()).collect();
+            entries.push(ManpageEntry::from_content(content, vector, chunk_vectors));
+        }
 
-        info!(count = entries.len(), "Embedding generation complete");
         Ok(entries)
     }
 
-    /// Generates a single embedding with basic retry.
+    /// Retries failed pages sequentially with backoff.
+    ///
+    /// Pages that still fail are logged and skipped.
+    async fn retry_failed(&self, failed: Vec<ManpageContent>) -> Vec<ManpageEntry> {
+        let mut entries = Vec::new();
+        if failed.is_empty() {
+            return entries;
+        }
+
+        info!(
+            count = failed.len(),
+            "Retrying failed embeddings sequentially"
+        );
+        println!("\nRetrying {} failed embeddings...", failed.len());
+
+        for content in &failed {
+            match self.embed_with_retry(content).await {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    warn!(tool = %content.tool_name, error = %e, "Final retry failed");
+                    // Continue with other items instead of failing completely
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Generates the embeddings of a manpage and its chunks with basic retry.
     async fn generate_single(
-        client: &OllamaClient,
+        embedder: &dyn Embedder,
         model: &str,
         content: &ManpageContent,
     ) -> Result<ManpageEntry> {
         let max_attempts = 2;
 
         for attempt in 1..=max_attempts {
-            match client.generate_embedding(model, &content.description).await {
-                Ok(vector) => {
-                    return Ok(ManpageEntry {
-                        tool_name: content.tool_name.clone(),
-                        section: content.section.clone(),
-                        description: content.description.clone(),
-                        vector,
-                    });
-                }
+            match Self::embed_content(embedder, model, content).await {
+                Ok(entry) => return Ok(entry),
                 Err(e) if attempt < max_attempts => {
                     sleep(Duration::from_millis(500)).await;
                     debug!(attempt = attempt, error = %e, "Quick retry");
@@ -208,12 +347,46 @@ impl EmbeddingGenerator {
         unreachable!()
     }
 
+    /// Embeds the description and every chunk of a manpage in one request.
+    async fn embed_content(
+        embedder: &dyn Embedder,
+        model: &str,
+        content: &ManpageContent,
+    ) -> Result<ManpageEntry> {
+        let texts = embedding_texts(content);
+        let mut vectors = embedder.embed_batch(model, &texts).await?.into_iter();
+
+        let vector = vectors.next().context("Missing description vector")?;
+        let chunk_vectors: Vec<Vec<f32>> = vectors.collect();
+        anyhow::ensure!(
+            chunk_vectors.len() == content.chunks.len(),
+            "Embedding returned {} chunk vectors for {} chunks",
+            chunk_vectors.len(),
+            content.chunks.len()
+        );
+
+        Ok(ManpageEntry::from_content(content, vector, chunk_vectors))
+    }
+
+    /// Embeds the description and every chunk of a manpage with retry logic.
+    async fn embed_with_retry(&self, content: &ManpageContent) -> Result<ManpageEntry> {
+        let vector = self.generate_with_retry(&content.description).await?;
+
+        let mut chunk_vectors = Vec::with_capacity(content.chunks.len());
+        for chunk in &content.chunks {
+            let text = chunk.embedding_text(&content.tool_name);
+            chunk_vectors.push(self.generate_with_retry(&text).await?);
+        }
+
+        Ok(ManpageEntry::from_content(content, vector, chunk_vectors))
+    }
+
     /// Generates embedding with retry logic.
     async fn generate_with_retry(&self, text: &str) -> Result<Vec<f32>> {
         let max_attempts = 3;
 
         for attempt in 1..=max_attempts {
-            match self.client.generate_embedding(&self.model, text).await {
+            match self.embedder.embed(&self.model, text).await {
                 Ok(vector) => return Ok(vector),
                 Err(e) if attempt < max_attempts => {
                     let delay = Duration::from_secs(2_u64.pow(attempt));
@@ -237,22 +410,23 @@ impl EmbeddingGenerator {
     /// Generates embeddings with pipelined extraction.
     ///
     /// Extracts manpages and generates embeddings concurrently using channels.
+    /// Extracted pages are grouped into batches of up to `batch_size` pages
+    /// as they become available.
     ///
     /// # Errors
     ///
     /// Returns an error if embedding generation fails.
-    #[allow(clippy::too_many_lines)]
     pub async fn generate_embeddings_pipelined(
         &self,
         paths: Vec<PathBuf>,
     ) -> Result<Vec<ManpageEntry>> {
         let total = paths.len();
-        let concurrency = 10;
         let channel_size = 100;
 
         info!(
             total = total,
-            concurrency = concurrency,
+            batch_size = self.batch_size,
+            concurrency = BATCH_CONCURRENCY,
             "Starting pipelined extraction and embedding"
         );
 
@@ -299,37 +473,9 @@ impl EmbeddingGenerator {
             (extracted, errors)
         });
 
-        // Process embeddings as they come from extraction (true pipelining)
-        let client = self.client.clone();
-        let model = self.model.clone();
-        let failed_items = Arc::new(tokio::sync::Mutex::new(Vec::new()));
-
-        // Convert receiver to stream and process concurrently
-        let rx_stream = ReceiverStream::new(rx);
-        let results: Vec<Option<ManpageEntry>> = rx_stream
-            .map(|content| {
-                let client = client.clone();
-                let model = model.clone();
-                let embed_pb = embed_pb.clone();
-                let failed_items = Arc::clone(&failed_items);
-
-                async move {
-                    let result = Self::generate_single(&client, &model, &content).await;
-                    embed_pb.inc(1);
-
-                    match result {
-                        Ok(entry) => Some(entry),
-                        Err(e) => {
-                            warn!(tool = %content.tool_name, error = %e, "Failed to embed");
-                            failed_items.lock().await.push(content);
-                            None
-                        }
-                    }
-                }
-            })
-            .buffer_unordered(concurrency)
-            .collect()
-            .await;
+        // Embed batches as they come from extraction (true pipelining)
+        let batches = ReceiverStream::new(rx).ready_chunks(self.batch_size);
+        let (mut entries, failed) = self.embed_batches(batches, &embed_pb).await;
 
         embed_pb.finish();
 
@@ -345,32 +491,25 @@ impl EmbeddingGenerator {
             "Extraction complete"
         );
 
-        // Collect results
-        let mut entries: Vec<ManpageEntry> = results.into_iter().flatten().collect();
-
-        // Retry failed items
-        let failed = failed_items.lock().await;
-        if !failed.is_empty() {
-            info!(count = failed.len(), "Retrying failed embeddings");
-            println!("\nRetrying {} failed embeddings...", failed.len());
-
-            for content in failed.iter() {
-                if let Ok(vector) = self.generate_with_retry(&content.description).await {
-                    entries.push(ManpageEntry {
-                        tool_name: content.tool_name.clone(),
-                        section: content.section.clone(),
-                        description: content.description.clone(),
-                        vector,
-                    });
-                }
-            }
-        }
+        entries.extend(self.retry_failed(failed).await);
 
         info!(count = entries.len(), "Pipelined processing complete");
         Ok(entries)
     }
 }
 
+/// Returns the texts embedded for a page: its description, then its chunks.
+fn embedding_texts(content: &ManpageContent) -> Vec<String> {
+    std::iter::once(content.description.clone())
+        .chain(
+            content
+                .chunks
+                .iter()
+                .map(|chunk| chunk.embedding_text(&content.tool_name)),
+        )
+        .collect()
+}
+
 /// Default manpage directories to scan.
 const DEFAULT_PATHS: &[&str] = &[
     "/usr/share/man",
@@ -378,14 +517,35 @@ const DEFAULT_PATHS: &[&str] = &[
     "/opt/homebrew/share/man", // macOS Homebrew
 ];
 
-/// Manpage sections to scan (user commands and system administration).
-const SECTIONS: &[&str] = &["man1", "man8"];
+/// Manpage sections scanned unless configured otherwise.
+const DEFAULT_SECTIONS: &[&str] = &["1", "5", "7", "8"];
+
+/// Compression suffixes recognized on manpage files.
+const COMPRESSION_SUFFIXES: &[&str] = &[".gz", ".bz2", ".xz", ".zst"];
+
+/// Decompression commands for the compression suffixes.
+const DECOMPRESSORS: &[(&str, &str)] = &[
+    (".gz", "gzip"),
+    (".bz2", "bzip2"),
+    (".xz", "xz"),
+    (".zst", "zstd"),
+];
+
+/// Files larger than this are never checked for `.so` redirects.
+const MAX_REDIRECT_SIZE: u64 = 1024;
+
+/// Maximum number of chained `.so` redirects followed.
+const MAX_REDIRECTS: usize = 5;
 
 /// Scanner for finding manpage files on the system.
 #[derive(Debug)]
 pub struct ManpageScanner {
     /// Directories to scan for manpages.
     paths: Vec<PathBuf>,
+    /// Sections to scan (e.g., "1" for `man1`).
+    sections: Vec<String>,
+    /// Languages of localized pages to scan in addition to untranslated ones.
+    languages: Vec<String>,
 }
 
 impl ManpageScanner {
@@ -407,60 +567,218 @@ impl ManpageScanner {
         }
 
         debug!(?paths, "Initialized manpage scanner");
-        Self { paths }
+        Self::with_paths(paths)
     }
 
     /// Creates a scanner with custom paths (for testing).
     #[must_use]
-    pub const fn with_paths(paths: Vec<PathBuf>) -> Self {
-        Self { paths }
+    pub fn with_paths(paths: Vec<PathBuf>) -> Self {
+        Self {
+            paths,
+            sections: default_sections(),
+            languages: Vec::new(),
+        }
+    }
+
+    /// Sets the sections to scan (e.g., `["1", "5"]`).
+    #[must_use]
+    pub fn with_sections(mut self, sections: Vec<String>) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Sets the languages of localized pages to scan (e.g., `["de"]`).
+    ///
+    /// A language also matches its regional variants ("de" scans `de`,
+    /// `de_DE` and `de_DE.UTF-8`).
+    #[must_use]
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
     }
 
     /// Scans all configured directories for manpage files.
     ///
-    /// Returns a list of paths to manpage files (man1 and man8 sections).
+    /// Returns a list of paths to manpage files in the configured sections,
+    /// without aliases. See [`Self::scan`].
     ///
     /// # Errors
     ///
     /// Returns an error if directory reading fails unexpectedly.
     pub fn scan_directories(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.scan()?.pages)
+    }
+
+    /// Scans all configured directories for manpages and their aliases.
+    ///
+    /// Directories are compared by canonical path, so a directory reached
+    /// twice (e.g., through a symlink in `$MANPATH`) is scanned once.
+    /// Localized pages are scanned in the locale subdirectories of each
+    /// directory (e.g., `de/man1`) for the configured languages. When
+    /// several directories provide the same page (tool name, section and
+    /// language), only the first one in search order is kept, like `man`
+    /// would display it. Pages that are symlinks or `.so` redirects to another scanned
+    /// page become aliases of that page.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if directory reading fails unexpectedly.
+    pub fn scan(&self) -> Result<ScanResult> {
         let mut manpages = Vec::new();
+        let mut seen_dirs = HashSet::new();
 
         for base_path in &self.paths {
-            if !base_path.exists() {
+            let Ok(canonical) = fs::canonicalize(base_path) else {
                 debug!(?base_path, "Manpage directory does not exist, skipping");
                 continue;
+            };
+            if !seen_dirs.insert(canonical) {
+                debug!(?base_path, "Manpage directory already scanned, skipping");
+                continue;
+            }
+
+            manpages.extend(self.scan_sections(base_path));
+            for locale_path in self.locale_dirs(base_path) {
+                manpages.extend(self.scan_sections(&locale_path));
+            }
+        }
+
+        let result = Self::group_aliases(Self::dedupe_pages(manpages));
+
+        info!(
+            count = result.pages.len(),
+            aliases = result.aliases.len(),
+            "Total manpages found"
+        );
+        Ok(result)
+    }
+
+    /// Scans the configured sections of a manpage directory.
+    fn scan_sections(&self, base_path: &Path) -> Vec<PathBuf> {
+        let mut manpages = Vec::new();
+
+        for section in &self.sections {
+            let section_path = base_path.join(format!("man{section}"));
+            if !section_path.exists() {
+                debug!(?section_path, "Section directory does not exist, skipping");
+                continue;
             }
 
-            for section in SECTIONS {
-                let section_path = base_path.join(section);
-                if !section_path.exists() {
-                    debug!(?section_path, "Section directory does not exist, skipping");
-                    continue;
+            match Self::scan_section(&section_path, section) {
+                Ok(pages) => {
+                    debug!(
+                        path = ?section_path,
+                        count = pages.len(),
+                        "Scanned section"
+                    );
+                    manpages.extend(pages);
                 }
+                Err(e) => {
+                    warn!(path = ?section_path, error = %e, "Failed to scan section");
+                }
+            }
+        }
 
-                match Self::scan_section(&section_path) {
-                    Ok(pages) => {
-                        debug!(
-                            path = ?section_path,
-                            count = pages.len(),
-                            "Scanned section"
-                        );
-                        manpages.extend(pages);
-                    }
-                    Err(e) => {
-                        warn!(path = ?section_path, error = %e, "Failed to scan section");
+        manpages
+    }
+
+    /// Returns the locale subdirectories of a manpage directory for the configured languages.
+    fn locale_dirs(&self, base_path: &Path) -> Vec<PathBuf> {
+        if self.languages.is_empty() {
+            return Vec::new();
+        }
+
+        let Ok(entries) = fs::read_dir(base_path) else {
+            return Vec::new();
+        };
+
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_dir())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(locale::locale_language)
+                    .is_some_and(|language| {
+                        language != DEFAULT_LANGUAGE
+                            && self
+                                .languages
+                                .iter()
+                                .any(|wanted| locale::matches_language(language, wanted))
+                    })
+            })
+            .collect();
+
+        dirs.sort();
+        dirs
+    }
+
+    /// Keeps only the first path for each `(tool_name, section, language)` page.
+    fn dedupe_pages(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+
+        paths
+            .into_iter()
+            .filter(|path| match Self::parse_filename(path) {
+                Ok((tool_name, section)) => {
+                    let identity = (tool_name, section, locale::page_language(path));
+                    let first = seen.insert(identity);
+                    if !first {
+                        debug!(?path, "Shadowed by an earlier manpage, skipping");
                     }
+                    first
+                }
+                Err(_) => true,
+            })
+            .collect()
+    }
+
+    /// Groups paths that resolve to the same manpage file.
+    ///
+    /// Of each group, the path named like the resolved file is indexed (or
+    /// the first one, if the file itself wasn't scanned); the others become
+    /// its aliases.
+    fn group_aliases(paths: Vec<PathBuf>) -> ScanResult {
+        let mut groups: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+        let mut group_index: HashMap<PathBuf, usize> = HashMap::new();
+
+        for path in paths {
+            let resolved = resolve_page(&path);
+            if let Some(&index) = group_index.get(&resolved) {
+                groups[index].1.push(path);
+            } else {
+                group_index.insert(resolved.clone(), groups.len());
+                groups.push((resolved, vec![path]));
+            }
+        }
+
+        let mut result = ScanResult::default();
+        for (resolved, mut members) in groups {
+            let identity = Self::parse_filename(&resolved).ok();
+            let page_index = members
+                .iter()
+                .position(|p| Self::parse_filename(p).ok() == identity)
+                .unwrap_or(0);
+            let page = members.remove(page_index);
+
+            for alias in members {
+                if let Ok((name, section)) = Self::parse_filename(&alias) {
+                    debug!(?alias, ?page, "Alias of another manpage");
+                    result.aliases.push(ManpageAlias {
+                        name,
+                        section,
+                        target: page.clone(),
+                    });
                 }
             }
+            result.pages.push(page);
         }
 
-        info!(count = manpages.len(), "Total manpages found");
-        Ok(manpages)
+        result
     }
 
     /// Scans a single section directory for manpage files.
-    fn scan_section(section_path: &Path) -> Result<Vec<PathBuf>> {
+    fn scan_section(section_path: &Path, section: &str) -> Result<Vec<PathBuf>> {
         let mut pages = Vec::new();
 
         let entries = fs::read_dir(section_path)
@@ -471,7 +789,7 @@ impl ManpageScanner {
                 .with_context(|| format!("Failed to read entry in: {}", section_path.display()))?;
 
             let path = entry.path();
-            if Self::is_manpage_file(&path) {
+            if Self::is_manpage_file(&path, section) {
                 pages.push(path);
             }
         }
@@ -479,17 +797,19 @@ impl ManpageScanner {
         Ok(pages)
     }
 
-    /// Checks if a file is a manpage based on its extension.
-    fn is_manpage_file(path: &Path) -> bool {
+    /// Checks if a file is a manpage of the section based on its extension.
+    ///
+    /// Accepts suffixed sections (`.1p`, `.1ssl`) and compressed files
+    /// (`.gz`, `.bz2`, `.xz`, `.zst`).
+    fn is_manpage_file(path: &Path, section: &str) -> bool {
         let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
             return false;
         };
 
-        // Check for .1, .8, .1.gz, .8.gz extensions
-        name.ends_with(".1")
-            || name.ends_with(".8")
-            || name.ends_with(".1.gz")
-            || name.ends_with(".8.gz")
+        match strip_compression(name).rsplit_once('.') {
+            Some((tool, ext)) => !tool.is_empty() && ext.starts_with(section),
+            None => false,
+        }
     }
 
     /// Returns the configured paths.
@@ -505,32 +825,127 @@ impl ManpageScanner {
     /// Returns an error if the manpage cannot be read or parsed.
     pub fn extract_content(path: &Path) -> Result<ManpageContent> {
         let (tool_name, section) = Self::parse_filename(path)?;
+        let language = locale::page_language(path);
+
+        debug!(tool = %tool_name, section = %section, language = %language, "Extracting manpage content");
+
+        let raw = Self::run_man(&tool_name, &section, &language)?;
 
-        debug!(tool = %tool_name, section = %section, "Extracting manpage content");
+        // Parse NAME and DESCRIPTION
+        let description = Self::parse_manpage_content(&raw, &tool_name);
+        let content = Self::clean_escape_sequences(&raw);
 
-        // Run man -P cat to get raw content
-        let output = Command::new("man")
-            .args(["-P", "cat", &tool_name])
+        Ok(ManpageContent {
+            tool_name,
+            section,
+            language,
+            description,
+            chunks: split_chunks(&content),
+            content,
+            source_path: path.to_path_buf(),
+        })
+    }
+
+    /// Renders a manpage from a section as plain text with escape sequences removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the man command fails or the output is not UTF-8.
+    pub fn render_manpage(tool_name: &str, section: &str, language: &str) -> Result<String> {
+        let raw = Self::run_man(tool_name, section, language)?;
+        Ok(Self::clean_escape_sequences(&raw))
+    }
+
+    /// Runs `man -P cat <section> <name>` and returns the raw output.
+    fn run_man(tool_name: &str, section: &str, language: &str) -> Result<String> {
+        let output = Self::man_command(tool_name, section, language)
             .output()
-            .with_context(|| format!("Failed to execute man command for '{tool_name}'"))?;
+            .with_context(|| {
+                format!("Failed to execute man command for '{tool_name}({section})'")
+            })?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("man command failed for '{}': {}", tool_name, stderr.trim());
+            anyhow::bail!(
+                "Manpage for '{}({})' not found: {}",
+                tool_name,
+                section,
+                stderr.trim()
+            );
         }
 
         // Convert output to UTF-8
-        let content = String::from_utf8(output.stdout)
-            .with_context(|| format!("Manpage '{tool_name}' contains invalid UTF-8"))?;
+        String::from_utf8(output.stdout)
+            .with_context(|| format!("Manpage '{tool_name}({section})' contains invalid UTF-8"))
+    }
 
-        // Parse NAME and DESCRIPTION
-        let description = Self::parse_manpage_content(&content, &tool_name);
+    /// Builds the `man` command that renders a page in a language.
+    ///
+    /// Localized pages are selected with `-L <language>`. Untranslated pages
+    /// are rendered in the C locale, since `man` would otherwise pick a
+    /// translation matching the user's locale.
+    fn man_command(tool_name: &str, section: &str, language: &str) -> Command {
+        let mut command = Command::new("man");
+        if language == DEFAULT_LANGUAGE {
+            command.args(["-L", "C"]).env("LC_ALL", "C");
+        } else {
+            command.args(["-L", language]);
+        }
 
-        Ok(ManpageContent {
-            tool_name,
-            section,
-            description,
-        })
+        command.args(["-P", "cat", section, tool_name]);
+        command
+    }
+
+    /// Removes ANSI escape sequences from text and collapses whitespace.
+    fn clean_escape_sequences(text: &str) -> String {
+        // Simple approach: remove common escape patterns
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                // Skip escape sequence
+                if chars.peek() == Some(&'[') {
+                    chars.next(); // consume '['
+                                  // Skip until we hit a letter (end of sequence)
+                    while let Some(&next) = chars.peek() {
+                        chars.next();
+                        if next.is_ascii_alphabetic() {
+                            break;
+                        }
+                    }
+                }
+            } else {
+                result.push(c);
+            }
+        }
+
+        // Normalize whitespace: collapse multiple spaces/newlines
+        let mut normalized = String::with_capacity(result.len());
+        let mut prev_whitespace = false;
+        let mut prev_newline = false;
+
+        for c in result.chars() {
+            if c == '\n' {
+                if !prev_newline {
+                    normalized.push('\n');
+                    prev_newline = true;
+                }
+                prev_whitespace = true;
+            } else if c.is_whitespace() {
+                if !prev_whitespace {
+                    normalized.push(' ');
+                    prev_whitespace = true;
+                }
+                prev_newline = false;
+            } else {
+                normalized.push(c);
+                prev_whitespace = false;
+                prev_newline = false;
+            }
+        }
+
+        normalized
     }
 
     /// Parses filename to extract tool name and section.
@@ -540,21 +955,15 @@ impl ManpageScanner {
             .and_then(|n| n.to_str())
             .context("Invalid manpage filename")?;
 
-        // Remove .gz if present
-        let filename = filename.strip_suffix(".gz").unwrap_or(filename);
+        // Remove compression suffix if present
+        let filename = strip_compression(filename);
 
-        // Extract section (last character before extension)
-        let section = filename
-            .chars()
-            .last()
-            .map_or_else(|| "1".to_string(), |c| c.to_string());
-
-        // Extract tool name (everything before the dot and section)
-        let tool_name = filename
+        // Split into tool name and section extension (e.g., "openssl", "1ssl")
+        let (tool_name, section) = filename
             .rsplit_once('.')
-            .map_or_else(|| filename.to_string(), |(name, _)| name.to_string());
+            .map_or((filename, "1"), |(name, section)| (name, section));
 
-        Ok((tool_name, section))
+        Ok((tool_name.to_string(), section.to_string()))
     }
 
     /// Parses manpage content to extract NAME and DESCRIPTION.
@@ -602,6 +1011,9 @@ impl ManpageScanner {
     }
 
     /// Extracts a section from manpage content.
+    ///
+    /// `section_name` is the English header; translated headers (e.g.,
+    /// "BESCHREIBUNG" for "DESCRIPTION") match as well.
     fn extract_section(content: &str, section_name: &str) -> Option<String> {
         let lines: Vec<&str> = content.lines().collect();
         let mut in_section = false;
@@ -610,8 +1022,11 @@ impl ManpageScanner {
         for line in lines {
             let trimmed = line.trim();
 
-            // Check if this is a section header
-            if trimmed == section_name || trimmed == section_name.to_uppercase() {
+            // Check if this is a section header, possibly translated
+            if trimmed == section_name
+                || trimmed == section_name.to_uppercase()
+                || locale::canonical_header(trimmed) == Some(section_name)
+            {
                 in_section = true;
                 continue;
             }
@@ -683,6 +1098,96 @@ impl Default for ManpageScanner {
     }
 }
 
+/// Returns the default sections to scan.
+fn default_sections() -> Vec<String> {
+    DEFAULT_SECTIONS.iter().map(ToString::to_string).collect()
+}
+
+/// Resolves symlinks and `.so` redirects of a manpage file.
+///
+/// Returns the canonical path of the file that holds the page content, or
+/// the path itself if it cannot be resolved.
+fn resolve_page(path: &Path) -> PathBuf {
+    let mut current = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    for _ in 0..MAX_REDIRECTS {
+        let Some(target) = read_redirect(&current) else {
+            break;
+        };
+        match fs::canonicalize(&target) {
+            Ok(next) if next != current => current = next,
+            _ => break,
+        }
+    }
+
+    current
+}
+
+/// Returns the file a `.so` redirect page points to, if it is one.
+///
+/// Redirect targets (e.g., `man1/vim.1`) are relative to the manpage
+/// directory and may be compressed.
+fn read_redirect(path: &Path) -> Option<PathBuf> {
+    if fs::metadata(path).ok()?.len() > MAX_REDIRECT_SIZE {
+        return None;
+    }
+
+    let text = read_page_source(path)?;
+    let target = parse_redirect(&text)?;
+
+    // <base>/man1/vi.1 → <base>
+    let base = path.parent()?.parent()?;
+    let target = base.join(target);
+    if target.exists() {
+        return Some(target);
+    }
+
+    let name = target.file_name()?.to_str()?;
+    COMPRESSION_SUFFIXES
+        .iter()
+        .map(|suffix| target.with_file_name(format!("{name}{suffix}")))
+        .find(|candidate| candidate.exists())
+}
+
+/// Reads the roff source of a small manpage file, decompressing it if needed.
+fn read_page_source(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+
+    let Some((_, program)) = DECOMPRESSORS
+        .iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+    else {
+        return fs::read_to_string(path).ok();
+    };
+
+    let output = Command::new(program).arg("-dc").arg(path).output().ok()?;
+    if !output.status.success() {
+        debug!(?path, program, "Failed to decompress manpage");
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Parses a `.so <file>` request from the first roff line that isn't a comment.
+fn parse_redirect(source: &str) -> Option<&str> {
+    let line = source.lines().map(str::trim).find(|line| {
+        !line.is_empty() && !line.starts_with(".\\\"") && !line.starts_with("'\\\"")
+    })?;
+
+    line.strip_prefix(".so")
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+}
+
+/// Removes a compression suffix (e.g., ".gz") from a filename.
+fn strip_compression(name: &str) -> &str {
+    COMPRESSION_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -754,14 +1259,171 @@ mod tests {
 
     #[test]
     fn test_is_manpage_file() {
-        assert!(ManpageScanner::is_manpage_file(Path::new("ls.1")));
-        assert!(ManpageScanner::is_manpage_file(Path::new("cat.1.gz")));
-        assert!(ManpageScanner::is_manpage_file(Path::new("mount.8")));
-        assert!(ManpageScanner::is_manpage_file(Path::new("fsck.8.gz")));
+        assert!(ManpageScanner::is_manpage_file(Path::new("ls.1"), "1"));
+        assert!(ManpageScanner::is_manpage_file(Path::new("cat.1.gz"), "1"));
+        assert!(ManpageScanner::is_manpage_file(Path::new("mount.8"), "8"));
+        assert!(ManpageScanner::is_manpage_file(Path::new("fsck.8.gz"), "8"));
+        assert!(ManpageScanner::is_manpage_file(
+            Path::new("crontab.5.xz"),
+            "5"
+        ));
+        assert!(ManpageScanner::is_manpage_file(Path::new("cp.1p.bz2"), "1"));
+        assert!(ManpageScanner::is_manpage_file(
+            Path::new("openssl.1ssl.zst"),
+            "1"
+        ));
+
+        assert!(!ManpageScanner::is_manpage_file(
+            Path::new("readme.txt"),
+            "1"
+        ));
+        assert!(!ManpageScanner::is_manpage_file(Path::new("lib.3"), "1")); // wrong section
+        assert!(!ManpageScanner::is_manpage_file(Path::new(".1"), "1"));
+    }
+
+    #[test]
+    fn test_scan_skips_shadowed_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = create_test_structure(&temp_dir);
+        let second = temp_dir.path().join("local");
+        fs::create_dir_all(second.join("man1")).unwrap();
+        File::create(second.join("man1").join("ls.1.xz")).unwrap();
+        File::create(second.join("man1").join("tree.1")).unwrap();
+
+        let scanner = ManpageScanner::with_paths(vec![first, second]);
+        let pages = scanner.scan_directories().unwrap();
 
-        assert!(!ManpageScanner::is_manpage_file(Path::new("readme.txt")));
-        assert!(!ManpageScanner::is_manpage_file(Path::new("lib.3"))); // man3 not supported
-        assert!(!ManpageScanner::is_manpage_file(Path::new("config.5"))); // man5 not supported
+        assert_eq!(pages.len(), 5);
+        assert!(!pages.iter().any(|p| p.ends_with("ls.1.xz")));
+    }
+
+    #[test]
+    fn test_scan_symlinked_page_becomes_alias() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = create_test_structure(&temp_dir);
+        let man1 = base.join("man1");
+        fs::write(man1.join("vim.1"), ".TH VIM 1\n").unwrap();
+        std::os::unix::fs::symlink("vim.1", man1.join("vi.1")).unwrap();
+
+        let scanner = ManpageScanner::with_paths(vec![base]).with_sections(vec!["1".to_string()]);
+        let result = scanner.scan().unwrap();
+
+        assert_eq!(result.pages.len(), 3);
+        assert!(!result.pages.iter().any(|p| p.ends_with("vi.1")));
+        assert_eq!(
+            result.aliases,
+            vec![ManpageAlias {
+                name: "vi".to_string(),
+                section: "1".to_string(),
+                target: man1.join("vim.1"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_scan_so_redirect_becomes_alias() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = create_test_structure(&temp_dir);
+        let man1 = base.join("man1");
+        fs::write(man1.join("gunzip.1"), ".\\\" redirect\n.so man1/gzip.1\n").unwrap();
+        fs::write(man1.join("gzip.1"), ".TH GZIP 1\n").unwrap();
+
+        let scanner = ManpageScanner::with_paths(vec![base]).with_sections(vec!["1".to_string()]);
+        let result = scanner.scan().unwrap();
+
+        assert!(result.pages.iter().any(|p| p.ends_with("gzip.1")));
+        assert!(!result.pages.iter().any(|p| p.ends_with("gunzip.1")));
+        assert_eq!(result.aliases.len(), 1);
+        assert_eq!(result.aliases[0].name, "gunzip");
+        assert_eq!(result.aliases[0].target, man1.join("gzip.1"));
+    }
+
+    #[test]
+    fn test_scan_same_directory_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = create_test_structure(&temp_dir);
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&base, &link).unwrap();
+
+        let scanner = ManpageScanner::with_paths(vec![base.clone(), link, base]);
+        let result = scanner.scan().unwrap();
+
+        assert_eq!(result.pages.len(), 4);
+        assert!(result.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_scan_configured_languages() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = create_test_structure(&temp_dir);
+        for locale in ["de", "de_DE.UTF-8", "fr"] {
+            let man1 = base.join(locale).join("man1");
+            fs::create_dir_all(&man1).unwrap();
+            File::create(man1.join("ls.1.gz")).unwrap();
+        }
+
+        let scanner = ManpageScanner::with_paths(vec![base.clone()]);
+        assert_eq!(scanner.scan().unwrap().pages.len(), 4);
+
+        let scanner = ManpageScanner::with_paths(vec![base]).with_languages(vec!["de".to_string()]);
+        let pages = scanner.scan().unwrap().pages;
+        let languages: Vec<String> = pages
+            .iter()
+            .filter(|p| p.ends_with("ls.1.gz"))
+            .map(|p| locale::page_language(p))
+            .collect();
+        assert_eq!(languages, vec!["de", "de_DE"]);
+        assert!(pages.iter().any(|p| p.ends_with("ls.1")));
+    }
+
+    #[test]
+    fn test_parse_redirect() {
+        assert_eq!(parse_redirect(".so man1/vim.1\n"), Some("man1/vim.1"));
+        assert_eq!(
+            parse_redirect(".\\\" comment\n\n.so man8/mount.8"),
+            Some("man8/mount.8")
+        );
+        assert_eq!(parse_redirect(".TH LS 1\n.so man1/vim.1"), None);
+        assert_eq!(parse_redirect(".source x"), None);
+        assert_eq!(parse_redirect(""), None);
+    }
+
+    #[test]
+    fn test_scan_configured_sections() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = create_test_structure(&temp_dir);
+        let man5 = base.join("man5");
+        fs::create_dir_all(&man5).unwrap();
+        File::create(man5.join("crontab.5.xz")).unwrap();
+
+        let scanner = ManpageScanner::with_paths(vec![base.clone()]);
+        assert_eq!(scanner.scan_directories().unwrap().len(), 5);
+
+        let scanner = ManpageScanner::with_paths(vec![base]).with_sections(vec!["5".to_string()]);
+        let pages = scanner.scan_directories().unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].ends_with("crontab.5.xz"));
+    }
+
+    #[test]
+    fn test_man_command_language() {
+        let args = |command: &Command| -> Vec<String> {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        // Untranslated pages ignore the user's locale
+        let command = ManpageScanner::man_command("ls", "1", DEFAULT_LANGUAGE);
+        assert_eq!(args(&command), ["-L", "C", "-P", "cat", "1", "ls"]);
+        assert!(command
+            .get_envs()
+            .any(|(key, value)| key == "LC_ALL" && value == Some("C".as_ref())));
+
+        let command = ManpageScanner::man_command("ls", "1", "de");
+        assert_eq!(args(&command), ["-L", "de", "-P", "cat", "1", "ls"]);
+        assert_eq!(command.get_envs().count(), 0);
     }
 
     #[test]
@@ -777,6 +1439,11 @@ mod tests {
         let (name, section) = ManpageScanner::parse_filename(Path::new("git-commit.1")).unwrap();
         assert_eq!(name, "git-commit");
         assert_eq!(section, "1");
+
+        let (name, section) =
+            ManpageScanner::parse_filename(Path::new("openssl.1ssl.zst")).unwrap();
+        assert_eq!(name, "openssl");
+        assert_eq!(section, "1ssl");
     }
 
     #[test]
@@ -801,6 +1468,17 @@ mod tests {
         assert!(result.contains("list"));
     }
 
+    #[test]
+    fn test_parse_translated_manpage_content() {
+        let content = "BEZEICHNUNG\n       ls - Verzeichnisinhalte auflisten\n\nÜBERSICHT\n       ls [OPTION]...\n\nBESCHREIBUNG\n       Informationen über DATEIEN auflisten.";
+
+        let result = ManpageScanner::parse_manpage_content(content, "ls");
+        assert_eq!(
+            result,
+            "ls - Verzeichnisinhalte auflisten - Informationen über DATEIEN auflisten."
+        );
+    }
+
     #[test]
     fn test_extract_first_paragraph() {
         let text = "First paragraph line one. Line two.\n\nSecond paragraph.";
@@ -808,4 +1486,118 @@ mod tests {
         assert!(para.contains("First paragraph"));
         assert!(!para.contains("Second"));
     }
+
+    #[test]
+    fn test_clean_escape_sequences() {
+        let text = "\x1b[1mNAME\x1b[0m\n\n\n       ls  -  list";
+        let cleaned = ManpageScanner::clean_escape_sequences(text);
+        assert_eq!(cleaned, "NAME\nls - list");
+    }
+
+    /// Embedder that returns the text length and records its requests.
+    #[derive(Debug, Default)]
+    struct FakeEmbedder {
+        /// Batches with more texts than this fail.
+        max_batch: usize,
+        /// Number of texts of every batch request.
+        requests: std::sync::Mutex<Vec<usize>>,
+    }
+
+    impl Embedder for FakeEmbedder {
+        fn embed<'a>(
+            &'a self,
+            _model: &'a str,
+            text: &'a str,
+        ) -> futures::future::BoxFuture<'a, Result<Vec<f32>>> {
+            #[allow(clippy::cast_precision_loss)]
+            let vector = vec![text.len() as f32];
+            Box::pin(async move { Ok(vector) })
+        }
+
+        fn embed_batch<'a>(
+            &'a self,
+            model: &'a str,
+            texts: &'a [String],
+        ) -> futures::future::BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            Box::pin(async move {
+                self.requests.lock().unwrap().push(texts.len());
+                anyhow::ensure!(texts.len() <= self.max_batch, "batch too large");
+
+                let mut vectors = Vec::new();
+                for text in texts {
+                    vectors.push(self.embed(model, text).await?);
+                }
+                Ok(vectors)
+            })
+        }
+    }
+
+    fn test_content(tool_name: &str, chunks: usize) -> ManpageContent {
+        ManpageContent {
+            tool_name: tool_name.to_string(),
+            section: "1".to_string(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            description: format!("{tool_name} - description"),
+            content: String::new(),
+            chunks: (0..chunks)
+                .map(|i| ManpageChunk {
+                    heading: "OPTIONS".to_string(),
+                    text: "x".repeat(i + 1),
+                })
+                .collect(),
+            source_path: PathBuf::from(format!("/usr/share/man/man1/{tool_name}.1.gz")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_embeddings_in_batches() {
+        let embedder = Arc::new(FakeEmbedder {
+            max_batch: 4,
+            ..FakeEmbedder::default()
+        });
+        let generator =
+            EmbeddingGenerator::with_embedder(embedder.clone(), "fake").with_batch_size(4);
+
+        let contents = vec![
+            test_content("ls", 2),
+            test_content("cp", 0),
+            test_content("mv", 1),
+        ];
+        let mut entries = generator.generate_embeddings(contents).await.unwrap();
+        entries.sort_by(|a, b| a.tool_name.cmp(&b.tool_name));
+
+        // 6 texts in requests of at most 4
+        assert_eq!(*embedder.requests.lock().unwrap(), vec![4, 2]);
+
+        let ls = &entries[1];
+        assert_eq!(ls.tool_name, "ls");
+        assert_eq!(ls.vector, vec![16.0]);
+        assert_eq!(ls.chunks.len(), 2);
+        assert_eq!(ls.chunks[0].vector, vec![13.0]);
+        assert_eq!(ls.chunks[1].vector, vec![14.0]);
+        assert!(entries[0].chunks.is_empty());
+        assert_eq!(entries[2].chunks[0].vector, vec![13.0]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_falls_back_to_single_pages() {
+        let embedder = Arc::new(FakeEmbedder {
+            max_batch: 2,
+            ..FakeEmbedder::default()
+        });
+        let generator =
+            EmbeddingGenerator::with_embedder(embedder.clone(), "fake").with_batch_size(3);
+
+        let contents = vec![
+            test_content("ls", 1),
+            test_content("cp", 0),
+            test_content("mv", 0),
+        ];
+        let entries = generator.generate_embeddings(contents).await.unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|entry| entry.vector.len() == 1));
+        // One failed batch of 3 pages (4 texts), then one request per page
+        assert_eq!(*embedder.requests.lock().unwrap(), vec![3, 2, 1, 1]);
+    }
 }
@@ -110,8 +110,7 @@ fn check_command_exists(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
         .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+        .is_ok_and(|output| output.status.success())
 }
 
 /// Result of an installation attempt.
@@ -330,8 +329,7 @@ fn is_docker_daemon_running() -> bool {
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()
-        .map(|status| status.success())
-        .unwrap_or(false)
+        .is_ok_and(|status| status.success())
 }
 
 /// Docker container status.
@@ -359,8 +357,7 @@ fn check_container_status(name: &str) -> ContainerStatus {
     let running = Command::new("docker")
         .args(["ps", "-q", "-f", &format!("name=^{name}$")])
         .output()
-        .map(|output| !output.stdout.is_empty())
-        .unwrap_or(false);
+        .is_ok_and(|output| !output.stdout.is_empty());
 
     if running {
         return ContainerStatus::Running;
@@ -370,8 +367,7 @@ fn check_container_status(name: &str) -> ContainerStatus {
     let exists = Command::new("docker")
         .args(["ps", "-aq", "-f", &format!("name=^{name}$")])
         .output()
-        .map(|output| !output.stdout.is_empty())
-        .unwrap_or(false);
+        .is_ok_and(|output| !output.stdout.is_empty());
 
     if exists {
         ContainerStatus::Stopped
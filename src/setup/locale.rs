@@ -0,0 +1,228 @@
+//! Languages of localized manpages.
+//!
+//! Translated manpages live in locale subdirectories of a man directory
+//! (e.g., `/usr/share/man/de/man1`). Untranslated pages are stored with the
+//! language [`DEFAULT_LANGUAGE`].
+
+use std::env;
+use std::path::Path;
+
+/// Language of untranslated manpages.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Translations of the section headers that are parsed, by English name.
+///
+/// Headers are matched after rendering, so only all-caps spellings appear.
+const SECTION_HEADERS: &[(&str, &[&str])] = &[
+    (
+        "NAME",
+        &[
+            "BEZEICHNUNG",
+            "NOM",
+            "NOMBRE",
+            "NOME",
+            "NAAM",
+            "NAZWA",
+            "JMÉNO",
+            "НАЗВАНИЕ",
+        ],
+    ),
+    (
+        "SYNOPSIS",
+        &[
+            "ÜBERSICHT",
+            "SYNOPSE",
+            "SINOPSIS",
+            "SINTASSI",
+            "SKŁADNIA",
+            "ОБЗОР",
+        ],
+    ),
+    (
+        "DESCRIPTION",
+        &[
+            "BESCHREIBUNG",
+            "DESCRIPCIÓN",
+            "DESCRIÇÃO",
+            "DESCRIZIONE",
+            "BESCHRIJVING",
+            "OPIS",
+            "POPIS",
+            "ОПИСАНИЕ",
+        ],
+    ),
+    (
+        "OPTIONS",
+        &[
+            "OPTIONEN",
+            "OPCIONES",
+            "OPÇÕES",
+            "OPZIONI",
+            "OPCJE",
+            "ПАРАМЕТРЫ",
+        ],
+    ),
+    (
+        "EXAMPLES",
+        &[
+            "BEISPIELE",
+            "EXEMPLES",
+            "EJEMPLOS",
+            "EXEMPLOS",
+            "ESEMPI",
+            "PRZYKŁADY",
+            "ПРИМЕРЫ",
+        ],
+    ),
+    (
+        "SEE ALSO",
+        &[
+            "SIEHE AUCH",
+            "VOIR AUSSI",
+            "VÉASE TAMBIÉN",
+            "VEJA TAMBÉM",
+            "VEDERE ANCHE",
+            "ZOBACZ TAKŻE",
+            "СМОТРИТЕ ТАКЖЕ",
+        ],
+    ),
+    ("AUTHOR", &["AUTOR", "AUTEUR", "AUTORE", "АВТОР"]),
+    (
+        "AUTHORS",
+        &["AUTOREN", "AUTEURS", "AUTORES", "AUTORI", "АВТОРЫ"],
+    ),
+    (
+        "REPORTING BUGS",
+        &[
+            "FEHLER MELDEN",
+            "SIGNALER DES BOGUES",
+            "INFORMAR DE ERRORES",
+        ],
+    ),
+    (
+        "COPYRIGHT",
+        &["URHEBERRECHT", "DERECHOS DE AUTOR", "АВТОРСКИЕ ПРАВА"],
+    ),
+    (
+        "HISTORY",
+        &["GESCHICHTE", "HISTORIQUE", "HISTORIA", "ИСТОРИЯ"],
+    ),
+    ("COLOPHON", &["KOLOPHON", "COLOFÓN"]),
+];
+
+/// Returns the English name of a section header, translated or not.
+///
+/// Returns `None` for headers that are not in the translation table.
+#[must_use]
+pub fn canonical_header(header: &str) -> Option<&'static str> {
+    SECTION_HEADERS
+        .iter()
+        .find(|(name, translations)| *name == header || translations.contains(&header))
+        .map(|(name, _)| *name)
+}
+
+/// Returns the language of a locale directory name (e.g., `de_DE.UTF-8` → `de_DE`).
+///
+/// The codeset and modifier are dropped. Returns `None` if the name is not a
+/// locale (`ll` or `ll_CC`, optionally followed by `.codeset` and `@modifier`).
+#[must_use]
+pub fn locale_language(name: &str) -> Option<&str> {
+    let language = name.split(['.', '@']).next().unwrap_or_default();
+    let (code, territory) = language
+        .split_once('_')
+        .map_or((language, None), |(code, territory)| {
+            (code, Some(territory))
+        });
+
+    let valid_code = code.len() == 2 && code.bytes().all(|b| b.is_ascii_lowercase());
+    let valid_territory =
+        territory.is_none_or(|t| t.len() == 2 && t.bytes().all(|b| b.is_ascii_uppercase()));
+
+    (valid_code && valid_territory).then_some(language)
+}
+
+/// Returns the language of a manpage file from its locale directory.
+///
+/// `<base>/de/man1/ls.1.gz` is in "de"; pages outside a locale directory are
+/// in [`DEFAULT_LANGUAGE`].
+#[must_use]
+pub fn page_language(path: &Path) -> String {
+    path.parent()
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .and_then(|name| name.to_str())
+        .and_then(locale_language)
+        .unwrap_or(DEFAULT_LANGUAGE)
+        .to_string()
+}
+
+/// Checks if a language (e.g., `de_DE`) is a variant of `wanted` (e.g., `de`).
+#[must_use]
+pub fn matches_language(language: &str, wanted: &str) -> bool {
+    language == wanted
+        || language.split('_').next() == Some(wanted)
+        || wanted.split('_').next() == Some(language)
+}
+
+/// Returns the language of the user's locale (`LC_ALL`, `LC_MESSAGES`, `LANG`).
+///
+/// Returns `None` for the C/POSIX locale or if no locale is set.
+#[must_use]
+pub fn user_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| locale_language(&value).map(ToString::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_canonical_header() {
+        assert_eq!(canonical_header("NAME"), Some("NAME"));
+        assert_eq!(canonical_header("BEZEICHNUNG"), Some("NAME"));
+        assert_eq!(canonical_header("BESCHREIBUNG"), Some("DESCRIPTION"));
+        assert_eq!(canonical_header("SIEHE AUCH"), Some("SEE ALSO"));
+        assert_eq!(canonical_header("FOO"), None);
+    }
+
+    #[test]
+    fn test_locale_language() {
+        assert_eq!(locale_language("de"), Some("de"));
+        assert_eq!(locale_language("de_DE.UTF-8"), Some("de_DE"));
+        assert_eq!(locale_language("sr@latin"), Some("sr"));
+        assert_eq!(locale_language("man"), None);
+        assert_eq!(locale_language("man1"), None);
+        assert_eq!(locale_language("C.UTF-8"), None);
+        assert_eq!(locale_language("POSIX"), None);
+    }
+
+    #[test]
+    fn test_page_language() {
+        assert_eq!(
+            page_language(&PathBuf::from("/usr/share/man/de/man1/ls.1.gz")),
+            "de"
+        );
+        assert_eq!(
+            page_language(&PathBuf::from("/usr/share/man/pt_BR/man1/ls.1.gz")),
+            "pt_BR"
+        );
+        assert_eq!(
+            page_language(&PathBuf::from("/usr/share/man/man1/ls.1.gz")),
+            DEFAULT_LANGUAGE
+        );
+    }
+
+    #[test]
+    fn test_matches_language() {
+        assert!(matches_language("de", "de"));
+        assert!(matches_language("de_DE", "de"));
+        assert!(matches_language("de", "de_AT"));
+        assert!(!matches_language("pt_BR", "pt_PT"));
+        assert!(!matches_language("fr", "de"));
+    }
+}
@@ -1,74 +1,95 @@
 //! Metadata tracking for incremental updates.
 //!
-//! Stores file hashes to enable processing only new/changed manpages.
+//! Tracks the hash, modification time and embedding model of every indexed
+//! manpage file in `index.db`, so only new/changed manpages are processed.
+//! File records are written in the same transaction as the index rows.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
 use crate::db;
 
-/// Metadata for indexed manpages.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct IndexMetadata {
-    /// Map of file path to content hash.
-    pub files: HashMap<String, String>,
+/// Name of the legacy JSON metadata file (before hashes moved into the index).
+const LEGACY_METADATA_FILENAME: &str = "index_metadata.json";
+
+/// Tracking record for an indexed manpage file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRecord {
+    /// Path of the manpage file.
+    pub source_path: String,
+    /// blake3 hash of the file contents.
+    pub hash: String,
+    /// Modification time in seconds since the Unix epoch.
+    pub mtime: Option<i64>,
+    /// Embedding model used to index the file.
+    pub embedding_model: String,
+    /// Time of indexing in seconds since the Unix epoch.
+    pub indexed_at: i64,
 }
 
-impl IndexMetadata {
-    /// Loads metadata from disk, or returns empty if not found.
+impl FileRecord {
+    /// Builds a record for a file by hashing its current contents.
     ///
     /// # Errors
     ///
-    /// Returns an error if the file exists but cannot be read or parsed.
-    pub fn load() -> Result<Self> {
-        let path = get_metadata_path()?;
-
-        if !path.exists() {
-            return Ok(Self::default());
-        }
-
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
-
-        let metadata: Self = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse metadata: {}", path.display()))?;
-
-        info!(files = metadata.files.len(), "Loaded index metadata");
-        Ok(metadata)
+    /// Returns an error if the file cannot be read.
+    pub fn for_file(path: &Path, embedding_model: &str) -> Result<Self> {
+        Ok(Self {
+            source_path: path.to_string_lossy().to_string(),
+            hash: compute_file_hash(path)?,
+            mtime: file_mtime(path),
+            embedding_model: embedding_model.to_string(),
+            indexed_at: unix_now(),
+        })
     }
+}
 
-    /// Saves metadata to disk.
+/// Metadata for indexed manpages.
+#[derive(Debug, Clone, Default)]
+pub struct IndexMetadata {
+    /// Map of file path to tracking record.
+    pub files: HashMap<String, FileRecord>,
+}
+
+impl IndexMetadata {
+    /// Loads file records from the index, or returns empty if there are none.
+    ///
+    /// Removes a legacy `index_metadata.json` on first load; its hashes
+    /// can't be trusted, so the pages it lists are processed again.
     ///
     /// # Errors
     ///
-    /// Returns an error if the file cannot be written.
-    pub fn save(&self) -> Result<()> {
-        let path = get_metadata_path()?;
-
-        // Ensure parent directory exists
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).with_context(|| {
-                format!("Failed to create metadata directory: {}", parent.display())
-            })?;
-        }
+    /// Returns an error if the index cannot be read or the migration fails.
+    pub async fn load() -> Result<Self> {
+        migrate_legacy_metadata(&get_legacy_metadata_path()?)?;
 
-        let content = serde_json::to_string_pretty(self).context("Failed to serialize metadata")?;
+        let records = db::load_file_records()
+            .await
+            .context("Failed to load file records")?;
 
-        fs::write(&path, content)
-            .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+        let files: HashMap<String, FileRecord> = records
+            .into_iter()
+            .map(|r| (r.source_path.clone(), r))
+            .collect();
 
-        info!(files = self.files.len(), "Saved index metadata");
-        Ok(())
+        info!(files = files.len(), "Loaded index metadata");
+        Ok(Self { files })
     }
 
     /// Filters paths to only those that are new or changed.
     ///
+    /// The embedding model is not compared: manpage text is shared by all
+    /// models, and missing vectors are embedded from the stored text.
+    ///
+    /// Files whose modification time matches the stored record are treated
+    /// as unchanged without hashing them.
+    ///
     /// Returns tuple of (paths to process, count of unchanged).
     pub fn filter_changed(&self, paths: Vec<PathBuf>) -> (Vec<PathBuf>, usize) {
         let mut to_process = Vec::new();
@@ -77,17 +98,21 @@ impl IndexMetadata {
         for path in paths {
             let path_str = path.to_string_lossy().to_string();
 
+            let Some(record) = self.files.get(&path_str) else {
+                debug!(path = %path_str, "New file");
+                to_process.push(path);
+                continue;
+            };
+
+            if record.mtime.is_some() && record.mtime == file_mtime(&path) {
+                unchanged += 1;
+                continue;
+            }
+
             match compute_file_hash(&path) {
-                Ok(hash) => {
-                    if let Some(stored_hash) = self.files.get(&path_str) {
-                        if &hash == stored_hash {
-                            unchanged += 1;
-                            continue;
-                        }
-                        debug!(path = %path_str, "File changed");
-                    } else {
-                        debug!(path = %path_str, "New file");
-                    }
+                Ok(hash) if hash == record.hash => unchanged += 1,
+                Ok(_) => {
+                    debug!(path = %path_str, "File changed");
                     to_process.push(path);
                 }
                 Err(e) => {
@@ -100,35 +125,104 @@ impl IndexMetadata {
         (to_process, unchanged)
     }
 
-    /// Updates metadata with new file hashes.
-    pub fn update_hashes(&mut self, paths: &[PathBuf]) {
+    /// Forgets the records of the given paths so they are processed again.
+    pub fn forget(&mut self, paths: &[String]) {
         for path in paths {
-            let path_str = path.to_string_lossy().to_string();
-
-            if let Ok(hash) = compute_file_hash(path) {
-                self.files.insert(path_str, hash);
-            }
+            self.files.remove(path);
         }
     }
 
-    /// Removes entries for files that no longer exist.
-    pub fn remove_deleted(&mut self) {
-        let before = self.files.len();
-        self.files.retain(|path, _| Path::new(path).exists());
-        let removed = before - self.files.len();
+    /// Removes entries for files that were not found by the latest scan.
+    ///
+    /// This covers deleted files as well as files in sections or directories
+    /// that are no longer scanned. Returns the paths that were removed so
+    /// they can be dropped from the index.
+    pub fn remove_deleted(&mut self, scanned: &[PathBuf]) -> Vec<String> {
+        let scanned: HashSet<String> = scanned
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !scanned.contains(*path))
+            .cloned()
+            .collect();
+
+        for path in &removed {
+            self.files.remove(path);
+        }
 
-        if removed > 0 {
-            info!(removed = removed, "Removed deleted files from metadata");
+        if !removed.is_empty() {
+            info!(
+                removed = removed.len(),
+                "Removed deleted files from metadata"
+            );
         }
+
+        removed
     }
 }
 
-/// Gets the path to the metadata file.
-fn get_metadata_path() -> Result<PathBuf> {
+/// Builds file records for the given paths, skipping unreadable files.
+#[must_use]
+pub fn file_records(paths: &[PathBuf], embedding_model: &str) -> Vec<FileRecord> {
+    paths
+        .iter()
+        .filter_map(|path| match FileRecord::for_file(path, embedding_model) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                debug!(path = ?path, error = %e, "Failed to build file record");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Removes a legacy `index_metadata.json`.
+///
+/// Its hashes aren't imported: the file was saved before the index was
+/// written, so a hash may belong to a page version that never made it into
+/// the index. Without records, the next update checks every page again.
+fn migrate_legacy_metadata(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    fs::remove_file(path)
+        .with_context(|| format!("Failed to remove metadata: {}", path.display()))?;
+    info!(path = %path.display(), "Removed legacy index metadata, pages are checked again");
+
+    Ok(())
+}
+
+/// Gets the path to the legacy metadata file.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be determined.
+pub fn get_legacy_metadata_path() -> Result<PathBuf> {
     let db_path = db::get_database_path()?;
     let parent = db_path.parent().context("Database path has no parent")?;
 
-    Ok(parent.join("index_metadata.json"))
+    Ok(parent.join(LEGACY_METADATA_FILENAME))
+}
+
+/// Returns the modification time of a file in seconds since the Unix epoch.
+fn file_mtime(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|d| i64::try_from(d.as_secs()).ok())
+        .unwrap_or(0)
 }
 
 /// Computes a hash of the file contents.
@@ -160,6 +254,15 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    fn metadata_for(paths: &[PathBuf], model: &str) -> IndexMetadata {
+        IndexMetadata {
+            files: file_records(paths, model)
+                .into_iter()
+                .map(|r| (r.source_path.clone(), r))
+                .collect(),
+        }
+    }
+
     #[test]
     fn test_metadata_default() {
         let metadata = IndexMetadata::default();
@@ -202,8 +305,7 @@ mod tests {
         fs::write(&file1, "content 1").unwrap();
         fs::write(&file2, "content 2").unwrap();
 
-        let mut metadata = IndexMetadata::default();
-        metadata.update_hashes(&[file1.clone()]);
+        let metadata = metadata_for(std::slice::from_ref(&file1), "nomic-embed-text");
 
         let paths = vec![file1.clone(), file2.clone()];
         let (to_process, unchanged) = metadata.filter_changed(paths);
@@ -212,4 +314,89 @@ mod tests {
         assert_eq!(to_process.len(), 1);
         assert_eq!(to_process[0], file2);
     }
+
+    #[test]
+    fn test_filter_changed_detects_content_change() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, "content 1").unwrap();
+
+        let mut metadata = metadata_for(std::slice::from_ref(&file), "nomic-embed-text");
+        // Force the hash comparison by clearing the stored mtime
+        for record in metadata.files.values_mut() {
+            record.mtime = None;
+        }
+        fs::write(&file, "content 2").unwrap();
+
+        let (to_process, unchanged) = metadata.filter_changed(vec![file]);
+        assert_eq!(unchanged, 0);
+        assert_eq!(to_process.len(), 1);
+    }
+
+    #[test]
+    fn test_filter_changed_ignores_model_change() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, "content").unwrap();
+
+        // Vectors for another model are backfilled from the stored text
+        let metadata = metadata_for(std::slice::from_ref(&file), "mxbai-embed-large");
+
+        let (to_process, unchanged) = metadata.filter_changed(vec![file]);
+        assert_eq!(unchanged, 1);
+        assert!(to_process.is_empty());
+    }
+
+    #[test]
+    fn test_remove_deleted() {
+        let temp = TempDir::new().unwrap();
+        let kept = temp.path().join("kept.1");
+        let deleted = temp.path().join("deleted.1");
+
+        fs::write(&kept, "kept").unwrap();
+        fs::write(&deleted, "deleted").unwrap();
+
+        let mut metadata = metadata_for(&[kept.clone(), deleted.clone()], "nomic-embed-text");
+        fs::remove_file(&deleted).unwrap();
+
+        // The deleted file is no longer found by the scan
+        let removed = metadata.remove_deleted(std::slice::from_ref(&kept));
+
+        assert_eq!(removed, vec![deleted.to_string_lossy().to_string()]);
+        assert_eq!(metadata.files.len(), 1);
+        assert!(metadata
+            .files
+            .contains_key(&kept.to_string_lossy().to_string()));
+    }
+
+    #[test]
+    fn test_remove_deleted_drops_unscanned_files() {
+        let temp = TempDir::new().unwrap();
+        let page = temp.path().join("crontab.5");
+        fs::write(&page, "crontab").unwrap();
+
+        // Still on disk, but its section is no longer scanned
+        let mut metadata = metadata_for(std::slice::from_ref(&page), "nomic-embed-text");
+        let removed = metadata.remove_deleted(&[]);
+
+        assert_eq!(removed, vec![page.to_string_lossy().to_string()]);
+        assert!(metadata.files.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_legacy_metadata_discards_hashes() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(LEGACY_METADATA_FILENAME);
+        fs::write(
+            &path,
+            r#"{"files": {"/usr/share/man/man1/ls.1.gz": "abc123"}}"#,
+        )
+        .unwrap();
+
+        migrate_legacy_metadata(&path).unwrap();
+        assert!(!path.exists());
+
+        // Nothing to migrate on later loads
+        migrate_legacy_metadata(&path).unwrap();
+    }
 }
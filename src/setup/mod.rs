@@ -4,23 +4,32 @@
 //! - Ollama detection and health checking
 //! - Model verification and pulling
 //! - Manpage scanning and indexing
+//! - Index export and import
 
+pub mod bundle;
+pub mod chunk;
 pub mod config;
 pub mod index;
 pub mod install;
+pub mod locale;
 pub mod metadata;
 pub mod models;
 pub mod ollama;
 
+pub use chunk::{split_chunks, ChunkEntry, ManpageChunk};
 pub use config::{
-    get_config_path, load_config, save_config, Config, IndexConfig, ModelsConfig, OllamaConfig,
+    get_config_path, load_config, save_config, Backend, Config, DistanceMetric, GenerationConfig,
+    IndexConfig, ModelOptions, ModelsConfig, OllamaConfig, OpenAiConfig, Quantization,
+    SearchConfig, VectorSettings,
+};
+pub use index::{
+    EmbeddingGenerator, ManpageAlias, ManpageContent, ManpageEntry, ManpageScanner, ScanResult,
 };
-pub use index::{EmbeddingGenerator, ManpageContent, ManpageEntry, ManpageScanner};
 pub use install::{
     detect_system, display_status, install_docker, install_native, start_ollama, wait_for_ollama,
     InstallResult, OllamaStatus, SystemCapabilities,
 };
-pub use metadata::IndexMetadata;
+pub use metadata::{file_records, FileRecord, IndexMetadata};
 pub use models::{
     display_embedding_model_selection, display_model_selection, display_preset_selection,
     get_available_embedding_models, get_available_models, get_system_ram_gb,
@@ -43,7 +52,7 @@ use crate::llm::OllamaClient;
 /// 3. Scan manpage directories
 /// 4. Extract descriptions
 /// 5. Generate embeddings
-/// 6. Store in `LanceDB`
+/// 6. Store in the `SQLite` index
 ///
 /// # Errors
 ///
@@ -205,6 +214,7 @@ pub async fn run_setup() -> Result<()> {
     );
 
     // Steps 3-6: Run indexing
+    let _lock = db::lock_index().context("Failed to lock index")?;
     let count = run_indexing().await?;
 
     println!("\n✓ Setup complete! Indexed {count} manpages");
@@ -222,7 +232,7 @@ pub async fn run_setup() -> Result<()> {
 /// 1. Scan manpage directories
 /// 2. Extract descriptions
 /// 3. Generate embeddings
-/// 4. Store in `LanceDB` (overwrites existing)
+/// 4. Upsert changed entries into the index
 ///
 /// # Errors
 ///
@@ -231,6 +241,7 @@ pub async fn run_update() -> Result<()> {
     println!("ulm update - Refreshing manpage index\n");
 
     // Run indexing steps
+    let _lock = db::lock_index().context("Failed to lock index")?;
     let count = run_indexing().await?;
 
     println!("\n✓ Update complete! Indexed {count} manpages");
@@ -238,10 +249,80 @@ pub async fn run_update() -> Result<()> {
         "  Database location: {}",
         db::get_database_path()?.display()
     );
+    if count > 0 {
+        print_index_size().await?;
+    }
 
     Ok(())
 }
 
+/// Prints the size of the index and the vectors of the configured model.
+///
+/// For quantized storage, the recall of the quantized search against an
+/// exact search is printed as well.
+async fn print_index_size() -> Result<()> {
+    let config = load_config().context("Failed to load config")?;
+    let db_path = db::get_database_path()?;
+    let file_size = std::fs::metadata(&db_path).map_or(0, |m| m.len());
+    println!("  Index size: {}", format_size(file_size));
+
+    let Some(storage) = db::vector_storage(config.embedding_model())
+        .await
+        .context("Failed to measure vector storage")?
+    else {
+        return Ok(());
+    };
+
+    if storage.quantization == Quantization::None {
+        println!(
+            "  Vectors: {} ({} float32)",
+            storage.vectors,
+            format_size(storage.search_bytes)
+        );
+    } else {
+        println!(
+            "  Vectors: {} ({} {} searched + {} float32 for rescoring)",
+            storage.vectors,
+            format_size(storage.search_bytes),
+            storage.quantization.as_str(),
+            format_size(storage.rescore_bytes)
+        );
+        println!(
+            "  Quantization adds {} to the index (float32 only: {})",
+            format_size(storage.search_bytes),
+            format_size(storage.rescore_bytes)
+        );
+    }
+    if let Some(recall) = storage.recall {
+        println!(
+            "  Recall of the {} search: {:.1}% of exact float32 nearest neighbors (sampled)",
+            storage.quantization.as_str(),
+            recall * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Formats a byte count with a binary unit (e.g., "1.5 MiB").
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
 /// Removes all ulm data (database and config).
 ///
 /// # Errors
@@ -252,20 +333,16 @@ pub fn run_clean() -> Result<()> {
 
     let mut removed = false;
 
-    // Remove database (now a single .db file instead of .lance directory)
-    let db_path = db::get_database_path()?;
-    if db_path.exists() {
-        std::fs::remove_file(&db_path)
-            .with_context(|| format!("Failed to remove database: {}", db_path.display()))?;
-        println!("✓ Removed database: {}", db_path.display());
+    // Remove the database with its WAL, lock and leftover build files
+    for path in db::database_files()? {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove database: {}", path.display()))?;
+        println!("✓ Removed database: {}", path.display());
         removed = true;
     }
 
-    // Remove metadata
-    let metadata_path = db_path
-        .parent()
-        .map(|p| p.join("index_metadata.json"))
-        .unwrap_or_default();
+    // Remove legacy metadata (hashes are now stored in the database)
+    let metadata_path = metadata::get_legacy_metadata_path()?;
     if metadata_path.exists() {
         std::fs::remove_file(&metadata_path)
             .with_context(|| format!("Failed to remove metadata: {}", metadata_path.display()))?;
@@ -293,56 +370,176 @@ pub fn run_clean() -> Result<()> {
 
 /// Runs the indexing steps (shared between setup and update).
 ///
+/// Callers must hold the index lock ([`db::lock_index`]).
+///
 /// Uses pipelined processing: extraction and embedding run in parallel.
-/// Supports incremental updates by tracking file hashes.
+/// Supports incremental updates by tracking file hashes: only new or changed
+/// manpages are re-embedded and upserted, and deleted manpages are removed
+/// from the index. Stored manpages without vectors for the configured
+/// embedding model or vector settings (e.g., after switching models or
+/// distance metrics) are embedded from their stored text. Pages reached through
+/// symlinks or `.so` redirects are stored as aliases of the page they point
+/// to. Returns the total number of entries in the index.
 ///
 /// # Errors
 ///
 /// Returns an error if any step fails.
 async fn run_indexing() -> Result<usize> {
+    let config = load_config().context("Failed to load config")?;
+
     // Step 3: Scan manpage directories
     println!("Scanning manpage directories...");
-    let scanner = ManpageScanner::new();
-    let all_paths = scanner
-        .scan_directories()
+    let scanner = ManpageScanner::new()
+        .with_sections(config.index.sections.clone())
+        .with_languages(config.index.languages.clone());
+    let ScanResult {
+        pages: all_paths,
+        aliases,
+    } = scanner
+        .scan()
         .context("Failed to scan manpage directories")?;
     let total_paths = all_paths.len();
-    println!("✓ Found {total_paths} manpages\n");
+    if aliases.is_empty() {
+        println!("✓ Found {total_paths} manpages\n");
+    } else {
+        println!(
+            "✓ Found {total_paths} manpages ({} aliases)\n",
+            aliases.len()
+        );
+    }
 
     if total_paths == 0 {
         println!("No manpages found. Check your MANPATH environment variable.");
         return Ok(0);
     }
 
+    // An incompatible index requires a full rebuild
+    let full_rebuild = !db::supports_incremental_update()
+        .await
+        .context("Failed to check index")?;
+
     // Load metadata and filter to changed files
-    let mut metadata = IndexMetadata::load().unwrap_or_default();
-    metadata.remove_deleted();
+    let embedding_model = config.embedding_model().to_string();
+    let vector_settings = config.index.vector_settings();
+    let mut metadata = if full_rebuild {
+        IndexMetadata::default()
+    } else {
+        let mut metadata = IndexMetadata::load()
+            .await
+            .context("Failed to load index metadata")?;
+
+        // Re-extract entries indexed before the full manpage text was stored
+        let missing_content = db::source_paths_without_content()
+            .await
+            .context("Failed to check stored manpage text")?;
+        metadata.forget(&missing_content);
+        metadata
+    };
 
-    let (paths_to_process, unchanged) = metadata.filter_changed(all_paths.clone());
+    let deleted = metadata.remove_deleted(&all_paths);
+    if !deleted.is_empty() {
+        let removed = db::delete_entries(&deleted)
+            .await
+            .context("Failed to remove deleted manpages from index")?;
+        println!("✓ Removed {removed} deleted manpages from index\n");
+    }
+
+    let (paths_to_process, unchanged) = metadata.filter_changed(all_paths);
     let to_process_count = paths_to_process.len();
+    let generator = EmbeddingGenerator::new().context("Failed to create embedding generator")?;
 
     if to_process_count == 0 {
-        println!("✓ All {unchanged} manpages unchanged - nothing to update\n");
-        return Ok(unchanged);
+        println!("✓ All {unchanged} manpages unchanged\n");
+    } else {
+        if unchanged > 0 {
+            println!("  {unchanged} unchanged, {to_process_count} to process\n");
+        }
+
+        // Steps 4-5: Extract and embed in pipeline (only changed files)
+        println!("Extracting and generating embeddings (pipelined)...");
+
+        let entries = generator
+            .generate_embeddings_pipelined(paths_to_process.clone())
+            .await
+            .context("Failed to generate embeddings")?;
+
+        let entry_count = entries.len();
+        let chunk_count: usize = entries.iter().map(|e| e.chunks.len()).sum();
+        println!("✓ Generated embeddings for {entry_count} manpages ({chunk_count} chunks)\n");
+        save_index_metadata(&entries)?;
+
+        // Step 6: Store entries and file hashes in one transaction
+        println!("Storing in database...");
+        let records = file_records(&paths_to_process, &embedding_model);
+        if full_rebuild {
+            db::create_index(&embedding_model, vector_settings, entries, records)
+                .await
+                .context("Failed to create database index")?;
+        } else {
+            db::upsert_entries(&embedding_model, vector_settings, entries, records)
+                .await
+                .context("Failed to update database index")?;
+        }
     }
 
-    if unchanged > 0 {
-        println!("  {unchanged} unchanged, {to_process_count} to process\n");
+    // Embed stored manpages that have no vectors for this model and settings yet
+    embed_stored_pages(&generator, &embedding_model, vector_settings).await?;
+
+    let total = db::count_entries()
+        .await
+        .context("Failed to count entries")?;
+
+    // Symlinked and redirecting pages point to their indexed target
+    if total > 0 {
+        db::replace_aliases(aliases)
+            .await
+            .context("Failed to store manpage aliases")?;
     }
 
-    // Steps 4-5: Extract and embed in pipeline (only changed files)
-    println!("Extracting and generating embeddings (pipelined)...");
+    info!(
+        updated = to_process_count,
+        total = total,
+        "Indexing complete"
+    );
+
+    Ok(total)
+}
 
-    let generator = EmbeddingGenerator::new().context("Failed to create embedding generator")?;
-    let entries = generator
-        .generate_embeddings_pipelined(paths_to_process.clone())
+/// Embeds stored manpages without vectors for `embedding_model` and `vector_settings`.
+///
+/// # Errors
+///
+/// Returns an error if embedding or storing the vectors fails.
+async fn embed_stored_pages(
+    generator: &EmbeddingGenerator,
+    embedding_model: &str,
+    vector_settings: VectorSettings,
+) -> Result<()> {
+    let missing = db::pages_without_vectors(embedding_model, vector_settings)
         .await
-        .context("Failed to generate embeddings")?;
+        .context("Failed to check stored vectors")?;
+    if !missing.is_empty() {
+        println!(
+            "Embedding {} stored manpages with '{embedding_model}'...",
+            missing.len()
+        );
+        let entries = generator
+            .generate_embeddings(missing)
+            .await
+            .context("Failed to generate embeddings")?;
+        save_index_metadata(&entries)?;
 
-    let entry_count = entries.len();
-    println!("✓ Generated {entry_count} embeddings\n");
+        let stored = db::store_vectors(embedding_model, vector_settings, entries)
+            .await
+            .context("Failed to store vectors")?;
+        println!("✓ Added '{embedding_model}' vectors for {stored} manpages\n");
+    }
 
-    // Update config with embedding dimension
+    Ok(())
+}
+
+/// Records the embedding model and dimension of new vectors in the config.
+fn save_index_metadata(entries: &[ManpageEntry]) -> Result<()> {
     if let Some(first_entry) = entries.first() {
         #[allow(clippy::cast_possible_truncation)]
         let dimension = first_entry.vector.len() as u32;
@@ -352,17 +549,18 @@ async fn run_indexing() -> Result<usize> {
         info!(dimension = dimension, model = %config.embedding_model(), "Saved index metadata to config");
     }
 
-    // Update metadata with processed files
-    metadata.update_hashes(&paths_to_process);
-    metadata.save().context("Failed to save metadata")?;
-
-    // Step 6: Store in LanceDB
-    println!("Storing in database...");
-    db::create_index(entries)
-        .await
-        .context("Failed to create database index")?;
+    Ok(())
+}
 
-    info!(count = entry_count, "Indexing complete");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(entry_count)
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
 }
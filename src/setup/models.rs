@@ -312,6 +312,18 @@ fn get_default_embedding_models() -> Vec<EmbeddingModel> {
     ]
 }
 
+/// Returns the embedding dimension of a recommended embedding model.
+///
+/// The `latest` tag is ignored (e.g., "all-minilm:latest" is "all-minilm").
+#[must_use]
+pub fn recommended_dimension(model: &str) -> Option<u32> {
+    let name = model.strip_suffix(":latest").unwrap_or(model);
+    get_default_embedding_models()
+        .into_iter()
+        .find(|candidate| candidate.name == name)
+        .map(|candidate| candidate.dimension)
+}
+
 /// Fetches available embedding models from Ollama and merges with recommended list.
 ///
 /// # Errors
@@ -636,8 +648,7 @@ pub async fn pull_model_with_progress(client: &OllamaClient, model_name: &str) -
             _ => {
                 // Download progress
                 if let (Some(total), Some(completed)) = (progress.total, progress.completed) {
-                    if total > 0 {
-                        let percent = completed * 100 / total;
+                    if let Some(percent) = (completed * 100).checked_div(total) {
                         pb.set_position(percent);
 
                         // Show new layer info
@@ -666,6 +677,13 @@ pub async fn pull_model_with_progress(client: &OllamaClient, model_name: &str) -
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_recommended_dimension() {
+        assert_eq!(recommended_dimension("mxbai-embed-large"), Some(1024));
+        assert_eq!(recommended_dimension("all-minilm:latest"), Some(384));
+        assert_eq!(recommended_dimension("all-minilm:l6-v2"), None);
+    }
+
     #[test]
     fn test_recommended_model_serialization() {
         let model = RecommendedModel {
@@ -19,8 +19,8 @@ pub fn display_error(error: &anyhow::Error) {
         let _ = writeln!(stderr, "  {}: {cause}", i + 1);
     }
 
-    // Add actionable guidance based on error message
-    let error_str = error.to_string().to_lowercase();
+    // Add actionable guidance based on the full error chain
+    let error_str = format!("{error:#}").to_lowercase();
     let guidance = get_guidance(&error_str);
 
     if !guidance.is_empty() {
@@ -36,6 +36,18 @@ pub fn display_error(error: &anyhow::Error) {
 fn get_guidance(error_str: &str) -> Vec<&'static str> {
     let mut suggestions = Vec::new();
 
+    // Index built by an incompatible version
+    if error_str.contains("rebuild required") {
+        suggestions.push("Rebuild the index: ulm update");
+        return suggestions;
+    }
+
+    // Embedding model switched in the config
+    if error_str.contains("no embeddings for") {
+        suggestions.push("Embed manpages with the configured model: ulm update");
+        return suggestions;
+    }
+
     // Ollama connection issues
     if error_str.contains("connection refused") || error_str.contains("connect error") {
         suggestions.push("Ensure Ollama is running: ollama serve");
@@ -104,6 +116,21 @@ mod tests {
         assert!(guidance.iter().any(|s| s.contains("--setup")));
     }
 
+    #[test]
+    fn test_get_guidance_rebuild_required() {
+        let guidance = get_guidance("failed to search database: index rebuild required");
+        assert_eq!(guidance, vec!["Rebuild the index: ulm update"]);
+    }
+
+    #[test]
+    fn test_get_guidance_missing_model_embeddings() {
+        let guidance = get_guidance("index has no embeddings for 'mxbai-embed-large'");
+        assert_eq!(
+            guidance,
+            vec!["Embed manpages with the configured model: ulm update"]
+        );
+    }
+
     #[test]
     fn test_get_guidance_clipboard() {
         let guidance = get_guidance("clipboard error");
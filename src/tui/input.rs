@@ -45,6 +45,12 @@ fn handle_key(app: &mut App, key: KeyEvent) -> Option<UserAction> {
             .selected_suggestion()
             .map(|s| UserAction::Copy(s.command.clone())),
 
+        // Reasoning panel
+        KeyCode::Char('r') => {
+            app.toggle_reasoning();
+            None
+        }
+
         // Abort
         KeyCode::Esc | KeyCode::Char('q') => Some(UserAction::Abort),
 
@@ -10,6 +10,7 @@ pub mod render;
 pub use error::display_error;
 
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::{Context, Result};
 use crossterm::event;
@@ -19,8 +20,23 @@ use crossterm::terminal::{
 };
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::UnboundedReceiver;
 
 use crate::llm::{CommandSuggestion, RiskLevel};
+use crate::query::QueryUpdate;
+
+/// Whether the TUI currently owns the terminal.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Checks if the TUI currently owns the terminal.
+///
+/// Log output must not be written to the terminal meanwhile, or it draws
+/// over the TUI.
+#[must_use]
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
 
 /// User action selected in the TUI.
 #[derive(Debug, Clone)]
@@ -41,6 +57,35 @@ pub enum UserAction {
 ///
 /// Returns an error if terminal operations fail.
 pub fn run_tui(suggestions: Vec<CommandSuggestion>) -> Result<UserAction> {
+    run_terminal(App::new(suggestions), None)
+}
+
+/// Runs the TUI while suggestions are still being generated.
+///
+/// Opens right away and adds each suggestion from `updates` as it arrives
+/// (see [`crate::query::stream_query`]). Returns the user's chosen action.
+///
+/// # Errors
+///
+/// Returns an error if terminal operations fail, or the query failed before
+/// any suggestion arrived.
+pub fn run_tui_streaming(updates: UnboundedReceiver<QueryUpdate>) -> Result<UserAction> {
+    run_terminal(App::loading(), Some(updates))
+}
+
+/// Runs the TUI, marking it active (see [`is_active`]) until it's closed.
+fn run_terminal(app: App, updates: Option<UnboundedReceiver<QueryUpdate>>) -> Result<UserAction> {
+    ACTIVE.store(true, Ordering::Relaxed);
+    let result = run_in_terminal(app, updates);
+    ACTIVE.store(false, Ordering::Relaxed);
+    result
+}
+
+/// Sets up the terminal, runs the event loop and restores the terminal.
+fn run_in_terminal(
+    mut app: App,
+    updates: Option<UnboundedReceiver<QueryUpdate>>,
+) -> Result<UserAction> {
     // Setup terminal
     enable_raw_mode().context("Failed to enable raw mode")?;
     let mut stdout = io::stdout();
@@ -49,11 +94,8 @@ pub fn run_tui(suggestions: Vec<CommandSuggestion>) -> Result<UserAction> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).context("Failed to create terminal")?;
 
-    // Create app state
-    let mut app = App::new(suggestions);
-
     // Main event loop
-    let result = run_event_loop(&mut terminal, &mut app);
+    let result = run_event_loop(&mut terminal, &mut app, updates);
 
     // Cleanup terminal
     disable_raw_mode().context("Failed to disable raw mode")?;
@@ -68,8 +110,16 @@ pub fn run_tui(suggestions: Vec<CommandSuggestion>) -> Result<UserAction> {
 fn run_event_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
+    mut updates: Option<UnboundedReceiver<QueryUpdate>>,
 ) -> Result<UserAction> {
     loop {
+        // Take in suggestions generated since the last frame
+        if let Some(receiver) = &mut updates {
+            if !receive_updates(app, receiver)? {
+                updates = None;
+            }
+        }
+
         // Render
         terminal
             .draw(|frame| render::render(frame, app))
@@ -86,6 +136,26 @@ fn run_event_loop(
     }
 }
 
+/// Applies all pending updates to the app.
+///
+/// Returns `false` once the sender is gone and no more updates will come.
+///
+/// # Errors
+///
+/// Returns the query error if the query failed before any suggestion arrived.
+fn receive_updates(app: &mut App, updates: &mut UnboundedReceiver<QueryUpdate>) -> Result<bool> {
+    loop {
+        match updates.try_recv() {
+            Ok(update) => app.apply_update(update)?,
+            Err(TryRecvError::Empty) => return Ok(true),
+            Err(TryRecvError::Disconnected) => {
+                app.finish_loading();
+                return Ok(false);
+            }
+        }
+    }
+}
+
 /// Application state for the TUI.
 #[derive(Debug)]
 pub struct App {
@@ -95,6 +165,14 @@ pub struct App {
     pub selected: usize,
     /// Status message to display (e.g., "Copied!").
     pub status_message: Option<String>,
+    /// Whether more suggestions are still being generated.
+    pub loading: bool,
+    /// Current step of the query while loading (e.g., "Searching manpages...").
+    pub progress: Option<String>,
+    /// Reasoning of a thinking model, received so far.
+    pub reasoning: String,
+    /// Whether the reasoning panel is open.
+    pub show_reasoning: bool,
 }
 
 impl App {
@@ -106,9 +184,55 @@ impl App {
             suggestions,
             selected: 0,
             status_message: None,
+            loading: false,
+            progress: None,
+            reasoning: String::new(),
+            show_reasoning: false,
         }
     }
 
+    /// Creates an empty App waiting for suggestions to be generated.
+    #[must_use]
+    pub fn loading() -> Self {
+        Self {
+            loading: true,
+            ..Self::new(Vec::new())
+        }
+    }
+
+    /// Applies a progress update of the query.
+    ///
+    /// # Errors
+    ///
+    /// Returns the query error if it failed before any suggestion arrived.
+    pub fn apply_update(&mut self, update: QueryUpdate) -> Result<()> {
+        match update {
+            QueryUpdate::Status(message) => self.progress = Some(message),
+            QueryUpdate::Suggestion(suggestion) => self.suggestions.push(suggestion),
+            QueryUpdate::Reasoning(text) => {
+                if self.reasoning.is_empty() {
+                    self.progress = Some("Thinking... (r shows the reasoning)".to_string());
+                }
+                self.reasoning.push_str(&text);
+            }
+            QueryUpdate::Done => self.finish_loading(),
+            QueryUpdate::Failed(err) => {
+                if self.suggestions.is_empty() {
+                    return Err(err);
+                }
+                self.finish_loading();
+                self.set_status(format!("Error: {err:#}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks generation as finished.
+    pub fn finish_loading(&mut self) {
+        self.loading = false;
+        self.progress = None;
+    }
+
     /// Moves selection to the previous item (with wrap-around).
     #[allow(clippy::missing_const_for_fn)]
     pub fn select_previous(&mut self) {
@@ -137,6 +261,11 @@ impl App {
         self.suggestions.get(self.selected)
     }
 
+    /// Opens or closes the reasoning panel, if there is reasoning to show.
+    pub fn toggle_reasoning(&mut self) {
+        self.show_reasoning = !self.show_reasoning && !self.reasoning.is_empty();
+    }
+
     /// Sets a status message.
     pub fn set_status(&mut self, message: String) {
         self.status_message = Some(message);
@@ -245,6 +374,67 @@ mod tests {
         assert!(app.selected_suggestion().is_none());
     }
 
+    #[test]
+    fn test_apply_updates() {
+        let mut app = App::loading();
+        assert!(app.loading);
+
+        app.apply_update(QueryUpdate::Status("Searching manpages...".to_string()))
+            .unwrap();
+        assert_eq!(app.progress.as_deref(), Some("Searching manpages..."));
+
+        for suggestion in test_suggestions() {
+            app.apply_update(QueryUpdate::Suggestion(suggestion))
+                .unwrap();
+        }
+        assert_eq!(app.suggestions.len(), 2);
+        assert_eq!(
+            app.selected_suggestion().map(|s| s.command.as_str()),
+            Some("ls -la")
+        );
+
+        app.apply_update(QueryUpdate::Done).unwrap();
+        assert!(!app.loading);
+        assert!(app.progress.is_none());
+    }
+
+    #[test]
+    fn test_reasoning_panel() {
+        let mut app = App::loading();
+        app.toggle_reasoning();
+        assert!(!app.show_reasoning, "nothing to show yet");
+
+        for text in ["The user wants ", "large files."] {
+            app.apply_update(QueryUpdate::Reasoning(text.to_string()))
+                .unwrap();
+        }
+        assert_eq!(app.reasoning, "The user wants large files.");
+        assert!(app.progress.as_deref().unwrap().starts_with("Thinking"));
+
+        app.toggle_reasoning();
+        assert!(app.show_reasoning);
+        app.toggle_reasoning();
+        assert!(!app.show_reasoning);
+    }
+
+    #[test]
+    fn test_apply_failed_update() {
+        let mut app = App::loading();
+        let err = app
+            .apply_update(QueryUpdate::Failed(anyhow::anyhow!("Index not found")))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Index not found");
+
+        // Suggestions already shown stay available
+        let mut app = App::loading();
+        app.apply_update(QueryUpdate::Suggestion(test_suggestions().remove(0)))
+            .unwrap();
+        app.apply_update(QueryUpdate::Failed(anyhow::anyhow!("timeout")))
+            .unwrap();
+        assert!(!app.loading);
+        assert_eq!(app.status_message.as_deref(), Some("Error: timeout"));
+    }
+
     #[test]
     fn test_risk_color() {
         assert_eq!(risk_color(&RiskLevel::Safe), ratatui::style::Color::Green);
@@ -12,23 +12,56 @@ use ratatui::Frame;
 use super::{risk_color, App};
 
 /// Renders the TUI to the given frame.
+///
+/// The reasoning panel, when open, takes the place of the explanation.
 pub fn render(frame: &mut Frame, app: &App) {
+    let details = if app.show_reasoning {
+        Constraint::Percentage(50) // Reasoning
+    } else {
+        Constraint::Length(5) // Explanation
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Min(6),    // Suggestions list
-            Constraint::Length(5), // Explanation
+            Constraint::Min(6), // Suggestions list
+            details,
             Constraint::Length(1), // Footer
         ])
         .split(frame.area());
 
     render_suggestions(frame, app, chunks[0]);
-    render_explanation(frame, app, chunks[1]);
+    if app.show_reasoning {
+        render_reasoning(frame, app, chunks[1]);
+    } else {
+        render_explanation(frame, app, chunks[1]);
+    }
     render_footer(frame, app, chunks[2]);
 }
 
 /// Renders the list of suggestions.
+///
+/// While suggestions are generated, the title says so; until the first one
+/// arrives, the current step of the query is shown instead of the list.
 fn render_suggestions(frame: &mut Frame, app: &App, area: Rect) {
+    let title = if app.loading {
+        " ulm - Command Suggestions (generating...) "
+    } else {
+        " ulm - Command Suggestions "
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+
+    if app.suggestions.is_empty() {
+        let message = if app.loading {
+            app.progress.as_deref().unwrap_or("Starting...")
+        } else {
+            "No suggestions found."
+        };
+        let paragraph = Paragraph::new(Span::styled(message, Style::default().fg(Color::DarkGray)))
+            .block(block);
+        frame.render_widget(paragraph, area);
+        return;
+    }
+
     let items: Vec<ListItem> = app
         .suggestions
         .iter()
@@ -88,11 +121,7 @@ fn render_suggestions(frame: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" ulm - Command Suggestions "),
-    );
+    let list = List::new(items).block(block);
 
     frame.render_widget(list, area);
 }
@@ -114,15 +143,40 @@ fn render_explanation(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(paragraph, area);
 }
 
+/// Renders the reasoning of a thinking model, scrolled to its end.
+fn render_reasoning(frame: &mut Frame, app: &App, area: Rect) {
+    // Estimate the wrapped height to keep the latest reasoning in view
+    let width = usize::from(area.width.saturating_sub(2)).max(1);
+    let lines: usize = app
+        .reasoning
+        .lines()
+        .map(|line| line.chars().count().div_ceil(width).max(1))
+        .sum();
+    let hidden = lines.saturating_sub(usize::from(area.height.saturating_sub(2)));
+
+    let paragraph = Paragraph::new(Text::from(app.reasoning.as_str()))
+        .style(Style::default().fg(Color::DarkGray))
+        .block(Block::default().borders(Borders::ALL).title(" Reasoning "))
+        .wrap(Wrap { trim: false })
+        .scroll((u16::try_from(hidden).unwrap_or(u16::MAX), 0));
+
+    frame.render_widget(paragraph, area);
+}
+
 /// Renders the footer with key bindings and status.
 fn render_footer(frame: &mut Frame, app: &App, area: Rect) {
     let footer_text = if let Some(status) = &app.status_message {
         Span::styled(status, Style::default().fg(Color::Green))
-    } else {
+    } else if app.reasoning.is_empty() {
         Span::styled(
             " Up/Down Navigate  Enter/A Execute  K Copy  Esc/q Quit ",
             Style::default().fg(Color::DarkGray),
         )
+    } else {
+        Span::styled(
+            " Up/Down Navigate  Enter/A Execute  K Copy  r Reasoning  Esc/q Quit ",
+            Style::default().fg(Color::DarkGray),
+        )
     };
 
     let paragraph = Paragraph::new(Line::from(footer_text));
@@ -1,5 +1,7 @@
 //! Integration tests for CLI argument parsing.
 
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use std::time::Duration;
 
 use assert_cmd::Command;
@@ -77,6 +79,22 @@ fn test_query_with_quotes() {
         .stderr(predicate::str::contains("ulm setup"));
 }
 
+#[test]
+fn test_query_with_seed() {
+    // The seed is parsed before the query, which fails without setup
+    ulm()
+        .args(["--seed", "42", "find", "large", "files"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ulm setup"));
+
+    ulm()
+        .args(["--seed", "abc", "find"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value"));
+}
+
 #[test]
 fn test_no_args_shows_help_message() {
     ulm()
@@ -115,3 +133,90 @@ fn test_version_short_flag() {
         .success()
         .stdout(predicate::str::contains("0.3.2"));
 }
+
+#[test]
+fn test_index_subcommand_help() {
+    ulm()
+        .args(["index", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("export"))
+        .stdout(predicate::str::contains("import"));
+}
+
+#[test]
+fn test_index_import_missing_bundle() {
+    // Keep the config and data directories away from the user's
+    let home = tempfile::TempDir::new().unwrap();
+
+    ulm()
+        .args(["index", "import", "/nonexistent/index.ulm"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_DATA_HOME", home.path().join("data"))
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_prompt_show_requires_query() {
+    ulm()
+        .args(["prompt", "show"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("<QUERY>"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_prompt_show_rejects_invalid_template() {
+    let config_home = tempfile::TempDir::new().unwrap();
+    let config_dir = config_home.path().join("ulm");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("prompt.toml"),
+        "system = \"Never use sudo.\"",
+    )
+    .unwrap();
+
+    ulm()
+        .args(["prompt", "show", "list files"])
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("{{format}}"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_clean_removes_index_companion_files() {
+    let home = tempfile::TempDir::new().unwrap();
+    let data_dir = home.path().join("data").join("ulm");
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let files = [
+        "index.db",
+        "index.db-wal",
+        "index.db-shm",
+        "index.db.lock",
+        "index.db.build",
+    ];
+    for name in files {
+        std::fs::write(data_dir.join(name), "").unwrap();
+    }
+    std::fs::write(data_dir.join("other.txt"), "").unwrap();
+
+    ulm()
+        .arg("clean")
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_DATA_HOME", home.path().join("data"))
+        .assert()
+        .success();
+
+    for name in files {
+        assert!(!data_dir.join(name).exists(), "{name} was left behind");
+    }
+    assert!(data_dir.join("other.txt").exists());
+}